@@ -10,11 +10,17 @@ fn test_string() {
             token: Token {
                 tk_type: TokenType::LET,
                 literal: String::from("let"),
+                span: Span::default(),
+                error: None,
+                trivia: Vec::new(),
             },
             name: Identifier {
                 token: Token {
                     tk_type: TokenType::IDENT,
                     literal: String::from("myVar"),
+                    span: Span::default(),
+                    error: None,
+                    trivia: Vec::new(),
                 },
                 value: String::from("myVar"),
             },
@@ -22,10 +28,14 @@ fn test_string() {
                 token: Token {
                     tk_type: TokenType::IDENT,
                     literal: String::from("anotherVar"),
+                    span: Span::default(),
+                    error: None,
+                    trivia: Vec::new(),
                 },
                 value: String::from("anotherVar"),
             }),
         })],
+        trailing: Vec::new(),
     };
 
     assert!(
@@ -3,37 +3,22 @@
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-#[macro_use]
-extern crate lazy_static;
-
-mod ast;
-#[macro_use]
-mod code;
-mod compiler;
-mod evaluator;
-mod lexer;
-mod object;
-mod parser;
-mod repl;
-mod token;
-mod vm;
-
-use ast::*;
-use compiler::*;
-use evaluator::*;
-use lexer::*;
-use object::*;
-use parser::*;
 use std::cell::*;
 use std::rc::*;
 use std::time::*;
-use vm::*;
+use wacir::ast::*;
+use wacir::compiler::*;
+use wacir::evaluator::*;
+use wacir::lexer::*;
+use wacir::object::*;
+use wacir::parser::*;
+use wacir::vm::*;
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     let engine: String;
-    if args.len() != 3 {
-        println!("use -engine 'vm' or 'eval'");
+    if args.len() != 3 && args.len() != 4 {
+        println!("use -engine 'vm' or 'eval' [fibonacci|array|hash|array-write|hash-write]");
         return;
     }
     match &args[1][..] {
@@ -50,20 +35,18 @@ fn main() {
         }
     }
 
-    let input = "
-    let fibonacci = fn(x) {
-        if (x == 0) { 
-            0
-        } else {
-            if (x == 1) {
-                return 1;
-            } else {
-                fibonacci(x - 1) + fibonacci(x - 2); 
-            }
-        } 
+    let workload = args.get(3).map(|x| &x[..]).unwrap_or("fibonacci");
+    let input = match workload {
+        "fibonacci" => FIBONACCI,
+        "array" => ARRAY,
+        "hash" => HASH,
+        "array-write" => ARRAY_WRITE,
+        "hash-write" => HASH_WRITE,
+        _ => {
+            println!("use 'fibonacci', 'array', 'hash', 'array-write' or 'hash-write'");
+            return;
+        }
     };
-    fibonacci(35);
-    ";
 
     let result: Object;
 
@@ -116,3 +99,125 @@ fn main() {
         Err(errors) => panic!("{:?}", errors),
     }
 }
+
+const FIBONACCI: &str = "
+    let fibonacci = fn(x) {
+        if (x == 0) { 
+            0
+        } else {
+            if (x == 1) {
+                return 1;
+            } else {
+                fibonacci(x - 1) + fibonacci(x - 2); 
+            }
+        } 
+    };
+    fibonacci(35);
+    ";
+
+// Builds a large array, then passes it around and indexes it repeatedly.
+const ARRAY: &str = "
+    let build = fn(n) {
+        let arr = [];
+        let i = 0;
+        while (i < n) {
+            arr = push(arr, i);
+            i += 1;
+        }
+        arr
+    };
+    let sum = fn(arr) {
+        let total = 0;
+        let i = 0;
+        while (i < len(arr)) {
+            total += arr[i];
+            i += 1;
+        }
+        total
+    };
+    let arr = build(10000);
+    let total = 0;
+    let round = 0;
+    while (round < 200) {
+        total += sum(arr);
+        round += 1;
+    }
+    total;
+    ";
+
+// Looks keys up in a hash that is passed to a function on every lookup.
+const HASH: &str = "
+    let h = {};
+    let i = 0;
+    while (i < 1000) {
+        h[i] = i * 2;
+        i += 1;
+    }
+    let get = fn(h, key) { h[key] };
+    let total = 0;
+    let round = 0;
+    while (round < 500) {
+        let j = 0;
+        while (j < 1000) {
+            total += get(h, j);
+            j += 1;
+        }
+        round += 1;
+    }
+    total;
+    ";
+
+// Overwrites every element of a 100x200 grid, round after round.
+const ARRAY_WRITE: &str = "
+    let row = fn(n) {
+        let arr = [];
+        let i = 0;
+        while (i < n) {
+            arr = push(arr, 0);
+            i += 1;
+        }
+        arr
+    };
+    let grid = [];
+    let i = 0;
+    while (i < 100) {
+        grid = push(grid, row(200));
+        i += 1;
+    }
+    let round = 0;
+    while (round < 50) {
+        let r = 0;
+        while (r < 100) {
+            let c = 0;
+            while (c < 200) {
+                grid[r][c] += c;
+                c += 1;
+            }
+            r += 1;
+        }
+        round += 1;
+    }
+    grid[99][199];
+    ";
+
+// Updates every entry of a hash that is shared with a function, round after
+// round.
+const HASH_WRITE: &str = "
+    let h = {};
+    let i = 0;
+    while (i < 1000) {
+        h[i] = 0;
+        i += 1;
+    }
+    let add = fn(h, key, n) { h[key] += n; };
+    let round = 0;
+    while (round < 500) {
+        let j = 0;
+        while (j < 1000) {
+            add(h, j, round);
+            j += 1;
+        }
+        round += 1;
+    }
+    h[999];
+    ";
@@ -0,0 +1,270 @@
+// src/bytecode/bytecode.rs
+
+// The .mbc file layout. All integers are big-endian, like instruction
+// operands.
+//
+//   magic            "MKBC"
+//   format version   u16
+//   opcode table     u32 fingerprint of the opcode names and operand widths
+//   instructions     u32 length, then the bytes of the main program
+//   source map       u32 count, then (offset, start, end, line, column) u32s
+//   constants        u32 count, then a tag byte and the value of each
+//                    (a function's name is an empty string when it has none)
+//   checksum         u32 CRC-32 of everything before it
+
+use super::error::*;
+use super::validator::*;
+use crate::code::*;
+use crate::compiler::*;
+use crate::evaluator::*;
+use crate::object::*;
+use crate::token::*;
+use std::cell::*;
+use std::convert::TryFrom;
+use std::rc::*;
+
+pub const MAGIC: &[u8; 4] = b"MKBC";
+pub const FORMAT_VERSION: u16 = 4;
+
+const TAG_INTEGER: u8 = 1;
+const TAG_FLOAT: u8 = 2;
+const TAG_STRING: u8 = 3;
+const TAG_FUNCTION: u8 = 4;
+
+pub fn serialize(bytecode: &Bytecode) -> Result<Vec<u8>, BytecodeError> {
+    let mut w = Writer { out: Vec::new() };
+    w.out.extend_from_slice(MAGIC);
+    w.out.extend_from_slice(&FORMAT_VERSION.to_be_bytes());
+    w.out
+        .extend_from_slice(&opcode_table_version().to_be_bytes());
+    w.write_instructions(&bytecode.instuctions)?;
+    w.write_source_map(&bytecode.source_map)?;
+
+    let constants = bytecode.constants.borrow();
+    w.write_len(constants.len())?;
+    for constant in constants.iter() {
+        match constant {
+            Object::Integer(Integer { value }) => {
+                w.out.push(TAG_INTEGER);
+                w.out.extend_from_slice(&value.to_be_bytes());
+            }
+            Object::Float(Float { value }) => {
+                w.out.push(TAG_FLOAT);
+                w.out.extend_from_slice(&value.to_bits().to_be_bytes());
+            }
+            Object::StringObj(StringObj { value }) => {
+                w.out.push(TAG_STRING);
+                w.write_string(value)?;
+            }
+            Object::CompiledFunction(function) => {
+                w.out.push(TAG_FUNCTION);
+                w.write_len(function.num_locals)?;
+                w.write_len(function.num_parameters)?;
+                w.write_instructions(&function.instructions)?;
+                w.write_source_map(&function.source_map)?;
+                w.write_string(function.name.as_deref().unwrap_or(""))?;
+            }
+            _ => {
+                return Err(BytecodeError::UnsupportedConstant(String::from(
+                    constant.get_type(),
+                )))
+            }
+        }
+    }
+
+    let checksum = crc32(&w.out);
+    w.out.extend_from_slice(&checksum.to_be_bytes());
+    Ok(w.out)
+}
+
+// Reads bytecode written by serialize and validates it against the default
+// builtins, so that the result can be run without the VM panicking.
+pub fn deserialize(data: &[u8]) -> Result<Bytecode, BytecodeError> {
+    if data.len() < MAGIC.len() || &data[..MAGIC.len()] != MAGIC {
+        return Err(BytecodeError::NotBytecode);
+    }
+    if data.len() < MAGIC.len() + 4 {
+        return Err(BytecodeError::Truncated);
+    }
+    let (body, checksum) = data.split_at(data.len() - 4);
+    let mut r = Reader {
+        data: body,
+        pos: MAGIC.len(),
+    };
+    let version = r.read_u16()?;
+    if version != FORMAT_VERSION {
+        return Err(BytecodeError::UnsupportedVersion(version));
+    }
+    if crc32(body).to_be_bytes() != checksum {
+        return Err(BytecodeError::ChecksumMismatch);
+    }
+    if r.read_u32()? != opcode_table_version() {
+        return Err(BytecodeError::OpcodeTableMismatch);
+    }
+
+    let instructions = r.read_instructions()?;
+    let source_map = r.read_source_map()?;
+    let count = r.read_len()?;
+    let mut constants = Vec::new();
+    for _ in 0..count {
+        let constant = match r.read_u8()? {
+            TAG_INTEGER => Object::Integer(Integer {
+                value: r.read_u64()? as i64,
+            }),
+            TAG_FLOAT => Object::Float(Float {
+                value: f64::from_bits(r.read_u64()?),
+            }),
+            TAG_STRING => Object::StringObj(StringObj {
+                value: r.read_string()?,
+            }),
+            TAG_FUNCTION => Object::CompiledFunction(Rc::new(CompiledFunction {
+                num_locals: r.read_len()?,
+                num_parameters: r.read_len()?,
+                instructions: r.read_instructions()?,
+                source_map: r.read_source_map()?,
+                name: Some(r.read_string()?).filter(|name| !name.is_empty()),
+            })),
+            tag => return Err(BytecodeError::InvalidConstantTag(tag)),
+        };
+        constants.push(constant);
+    }
+    if r.pos != body.len() {
+        return Err(BytecodeError::TrailingData);
+    }
+
+    let bytecode = Bytecode {
+        instuctions: instructions,
+        constants: Rc::new(RefCell::new(constants)),
+        source_map: source_map,
+    };
+    validate(&bytecode, &new_builtins())?;
+    Ok(bytecode)
+}
+
+// Identifies the instruction set, so that files compiled before an opcode was
+// added, removed or renumbered are rejected instead of misread.
+pub fn opcode_table_version() -> u32 {
+    let mut table = Vec::new();
+    for op in 0..DEFINITIONS.len() {
+        if let Ok(def) = lookup(op as u8) {
+            table.extend_from_slice(def.name.as_bytes());
+            table.extend(def.operand_widths.iter().map(|w| *w as u8));
+            table.push(0);
+        }
+    }
+    crc32(&table)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data.iter() {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+struct Writer {
+    out: Vec<u8>,
+}
+impl Writer {
+    fn write_len(&mut self, len: usize) -> Result<(), BytecodeError> {
+        let len = u32::try_from(len).map_err(|_| BytecodeError::TooLarge)?;
+        self.out.extend_from_slice(&len.to_be_bytes());
+        Ok(())
+    }
+
+    fn write_string(&mut self, value: &str) -> Result<(), BytecodeError> {
+        self.write_len(value.len())?;
+        self.out.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+
+    fn write_instructions(&mut self, ins: &Instructions) -> Result<(), BytecodeError> {
+        self.write_len(ins.0.len())?;
+        self.out.extend_from_slice(&ins.0);
+        Ok(())
+    }
+
+    fn write_source_map(&mut self, source_map: &SourceMap) -> Result<(), BytecodeError> {
+        self.write_len(source_map.0.len())?;
+        for (offset, span) in source_map.0.iter() {
+            self.write_len(*offset)?;
+            self.write_len(span.start)?;
+            self.write_len(span.end)?;
+            self.write_len(span.line)?;
+            self.write_len(span.column)?;
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], BytecodeError> {
+        if self.data.len() - self.pos < len {
+            return Err(BytecodeError::Truncated);
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, BytecodeError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, BytecodeError> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.read_bytes(2)?);
+        Ok(u16::from_be_bytes(bytes))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, BytecodeError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.read_bytes(4)?);
+        Ok(u32::from_be_bytes(bytes))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, BytecodeError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.read_bytes(8)?);
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    fn read_len(&mut self) -> Result<usize, BytecodeError> {
+        Ok(self.read_u32()? as usize)
+    }
+
+    fn read_string(&mut self) -> Result<String, BytecodeError> {
+        let len = self.read_len()?;
+        String::from_utf8(self.read_bytes(len)?.to_vec()).map_err(|_| BytecodeError::InvalidString)
+    }
+
+    fn read_instructions(&mut self) -> Result<Instructions, BytecodeError> {
+        let len = self.read_len()?;
+        Ok(Instructions(self.read_bytes(len)?.to_vec()))
+    }
+
+    fn read_source_map(&mut self) -> Result<SourceMap, BytecodeError> {
+        let count = self.read_len()?;
+        let mut source_map = SourceMap::new();
+        for _ in 0..count {
+            let offset = self.read_len()?;
+            let span = Span {
+                start: self.read_len()?,
+                end: self.read_len()?,
+                line: self.read_len()?,
+                column: self.read_len()?,
+            };
+            source_map.0.push((offset, span));
+        }
+        Ok(source_map)
+    }
+}
@@ -0,0 +1,258 @@
+// src/bytecode/bytecode_test.rs
+
+use crate::ast::*;
+use crate::bytecode::*;
+use crate::code::*;
+use crate::compiler::*;
+use crate::evaluator::*;
+use crate::lexer::*;
+use crate::object::*;
+use crate::parser::*;
+use crate::vm::*;
+use std::cell::*;
+use std::rc::*;
+
+fn compile(input: &str) -> Bytecode {
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l);
+    let program = p.parse_program().unwrap();
+    let mut comp = Compiler::new();
+    comp.compile(Node::Program(program)).unwrap();
+    comp.bytecode()
+}
+
+fn bytecode(instructions: Vec<Instructions>, constants: Vec<Object>) -> Bytecode {
+    Bytecode {
+        instuctions: Instructions(instructions.into_iter().flat_map(|x| x.0).collect()),
+        constants: Rc::new(RefCell::new(constants)),
+        source_map: SourceMap::new(),
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let input = "
+let counter = fn(start) {
+    let n = start;
+    fn() { n += 1; n }
+};
+let next = counter(1.5);
+next();
+let h = {\"key\": [1, -2, \"三\"]};
+let total = 0;
+while (total < 10) { total += h[\"key\"][0]; }
+[next(), total % 4, len(h[\"key\"][2])]
+";
+    let original = compile(input);
+    let data = serialize(&original).unwrap();
+    let loaded = deserialize(&data).unwrap();
+    assert!(
+        loaded.instuctions == original.instuctions
+            && *loaded.constants.borrow() == *original.constants.borrow()
+            && loaded.source_map == original.source_map,
+        "bytecode changed in the round trip"
+    );
+
+    let mut expected = Vm::new(compile(input));
+    expected.run().unwrap();
+    let mut vm = Vm::new(loaded);
+    vm.run().unwrap();
+    // Arrays only compare equal to themselves, so compare what they print.
+    let result = vm.last_popped_stack_elem.map(|x| x.inspect());
+    let expected = expected.last_popped_stack_elem.map(|x| x.inspect());
+    assert!(
+        result == expected,
+        "wrong result. expected={:?}, got={:?}",
+        expected,
+        result
+    );
+}
+
+#[test]
+fn test_load_errors() {
+    let data = serialize(&compile("puts(\"hello\")")).unwrap();
+
+    let mut version = data.clone();
+    version[5] = 9;
+    let mut corrupt = data.clone();
+    let last = corrupt.len() - 5;
+    corrupt[last] ^= 1;
+
+    let tests = vec![
+        (
+            b"#!/usr/bin/env monkey".to_vec(),
+            BytecodeError::NotBytecode,
+        ),
+        (version, BytecodeError::UnsupportedVersion(9)),
+        (corrupt, BytecodeError::ChecksumMismatch),
+    ];
+    for (input, expected) in tests {
+        match deserialize(&input) {
+            Err(err) => assert!(
+                err == expected,
+                "wrong error. expected={:?}, got={:?}",
+                expected,
+                err
+            ),
+            Ok(_) => panic!("expected {:?}", expected),
+        }
+    }
+
+    for len in 0..data.len() {
+        assert!(
+            deserialize(&data[..len]).is_err(),
+            "loaded a file truncated to {} bytes",
+            len
+        );
+    }
+}
+
+#[test]
+fn test_validate() {
+    let function = |instructions: Vec<Instructions>| {
+        Object::CompiledFunction(Rc::new(CompiledFunction {
+            instructions: Instructions(instructions.into_iter().flat_map(|x| x.0).collect()),
+            num_locals: 1,
+            num_parameters: 1,
+            source_map: SourceMap::new(),
+            name: None,
+        }))
+    };
+    let tests = vec![
+        (
+            bytecode(vec![Instructions(vec![255])], vec![]),
+            "invalid instruction at offset 0: opcode 255 undefined",
+        ),
+        (
+            bytecode(
+                vec![Instructions(vec![Opcode::OpConstant as u8, 0])],
+                vec![],
+            ),
+            "invalid instruction at offset 0: truncated operands",
+        ),
+        (
+            bytecode(vec![make(Opcode::OpConstant, &vec![5])], vec![]),
+            "invalid instruction at offset 0: constant index 5 out of range",
+        ),
+        (
+            bytecode(
+                vec![
+                    make(Opcode::OpTrue, &vec![]),
+                    make(Opcode::OpJump, &vec![2]),
+                ],
+                vec![],
+            ),
+            "invalid instruction at offset 1: jump target 2 is not an instruction",
+        ),
+        (
+            bytecode(
+                vec![make(Opcode::OpTrue, &vec![]), make(Opcode::OpAdd, &vec![])],
+                vec![],
+            ),
+            "invalid instruction at offset 1: stack underflow",
+        ),
+        (
+            bytecode(
+                vec![
+                    make(Opcode::OpTrue, &vec![]),
+                    make(Opcode::OpJumpNotTruthy, &vec![5]),
+                    make(Opcode::OpTrue, &vec![]),
+                    make(Opcode::OpPop, &vec![]),
+                ],
+                vec![],
+            ),
+            "invalid instruction at offset 5: stack underflow",
+        ),
+        (
+            bytecode(vec![make(Opcode::OpGetLocal, &vec![0])], vec![]),
+            "invalid instruction at offset 0: local index 0 out of range",
+        ),
+        (
+            bytecode(vec![make(Opcode::OpGetBuiltin, &vec![200])], vec![]),
+            "invalid instruction at offset 0: builtin index 200 out of range",
+        ),
+        (
+            bytecode(
+                vec![
+                    make(Opcode::OpNull, &vec![]),
+                    make(Opcode::OpTailCall, &vec![0]),
+                ],
+                vec![],
+            ),
+            "invalid instruction at offset 1: tail call outside a function",
+        ),
+        (
+            bytecode(
+                vec![make(Opcode::OpClosure, &vec![0, 0])],
+                vec![Object::Integer(Integer { value: 1 })],
+            ),
+            "invalid instruction at offset 0: constant 0 is a INTEGER, not a function",
+        ),
+        (
+            bytecode(
+                vec![make(Opcode::OpClosure, &vec![0, 0])],
+                vec![function(vec![
+                    make(Opcode::OpGetFree, &vec![0]),
+                    make(Opcode::OpReturnValue, &vec![]),
+                ])],
+            ),
+            "invalid instruction in constant 0 at offset 0: free variable index 0 out of range",
+        ),
+        (
+            bytecode(
+                vec![make(Opcode::OpClosure, &vec![0, 0])],
+                vec![function(vec![
+                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpSetIndex, &vec![0, 99]),
+                ])],
+            ),
+            "invalid instruction in constant 0 at offset 2: invalid assignment operator 99",
+        ),
+        (
+            bytecode(
+                vec![make(Opcode::OpClosure, &vec![0, 0])],
+                vec![function(vec![
+                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpSetIndex, &vec![1, 0]),
+                ])],
+            ),
+            "invalid instruction in constant 0 at offset 2: invalid assignment operator 0",
+        ),
+    ];
+
+    for (bytecode, expected) in tests {
+        match validate(&bytecode, &new_builtins()) {
+            Err(err) => assert!(
+                err.to_string() == expected,
+                "wrong error. expected={:?}, got={:?}",
+                expected,
+                err.to_string()
+            ),
+            Ok(_) => panic!("expected error {:?}", expected),
+        }
+        // deserialize runs the same checks.
+        let data = serialize(&bytecode).unwrap();
+        assert!(
+            deserialize(&data).is_err(),
+            "loaded invalid bytecode: {}",
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_corrupt_instructions() {
+    // No single corrupted byte may make loading panic.
+    let original =
+        compile("let f = fn(x) { if (x > 1) { [x, {\"a\": x}] } else { f(x + 1) } }; f(0)");
+    let constants = original.constants.borrow().clone();
+    for i in 0..original.instuctions.0.len() {
+        for value in 0..=255 {
+            let mut instructions = original.instuctions.clone();
+            instructions.0[i] = value;
+            let bytecode = bytecode(vec![instructions], constants.clone());
+            let data = serialize(&bytecode).unwrap();
+            let _ = deserialize(&data);
+        }
+    }
+}
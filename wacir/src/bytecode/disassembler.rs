@@ -0,0 +1,158 @@
+// src/bytecode/disassembler.rs
+
+use super::validator::*;
+use crate::code::*;
+use crate::compiler::*;
+use crate::evaluator::*;
+use crate::object::*;
+use std::collections::*;
+
+// Renders bytecode for reading: the main program, then every function
+// constant in the order it is created. Operands that refer to constants,
+// builtins or jump targets are annotated, and each instruction that starts a
+// new source line is followed by that line of source.
+pub fn disassemble(bytecode: &Bytecode, source: &str) -> String {
+    let constants = bytecode.constants.borrow();
+    let d = Disassembler {
+        constants: &constants,
+        builtins: new_builtins(),
+        lines: source.lines().collect(),
+    };
+
+    let mut out = String::from("== main ==\n");
+    d.chunk(&mut out, None, &bytecode.instuctions, &bytecode.source_map);
+
+    let mut printed = HashSet::new();
+    // Functions no OpClosure refers to still get printed, after the others.
+    let mut pending: Vec<usize> = constants
+        .iter()
+        .enumerate()
+        .rev()
+        .filter_map(|(i, c)| match c {
+            Object::CompiledFunction(_) => Some(i),
+            _ => None,
+        })
+        .collect();
+    pending.extend(closures(&bytecode.instuctions).into_iter().rev());
+    while let Some(index) = pending.pop() {
+        let f = match constants.get(index) {
+            Some(Object::CompiledFunction(f)) => f,
+            _ => continue,
+        };
+        if !printed.insert(index) {
+            continue;
+        }
+        let name = match &f.name {
+            Some(name) => format!(" {}", name),
+            None => String::new(),
+        };
+        out.push_str(&format!(
+            "\n== fn {}{} (parameters: {}, locals: {}) ==\n",
+            index, name, f.num_parameters, f.num_locals
+        ));
+        d.chunk(&mut out, Some(index), &f.instructions, &f.source_map);
+        pending.extend(closures(&f.instructions).into_iter().rev());
+    }
+    out
+}
+
+struct Disassembler<'a> {
+    constants: &'a [Object],
+    builtins: Builtins,
+    lines: Vec<&'a str>,
+}
+impl<'a> Disassembler<'a> {
+    fn chunk(
+        &self,
+        out: &mut String,
+        function: Option<usize>,
+        ins: &Instructions,
+        source_map: &SourceMap,
+    ) {
+        let decoded = match decode(function, ins) {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                out.push_str(&format!("ERROR: {}\n", err));
+                return;
+            }
+        };
+
+        let mut labels = BTreeMap::new();
+        for (_, op, operands) in decoded.iter() {
+            if let Some(target) = jump_target(op, operands) {
+                labels.insert(target, 0);
+            }
+        }
+        for (i, label) in labels.values_mut().enumerate() {
+            *label = i + 1;
+        }
+
+        let mut last_line = None;
+        for (offset, op, operands) in decoded.iter() {
+            if let Some(label) = labels.get(offset) {
+                out.push_str(&format!("L{}:\n", label));
+            }
+            let text = format!("{:04} {}", offset, self.instruction(op, operands, &labels));
+            match source_map.lookup(*offset) {
+                Some(span) if last_line != Some(span.line) => {
+                    last_line = Some(span.line);
+                    let line = match span.line.checked_sub(1) {
+                        Some(i) => self.lines.get(i).cloned().unwrap_or(""),
+                        None => "",
+                    };
+                    out.push_str(&format!("{:<40} {:>4}| {}\n", text, span.line, line.trim()));
+                }
+                _ => out.push_str(&format!("{}\n", text)),
+            }
+        }
+        if let Some(label) = labels.get(&ins.0.len()) {
+            out.push_str(&format!("L{}:\n", label));
+        }
+    }
+
+    fn instruction(
+        &self,
+        op: &Opcode,
+        operands: &[usize],
+        labels: &BTreeMap<usize, usize>,
+    ) -> String {
+        let mut text = String::from(DEFINITIONS[op].name);
+        for operand in operands.iter() {
+            text.push_str(&format!(" {}", operand));
+        }
+        let note = match op {
+            Opcode::OpConstant | Opcode::OpClosure => self.constant(operands[0]),
+            Opcode::OpGetBuiltin => self
+                .builtins
+                .get_by_index(operands[0])
+                .map(|b| b.name.clone()),
+            _ => jump_target(op, operands)
+                .and_then(|target| labels.get(&target))
+                .map(|label| format!("L{}", label)),
+        };
+        match note {
+            Some(note) => format!("{} ({})", text, note),
+            None => text,
+        }
+    }
+
+    fn constant(&self, index: usize) -> Option<String> {
+        match self.constants.get(index)? {
+            Object::StringObj(StringObj { value }) => Some(format!("{:?}", value)),
+            Object::CompiledFunction(_) => Some(format!("fn {}", index)),
+            constant => Some(constant.inspect()),
+        }
+    }
+}
+
+// The function constants ins creates closures of, in order.
+fn closures(ins: &Instructions) -> Vec<usize> {
+    match decode(None, ins) {
+        Ok(decoded) => decoded
+            .into_iter()
+            .filter(|(_, op, _)| *op == Opcode::OpClosure)
+            .map(|(_, _, operands)| operands[0])
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
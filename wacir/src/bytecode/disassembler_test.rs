@@ -0,0 +1,61 @@
+// src/bytecode/disassembler_test.rs
+
+use crate::ast::*;
+use crate::bytecode::*;
+use crate::compiler::*;
+use crate::lexer::*;
+use crate::parser::*;
+
+#[test]
+fn test_disassemble() {
+    let input = "let f = fn(x) {
+    if (x) { \"yes\" } else { len([]) }
+};
+let g = fn() { fn() { f } };
+f(true);
+";
+    let expected = "== main ==
+0000 OpClosure 1 0 (fn 1)                   1| let f = fn(x) {
+0004 OpSetGlobal 0
+0007 OpClosure 3 0 (fn 3)                   4| let g = fn() { fn() { f } };
+0011 OpSetGlobal 1
+0014 OpGetGlobal 0                          5| f(true);
+0017 OpTrue
+0018 OpCall 1
+0020 OpPop
+
+== fn 1 f (parameters: 1, locals: 1) ==
+0000 OpGetLocal 0                           2| if (x) { \"yes\" } else { len([]) }
+0002 OpJumpNotTruthy 11 (L1)
+0005 OpConstant 0 (\"yes\")
+0008 OpJump 18 (L2)
+L1:
+0011 OpGetBuiltin 0 (len)
+0013 OpArray 0
+0016 OpTailCall 1
+L2:
+0018 OpReturnValue
+
+== fn 3 g (parameters: 0, locals: 0) ==
+0000 OpClosure 2 0 (fn 2)                   4| let g = fn() { fn() { f } };
+0004 OpReturnValue
+
+== fn 2 (parameters: 0, locals: 0) ==
+0000 OpGetGlobal 0                          4| let g = fn() { fn() { f } };
+0003 OpReturnValue
+";
+
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l);
+    let program = p.parse_program().unwrap();
+    let mut comp = Compiler::new();
+    comp.compile(Node::Program(program)).unwrap();
+
+    let output = disassemble(&comp.bytecode(), input);
+    assert!(
+        output == expected,
+        "wrong disassembly.\nexpected:\n{}\ngot:\n{}",
+        expected,
+        output
+    );
+}
@@ -0,0 +1,57 @@
+// src/bytecode/error.rs
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BytecodeError {
+    UnsupportedConstant(String),
+    TooLarge,
+    NotBytecode,
+    UnsupportedVersion(u16),
+    OpcodeTableMismatch,
+    ChecksumMismatch,
+    Truncated,
+    TrailingData,
+    InvalidConstantTag(u8),
+    InvalidString,
+    InvalidInstruction {
+        function: Option<usize>, // constant index, or None for the main program
+        offset: usize,
+        reason: String,
+    },
+}
+impl fmt::Display for BytecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BytecodeError::UnsupportedConstant(t) => {
+                write!(f, "cannot serialize a constant of type {}", t)
+            }
+            BytecodeError::TooLarge => write!(f, "bytecode too large to serialize"),
+            BytecodeError::NotBytecode => write!(f, "not a Monkey bytecode file"),
+            BytecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported bytecode format version {}", version)
+            }
+            BytecodeError::OpcodeTableMismatch => {
+                write!(f, "bytecode was compiled for a different instruction set")
+            }
+            BytecodeError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            BytecodeError::Truncated => write!(f, "unexpected end of bytecode"),
+            BytecodeError::TrailingData => write!(f, "unexpected data after the constant pool"),
+            BytecodeError::InvalidConstantTag(tag) => write!(f, "invalid constant tag {}", tag),
+            BytecodeError::InvalidString => write!(f, "string is not valid UTF-8"),
+            BytecodeError::InvalidInstruction {
+                function,
+                offset,
+                reason,
+            } => match function {
+                Some(index) => write!(
+                    f,
+                    "invalid instruction in constant {} at offset {}: {}",
+                    index, offset, reason
+                ),
+                None => write!(f, "invalid instruction at offset {}: {}", offset, reason),
+            },
+        }
+    }
+}
+impl std::error::Error for BytecodeError {}
@@ -0,0 +1,13 @@
+mod bytecode;
+mod disassembler;
+mod error;
+mod validator;
+pub use bytecode::*;
+pub use disassembler::*;
+pub use error::*;
+pub use validator::*;
+
+#[cfg(test)]
+mod bytecode_test;
+#[cfg(test)]
+mod disassembler_test;
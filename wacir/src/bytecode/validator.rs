@@ -0,0 +1,275 @@
+// src/bytecode/validator.rs
+
+use super::error::*;
+use crate::code::*;
+use crate::compiler::*;
+use crate::object::*;
+use std::collections::*;
+use std::convert::TryInto;
+
+// Checks that bytecode from an untrusted source only does what compiled code
+// does: every opcode exists and has all of its operands, jumps land on
+// instructions, indices refer to existing constants, locals, free variables
+// and builtins, and no path pops more values than the frame has pushed.
+pub fn validate(bytecode: &Bytecode, builtins: &Builtins) -> Result<(), BytecodeError> {
+    let constants = bytecode.constants.borrow();
+
+    // A function only learns how many free variables it has from the
+    // OpClosure instructions that create it, so collect those first.
+    let mut num_free: HashMap<usize, usize> = HashMap::new();
+    let main = (None, &bytecode.instuctions);
+    let functions = constants.iter().enumerate().filter_map(|(i, c)| match c {
+        Object::CompiledFunction(f) => Some((Some(i), &f.instructions)),
+        _ => None,
+    });
+    for (function, ins) in std::iter::once(main).chain(functions) {
+        for (offset, op, operands) in decode(function, ins)? {
+            if op != Opcode::OpClosure {
+                continue;
+            }
+            let (index, count) = (operands[0], operands[1]);
+            match constants.get(index) {
+                Some(Object::CompiledFunction(_)) => {}
+                Some(c) => {
+                    return Err(invalid(
+                        function,
+                        offset,
+                        format!("constant {} is a {}, not a function", index, c.get_type()),
+                    ))
+                }
+                None => {
+                    return Err(invalid(
+                        function,
+                        offset,
+                        format!("constant index {} out of range", index),
+                    ))
+                }
+            }
+            if *num_free.entry(index).or_insert(count) != count {
+                return Err(invalid(
+                    function,
+                    offset,
+                    format!("closures of constant {} capture different counts", index),
+                ));
+            }
+        }
+    }
+
+    let check = Check {
+        constants: &constants,
+        num_builtins: builtins.len(),
+    };
+    check.function(None, &bytecode.instuctions, 0, 0)?;
+    for (i, constant) in constants.iter().enumerate() {
+        if let Object::CompiledFunction(f) = constant {
+            if f.num_parameters > f.num_locals {
+                return Err(invalid(
+                    Some(i),
+                    0,
+                    String::from("more parameters than locals"),
+                ));
+            }
+            let free = num_free.get(&i).cloned().unwrap_or(0);
+            check.function(Some(i), &f.instructions, f.num_locals, free)?;
+        }
+    }
+    Ok(())
+}
+
+struct Check<'a> {
+    constants: &'a [Object],
+    num_builtins: usize,
+}
+impl<'a> Check<'a> {
+    fn function(
+        &self,
+        function: Option<usize>,
+        ins: &Instructions,
+        num_locals: usize,
+        num_free: usize,
+    ) -> Result<(), BytecodeError> {
+        let decoded = decode(function, ins)?;
+        let mut at: HashMap<usize, usize> = HashMap::new();
+        for (i, (offset, _, _)) in decoded.iter().enumerate() {
+            at.insert(*offset, i);
+        }
+
+        for (offset, op, operands) in decoded.iter() {
+            let fail = |reason: String| Err(invalid(function, *offset, reason));
+            match op {
+                Opcode::OpConstant if operands[0] >= self.constants.len() => {
+                    return fail(format!("constant index {} out of range", operands[0]))
+                }
+                Opcode::OpGetLocal | Opcode::OpSetLocal | Opcode::OpCaptureLocal
+                    if operands[0] >= num_locals =>
+                {
+                    return fail(format!("local index {} out of range", operands[0]))
+                }
+                Opcode::OpGetFree | Opcode::OpSetFree | Opcode::OpCaptureFree
+                    if operands[0] >= num_free =>
+                {
+                    return fail(format!("free variable index {} out of range", operands[0]))
+                }
+                Opcode::OpGetBuiltin if operands[0] >= self.num_builtins => {
+                    return fail(format!("builtin index {} out of range", operands[0]))
+                }
+                Opcode::OpHash if operands[0] % 2 != 0 => {
+                    return fail(format!("odd number of hash elements {}", operands[0]))
+                }
+                Opcode::OpSetIndex if AssignOperator::decode(operands[1] as u8).is_none() => {
+                    return fail(format!("invalid assignment operator {}", operands[1]))
+                }
+                Opcode::OpTailCall if function.is_none() => {
+                    return fail(String::from("tail call outside a function"))
+                }
+                _ => {}
+            }
+            if let Some(target) = jump_target(op, operands) {
+                if target != ins.0.len() && !at.contains_key(&target) {
+                    return fail(format!("jump target {} is not an instruction", target));
+                }
+            }
+        }
+
+        // Walk every path through the code, tracking the fewest values the
+        // frame can have on the stack before each instruction.
+        let mut depth: Vec<Option<usize>> = vec![None; decoded.len()];
+        let mut work = Vec::new();
+        if !decoded.is_empty() {
+            depth[0] = Some(0);
+            work.push(0);
+        }
+        while let Some(i) = work.pop() {
+            let (offset, op, operands) = &decoded[i];
+            let d = depth[i].unwrap();
+            let (pops, pushes) = stack_effect(op, operands);
+            if d < pops {
+                return Err(invalid(function, *offset, String::from("stack underflow")));
+            }
+            let mut next = Vec::new();
+            match op {
+                Opcode::OpJump => next.push((operands[0], d)),
+                Opcode::OpReturnValue | Opcode::OpReturn => {}
+                Opcode::OpJumpNotTruthyOrPop | Opcode::OpJumpTruthyOrPop => {
+                    next.push((operands[0], d));
+                    next.push((offset + instruction_width(op), d - 1));
+                }
+                _ => {
+                    let after = d - pops + pushes;
+                    if let Some(target) = jump_target(op, operands) {
+                        next.push((target, after));
+                    }
+                    next.push((offset + instruction_width(op), after));
+                }
+            }
+            for (target, d) in next {
+                if let Some(&j) = at.get(&target) {
+                    if depth[j].map_or(true, |old| d < old) {
+                        depth[j] = Some(d);
+                        work.push(j);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Splits ins into (offset, opcode, operands) triples.
+pub(super) fn decode(
+    function: Option<usize>,
+    ins: &Instructions,
+) -> Result<Vec<(usize, Opcode, Vec<usize>)>, BytecodeError> {
+    let mut decoded = Vec::new();
+    let mut offset = 0;
+    while offset < ins.0.len() {
+        let def = match lookup(ins.0[offset]) {
+            Ok(def) => def,
+            Err(err) => return Err(invalid(function, offset, err)),
+        };
+        let width: usize = def.operand_widths.iter().sum();
+        if ins.0.len() - offset - 1 < width {
+            return Err(invalid(
+                function,
+                offset,
+                String::from("truncated operands"),
+            ));
+        }
+        let mut operands = Vec::new();
+        let mut pos = offset + 1;
+        for w in def.operand_widths.iter() {
+            operands.push(match w {
+                2 => read_u16(ins.0[pos..pos + 2].try_into().unwrap()) as usize,
+                _ => ins.0[pos] as usize,
+            });
+            pos += w;
+        }
+        decoded.push((offset, Opcode::from(ins.0[offset]), operands));
+        offset = pos;
+    }
+    Ok(decoded)
+}
+
+fn instruction_width(op: &Opcode) -> usize {
+    1 + DEFINITIONS[op].operand_widths.iter().sum::<usize>()
+}
+
+pub(super) fn jump_target(op: &Opcode, operands: &[usize]) -> Option<usize> {
+    match op {
+        Opcode::OpJump
+        | Opcode::OpJumpNotTruthy
+        | Opcode::OpJumpNotTruthyOrPop
+        | Opcode::OpJumpTruthyOrPop => Some(operands[0]),
+        _ => None,
+    }
+}
+
+// How many values op pops and then pushes.
+fn stack_effect(op: &Opcode, operands: &[usize]) -> (usize, usize) {
+    match op {
+        Opcode::OpConstant
+        | Opcode::OpTrue
+        | Opcode::OpFalse
+        | Opcode::OpNull
+        | Opcode::OpGetGlobal
+        | Opcode::OpGetLocal
+        | Opcode::OpGetBuiltin
+        | Opcode::OpGetFree
+        | Opcode::OpCaptureLocal
+        | Opcode::OpCaptureFree
+        | Opcode::OpCurrentClosure => (0, 1),
+        Opcode::OpAdd
+        | Opcode::OpSub
+        | Opcode::OpMul
+        | Opcode::OpDiv
+        | Opcode::OpMod
+        | Opcode::OpEqual
+        | Opcode::OpNotEqual
+        | Opcode::OpGreaterThan
+        | Opcode::OpLessThan
+        | Opcode::OpGreaterEqual
+        | Opcode::OpLessEqual
+        | Opcode::OpIndex => (2, 1),
+        Opcode::OpMinus | Opcode::OpBang => (1, 1),
+        Opcode::OpPop
+        | Opcode::OpJumpNotTruthy
+        | Opcode::OpSetGlobal
+        | Opcode::OpSetLocal
+        | Opcode::OpSetFree
+        | Opcode::OpReturnValue => (1, 0),
+        Opcode::OpJumpNotTruthyOrPop | Opcode::OpJumpTruthyOrPop => (1, 1),
+        Opcode::OpJump | Opcode::OpReturn => (0, 0),
+        Opcode::OpArray | Opcode::OpHash => (operands[0], 1),
+        Opcode::OpClosure => (operands[1], 1),
+        Opcode::OpCall | Opcode::OpTailCall => (operands[0] + 1, 1),
+        Opcode::OpSetIndex => (operands[0] + 2, 1),
+    }
+}
+
+fn invalid(function: Option<usize>, offset: usize, reason: String) -> BytecodeError {
+    BytecodeError::InvalidInstruction {
+        function: function,
+        offset: offset,
+        reason: reason,
+    }
+}
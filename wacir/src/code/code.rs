@@ -1,5 +1,6 @@
 // src/code.rs
 
+use crate::token::*;
 use std::collections::*;
 use std::convert::TryInto;
 
@@ -33,8 +34,8 @@ impl Instructions {
                     i += 1 + read;
                 }
                 Err(err) => {
-                    out.push_str(&format!("ERROR: {}", err));
-                    continue;
+                    out.push_str(&format!("ERROR: {}\n", err));
+                    break;
                 }
             }
         }
@@ -61,7 +62,35 @@ impl Instructions {
     }
 }
 
-#[derive(Debug, PartialEq, Hash, Eq, Clone)]
+// Maps instruction offsets back to the source span they were compiled from.
+// Entries are sorted by offset and only recorded when the span changes.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SourceMap(pub Vec<(usize, Span)>);
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap(Vec::new())
+    }
+    pub fn add(&mut self, offset: usize, span: Span) {
+        if let Some((_, last)) = self.0.last() {
+            if *last == span {
+                return;
+            }
+        }
+        self.0.push((offset, span));
+    }
+    pub fn truncate(&mut self, len: usize) {
+        self.0.retain(|(offset, _)| *offset < len);
+    }
+    pub fn lookup(&self, offset: usize) -> Option<Span> {
+        match self.0.binary_search_by_key(&offset, |(o, _)| *o) {
+            Ok(i) => Some(self.0[i].1),
+            Err(0) => None,
+            Err(i) => Some(self.0[i - 1].1),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Hash, Eq, Clone, Copy)]
 pub enum Opcode {
     OpConstant,
     OpAdd,
@@ -92,6 +121,18 @@ pub enum Opcode {
     OpGetBuiltin,
     OpClosure,
     OpGetFree,
+    OpCurrentClosure,
+    OpTailCall,
+    OpSetFree,
+    OpCaptureLocal,
+    OpCaptureFree,
+    OpSetIndex,
+    OpJumpNotTruthyOrPop,
+    OpJumpTruthyOrPop,
+    OpLessThan,
+    OpLessEqual,
+    OpGreaterEqual,
+    OpMod,
 }
 impl From<u8> for Opcode {
     fn from(v: u8) -> Self {
@@ -125,11 +166,70 @@ impl From<u8> for Opcode {
             26 => Opcode::OpGetBuiltin,
             27 => Opcode::OpClosure,
             28 => Opcode::OpGetFree,
+            29 => Opcode::OpCurrentClosure,
+            30 => Opcode::OpTailCall,
+            31 => Opcode::OpSetFree,
+            32 => Opcode::OpCaptureLocal,
+            33 => Opcode::OpCaptureFree,
+            34 => Opcode::OpSetIndex,
+            35 => Opcode::OpJumpNotTruthyOrPop,
+            36 => Opcode::OpJumpTruthyOrPop,
+            37 => Opcode::OpLessThan,
+            38 => Opcode::OpLessEqual,
+            39 => Opcode::OpGreaterEqual,
+            40 => Opcode::OpMod,
             _ => panic!("invalid Opcode"),
         }
     }
 }
 
+// Operator operand of OpSetIndex. It is numbered separately from the opcodes
+// and starts at 1, so plain `=` never shares a byte with an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignOperator {
+    Assign = 1,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+impl AssignOperator {
+    pub fn lookup(operator: &str) -> Option<AssignOperator> {
+        match operator {
+            "=" => Some(AssignOperator::Assign),
+            "+=" => Some(AssignOperator::Add),
+            "-=" => Some(AssignOperator::Sub),
+            "*=" => Some(AssignOperator::Mul),
+            "/=" => Some(AssignOperator::Div),
+            "%=" => Some(AssignOperator::Mod),
+            _ => None,
+        }
+    }
+    pub fn decode(byte: u8) -> Option<AssignOperator> {
+        match byte {
+            1 => Some(AssignOperator::Assign),
+            2 => Some(AssignOperator::Add),
+            3 => Some(AssignOperator::Sub),
+            4 => Some(AssignOperator::Mul),
+            5 => Some(AssignOperator::Div),
+            6 => Some(AssignOperator::Mod),
+            _ => None,
+        }
+    }
+    // The arithmetic a compound assignment applies to the old value.
+    pub fn opcode(self) -> Option<Opcode> {
+        match self {
+            AssignOperator::Assign => None,
+            AssignOperator::Add => Some(Opcode::OpAdd),
+            AssignOperator::Sub => Some(Opcode::OpSub),
+            AssignOperator::Mul => Some(Opcode::OpMul),
+            AssignOperator::Div => Some(Opcode::OpDiv),
+            AssignOperator::Mod => Some(Opcode::OpMod),
+        }
+    }
+}
+
 pub struct Definition<'a> {
     pub name: &'a str,
     pub operand_widths: Vec<usize>,
@@ -341,16 +441,106 @@ lazy_static! {
                 operand_widths: vec![1],
             },
         );
+        map.insert(
+            Opcode::OpCurrentClosure,
+            Definition {
+                name: "OpCurrentClosure",
+                operand_widths: Vec::new(),
+            },
+        );
+        map.insert(
+            Opcode::OpTailCall,
+            Definition {
+                name: "OpTailCall",
+                operand_widths: vec![1],
+            },
+        );
+        map.insert(
+            Opcode::OpSetFree,
+            Definition {
+                name: "OpSetFree",
+                operand_widths: vec![1],
+            },
+        );
+        map.insert(
+            Opcode::OpCaptureLocal,
+            Definition {
+                name: "OpCaptureLocal",
+                operand_widths: vec![1],
+            },
+        );
+        map.insert(
+            Opcode::OpCaptureFree,
+            Definition {
+                name: "OpCaptureFree",
+                operand_widths: vec![1],
+            },
+        );
+        // Operands are the number of indexes below the value on the stack and
+        // the AssignOperator.
+        map.insert(
+            Opcode::OpSetIndex,
+            Definition {
+                name: "OpSetIndex",
+                operand_widths: vec![1, 1],
+            },
+        );
+        // Short-circuit jumps for `&&` and `||`: jump and keep the operand on
+        // the stack when it decides the result, otherwise pop it.
+        map.insert(
+            Opcode::OpJumpNotTruthyOrPop,
+            Definition {
+                name: "OpJumpNotTruthyOrPop",
+                operand_widths: vec![2],
+            },
+        );
+        map.insert(
+            Opcode::OpJumpTruthyOrPop,
+            Definition {
+                name: "OpJumpTruthyOrPop",
+                operand_widths: vec![2],
+            },
+        );
+        map.insert(
+            Opcode::OpLessThan,
+            Definition {
+                name: "OpLessThan",
+                operand_widths: Vec::new(),
+            },
+        );
+        map.insert(
+            Opcode::OpLessEqual,
+            Definition {
+                name: "OpLessEqual",
+                operand_widths: Vec::new(),
+            },
+        );
+        map.insert(
+            Opcode::OpGreaterEqual,
+            Definition {
+                name: "OpGreaterEqual",
+                operand_widths: Vec::new(),
+            },
+        );
+        map.insert(
+            Opcode::OpMod,
+            Definition {
+                name: "OpMod",
+                operand_widths: Vec::new(),
+            },
+        );
         map
     };
 }
 
 pub fn lookup<'a>(op: u8) -> Result<&'a Definition<'a>, String> {
-    if let Some(def) = DEFINITIONS.get(&Opcode::from(op)) {
-        Ok(def)
-    } else {
-        Err(format!("opcode {} undefined", op))
+    // Opcodes are numbered from zero, and Opcode::from panics on other bytes.
+    if (op as usize) < DEFINITIONS.len() {
+        if let Some(def) = DEFINITIONS.get(&Opcode::from(op)) {
+            return Ok(def);
+        }
     }
+    Err(format!("opcode {} undefined", op))
 }
 
 pub fn make(op: Opcode, operands: &Vec<isize>) -> Instructions {
@@ -1,6 +1,7 @@
 // src/code_test.rs
 
 use super::code::*;
+use crate::token::*;
 
 #[test]
 fn test_make() {
@@ -21,6 +22,11 @@ fn test_make() {
             vec![65534, 255],
             vec![Opcode::OpClosure as u8, 255, 254, 255],
         ),
+        (
+            Opcode::OpSetIndex,
+            vec![2, AssignOperator::Add as isize],
+            vec![Opcode::OpSetIndex as u8, 2, AssignOperator::Add as u8],
+        ),
     ];
 
     for tt in tests.iter() {
@@ -103,3 +109,32 @@ fn test_read_operands() {
         }
     }
 }
+
+#[test]
+fn test_source_map_lookup() {
+    let span = |line| Span {
+        start: 0,
+        end: 0,
+        line: line,
+        column: 1,
+    };
+    let mut source_map = SourceMap::new();
+    source_map.add(0, span(1));
+    source_map.add(3, span(1));
+    source_map.add(4, span(2));
+    source_map.add(7, span(3));
+
+    assert!(source_map.0.len() == 3, "equal spans must be merged");
+
+    let tests = [(0, 1), (2, 1), (3, 1), (4, 2), (6, 2), (7, 3), (100, 3)];
+    for tt in tests.iter() {
+        let got = source_map.lookup(tt.0);
+        assert!(
+            got == Some(span(tt.1)),
+            "wrong span for offset {}. want line {}, got={:?}",
+            tt.0,
+            tt.1,
+            got
+        );
+    }
+}
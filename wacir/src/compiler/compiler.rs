@@ -1,11 +1,14 @@
 // src/compiler.rs
 
+use super::error::*;
 use super::symbol_table::*;
 use crate::ast::*;
 use crate::code::*;
 use crate::evaluator::*;
 use crate::object::*;
+use crate::token::*;
 use std::cell::*;
+use std::convert::TryInto;
 use std::rc::*;
 
 pub struct Compiler {
@@ -13,32 +16,52 @@ pub struct Compiler {
     pub symbol_table: Rc<RefCell<SymbolTable>>,
     pub scopes: Vec<CompilationScope>,
     pub scope_index: usize,
+    span: Span, // span of the node currently being compiled
 }
 
 impl Compiler {
     pub fn new() -> Compiler {
+        Compiler::new_with_builtins(&new_builtins())
+    }
+
+    pub fn new_with_builtins(builtins: &Builtins) -> Compiler {
         let mut symbol_table = SymbolTable::new();
-        for (i, v) in get_builtin_names().iter().enumerate() {
-            symbol_table.define_builtin(i, &v);
-        }
+        symbol_table.define_builtins(builtins);
         let constants = Rc::new(RefCell::new(Vec::new()));
         let symbol_table = Rc::new(RefCell::new(symbol_table));
         let main_scope = CompilationScope {
             instructions: Instructions::new(),
             last_instruction: None,
             previous_instruction: None,
+            source_map: SourceMap::new(),
+            loops: Vec::new(),
         };
         Compiler {
             constants: Rc::clone(&constants),
             symbol_table: Rc::clone(&symbol_table),
             scopes: vec![main_scope],
             scope_index: 0,
+            span: Span::default(),
         }
     }
 
-    pub fn compile(&mut self, node: Node) -> Result<(), String> {
+    pub fn compile(&mut self, node: Node) -> Result<(), CompileError> {
+        let saved_span = std::mem::replace(&mut self.span, node.span());
+        let result = self.compile_node(node);
+        self.span = saved_span;
+        result
+    }
+
+    fn error(&self, kind: CompileErrorKind) -> CompileError {
+        CompileError {
+            kind: kind,
+            span: self.span,
+        }
+    }
+
+    fn compile_node(&mut self, node: Node) -> Result<(), CompileError> {
         match node {
-            Node::Program(Program { mut statements }) => {
+            Node::Program(Program { mut statements, .. }) => {
                 for _ in 0..statements.len() {
                     self.compile(Node::Statement(statements.remove(0)))?;
                 }
@@ -56,10 +79,17 @@ impl Compiler {
                 operator,
                 right,
             })) => {
-                if operator == "<" {
-                    self.compile(Node::Expression(*right))?;
+                if operator == "&&" || operator == "||" {
                     self.compile(Node::Expression(*left))?;
-                    self.emit(Opcode::OpGreaterThan, Vec::new());
+                    let jump_op = if operator == "&&" {
+                        Opcode::OpJumpNotTruthyOrPop
+                    } else {
+                        Opcode::OpJumpTruthyOrPop
+                    };
+                    let jump_pos = self.emit(jump_op, vec![9999]);
+                    self.compile(Node::Expression(*right))?;
+                    let after_right_pos = self.current_instructions().0.len();
+                    self.change_operand(jump_pos, after_right_pos as isize);
                 } else {
                     self.compile(Node::Expression(*left))?;
                     self.compile(Node::Expression(*right))?;
@@ -76,16 +106,28 @@ impl Compiler {
                         "/" => {
                             self.emit(Opcode::OpDiv, Vec::new());
                         }
+                        "%" => {
+                            self.emit(Opcode::OpMod, Vec::new());
+                        }
                         ">" => {
                             self.emit(Opcode::OpGreaterThan, Vec::new());
                         }
+                        "<" => {
+                            self.emit(Opcode::OpLessThan, Vec::new());
+                        }
+                        ">=" => {
+                            self.emit(Opcode::OpGreaterEqual, Vec::new());
+                        }
+                        "<=" => {
+                            self.emit(Opcode::OpLessEqual, Vec::new());
+                        }
                         "==" => {
                             self.emit(Opcode::OpEqual, Vec::new());
                         }
                         "!=" => {
                             self.emit(Opcode::OpNotEqual, Vec::new());
                         }
-                        _ => return Err(format!("unknown operator {}", operator)),
+                        _ => return Err(self.error(CompileErrorKind::UnknownOperator(operator))),
                     }
                 }
             }
@@ -94,6 +136,11 @@ impl Compiler {
                 let v = vec![self.add_constant(Object::Integer(integer))];
                 self.emit(Opcode::OpConstant, v);
             }
+            Node::Expression(Expression::FloatLiteral(FloatLiteral { token: _, value })) => {
+                let float = Float { value: value };
+                let v = vec![self.add_constant(Object::Float(float))];
+                self.emit(Opcode::OpConstant, v);
+            }
             Node::Expression(Expression::BooleanLiteral(BooleanLiteral { token: _, value })) => {
                 if value {
                     self.emit(Opcode::OpTrue, Vec::new());
@@ -110,7 +157,7 @@ impl Compiler {
                 match &operator[..] {
                     "!" => self.emit(Opcode::OpBang, Vec::new()),
                     "-" => self.emit(Opcode::OpMinus, Vec::new()),
-                    _ => return Err(format!("unknown operator {}", operator)),
+                    _ => return Err(self.error(CompileErrorKind::UnknownOperator(operator))),
                 };
             }
             Node::Expression(Expression::IfExpression(IfExpression {
@@ -122,10 +169,7 @@ impl Compiler {
                 self.compile(Node::Expression(*condition))?;
                 let jump_not_truthy_pos = self.emit(Opcode::OpJumpNotTruthy, vec![9999]);
                 self.compile(Node::Statement(Statement::BlockStatement(consequence)))?;
-
-                if self.last_instruction_is(Opcode::OpPop) {
-                    self.remove_last_pop();
-                }
+                self.leave_block_value();
 
                 let jump_pos = self.emit(Opcode::OpJump, vec![9999]);
 
@@ -134,9 +178,7 @@ impl Compiler {
 
                 if let Some(a) = alternative {
                     self.compile(Node::Statement(Statement::BlockStatement(a)))?;
-                    if self.last_instruction_is(Opcode::OpPop) {
-                        self.remove_last_pop();
-                    }
+                    self.leave_block_value();
                 } else {
                     self.emit(Opcode::OpNull, Vec::new());
                 }
@@ -144,24 +186,123 @@ impl Compiler {
                 self.change_operand(jump_pos, after_alternative_pos as isize);
             }
             Node::Statement(Statement::BlockStatement(BlockStatement {
-                token: _,
-                mut statements,
+                mut statements, ..
             })) => {
                 for _ in 0..statements.len() {
                     self.compile(Node::Statement(statements.remove(0)))?;
                 }
             }
+            Node::Statement(Statement::WhileStatement(WhileStatement {
+                token: _,
+                condition,
+                body,
+            })) => {
+                let loop_start = self.current_instructions().0.len();
+                self.compile(Node::Expression(condition))?;
+                let jump_not_truthy_pos = self.emit(Opcode::OpJumpNotTruthy, vec![9999]);
+
+                self.scopes[self.scope_index].loops.push(LoopContext {
+                    start: loop_start,
+                    breaks: Vec::new(),
+                });
+                let result = self.compile(Node::Statement(Statement::BlockStatement(body)));
+                let context = self.scopes[self.scope_index].loops.pop().unwrap();
+                result?;
+
+                self.emit(Opcode::OpJump, vec![loop_start as isize]);
+
+                let after_loop_pos = self.current_instructions().0.len();
+                self.change_operand(jump_not_truthy_pos, after_loop_pos as isize);
+                for pos in context.breaks.into_iter() {
+                    self.change_operand(pos, after_loop_pos as isize);
+                }
+            }
+            Node::Statement(Statement::BreakStatement(_)) => {
+                let pos = self.emit(Opcode::OpJump, vec![9999]);
+                if let Some(context) = self.scopes[self.scope_index].loops.last_mut() {
+                    context.breaks.push(pos);
+                }
+            }
+            Node::Statement(Statement::ContinueStatement(_)) => {
+                if let Some(start) = self.scopes[self.scope_index].loops.last().map(|c| c.start) {
+                    self.emit(Opcode::OpJump, vec![start as isize]);
+                }
+            }
             Node::Statement(Statement::LetStatement(LetStatement {
                 token: _,
                 name,
                 value,
             })) => {
-                let symbol = self.symbol_table.borrow_mut().define(&name.value);
-                self.compile(Node::Expression(value))?;
-                if symbol.scope == SymbolScope::GlobalScope {
-                    self.emit(Opcode::OpSetGlobal, vec![symbol.index]);
+                // A function is bound before its body is compiled, so the
+                // body can read and assign the binding that holds it.
+                let symbol = match &value {
+                    Expression::FunctionLiteral(FunctionLiteral { name: Some(_), .. }) => {
+                        let symbol = self.symbol_table.borrow_mut().define(&name.value);
+                        self.compile(Node::Expression(value))?;
+                        symbol
+                    }
+                    _ => {
+                        self.compile(Node::Expression(value))?;
+                        self.symbol_table.borrow_mut().define(&name.value)
+                    }
+                };
+                self.store_symbol(&symbol);
+            }
+            Node::Expression(Expression::AssignExpression(AssignExpression {
+                token: _,
+                target,
+                operator,
+                value,
+            })) => {
+                let mut target = *target;
+                let mut indexes = Vec::new();
+                while let Expression::IndexExpression(IndexExpression { left, index, .. }) = target
+                {
+                    indexes.push(*index);
+                    target = *left;
+                }
+                indexes.reverse();
+
+                let name = match target {
+                    Expression::Identifier(ident) => ident.value,
+                    other => {
+                        return Err(self.error(CompileErrorKind::InvalidAssignment(other.string())))
+                    }
+                };
+                let symbol = match self.symbol_table.borrow_mut().resolve(&name) {
+                    Some(symbol) => symbol,
+                    None => return Err(self.error(CompileErrorKind::UndefinedVariable(name))),
+                };
+                match symbol.scope {
+                    SymbolScope::GlobalScope | SymbolScope::LocalScope | SymbolScope::FreeScope => {
+                    }
+                    _ => return Err(self.error(CompileErrorKind::InvalidAssignment(name))),
+                }
+                let assign = match AssignOperator::lookup(&operator) {
+                    Some(assign) => assign,
+                    None => return Err(self.error(CompileErrorKind::UnknownOperator(operator))),
+                };
+
+                if indexes.is_empty() {
+                    if let Some(op) = assign.opcode() {
+                        self.load_symbol(&symbol);
+                        self.compile(Node::Expression(*value))?;
+                        self.emit(op, Vec::new());
+                    } else {
+                        self.compile(Node::Expression(*value))?;
+                    }
+                    self.store_symbol(&symbol);
+                    self.load_symbol(&symbol);
                 } else {
-                    self.emit(Opcode::OpSetLocal, vec![symbol.index]);
+                    // OpSetIndex modifies the container in place and leaves
+                    // the assigned element on the stack.
+                    self.load_symbol(&symbol);
+                    let depth = indexes.len();
+                    for index in indexes.into_iter() {
+                        self.compile(Node::Expression(index))?;
+                    }
+                    self.compile(Node::Expression(*value))?;
+                    self.emit(Opcode::OpSetIndex, vec![depth as isize, assign as isize]);
                 }
             }
             Node::Expression(Expression::Identifier(Identifier { token: _, value })) => {
@@ -169,7 +310,7 @@ impl Compiler {
                 if let Some(symbol) = s {
                     self.load_symbol(&symbol);
                 } else {
-                    return Err(format!("undefined variable {}", value));
+                    return Err(self.error(CompileErrorKind::UndefinedVariable(value)));
                 };
             }
             Node::Expression(Expression::StringLiteral(StringLiteral { token: _, value })) => {
@@ -213,9 +354,14 @@ impl Compiler {
                 token: _,
                 parameters,
                 body,
+                name,
             })) => {
                 self.enter_scope();
 
+                if let Some(name) = &name {
+                    self.symbol_table.borrow_mut().define_function_name(name);
+                }
+
                 for p in parameters.iter() {
                     self.symbol_table.borrow_mut().define(&p.value);
                 }
@@ -228,21 +374,25 @@ impl Compiler {
                 if !self.last_instruction_is(Opcode::OpReturnValue) {
                     self.emit(Opcode::OpReturn, Vec::new());
                 }
+                mark_tail_calls(self.current_instructions());
 
                 let free_symbols = self.symbol_table.borrow().free_symbols.clone();
                 let num_locals = self.symbol_table.borrow().num_definitions;
+                let source_map = self.scopes[self.scope_index].source_map.clone();
                 let instructions = self.leave_scope();
 
                 for s in free_symbols.iter() {
-                    self.load_symbol(s);
+                    self.capture_symbol(s);
                 }
 
                 let compiled_fn = CompiledFunction {
                     instructions: instructions,
                     num_locals: num_locals,
                     num_parameters: parameters.len(),
+                    source_map: source_map,
+                    name: name,
                 };
-                let fn_index = self.add_constant(Object::CompiledFunction(compiled_fn));
+                let fn_index = self.add_constant(Object::CompiledFunction(Rc::new(compiled_fn)));
                 self.emit(
                     Opcode::OpClosure,
                     vec![fn_index, free_symbols.len() as isize],
@@ -275,6 +425,7 @@ impl Compiler {
         Bytecode {
             instuctions: self.current_instructions().clone(),
             constants: Rc::clone(&self.constants),
+            source_map: self.scopes[self.scope_index].source_map.clone(),
         }
     }
 
@@ -286,6 +437,7 @@ impl Compiler {
     pub fn emit(&mut self, op: Opcode, operands: Vec<isize>) -> usize {
         let ins = make(op.clone(), &operands);
         let pos = self.add_instruction(ins.0);
+        self.scopes[self.scope_index].source_map.add(pos, self.span);
 
         self.set_last_instruction(op, pos);
         pos
@@ -329,17 +481,30 @@ impl Compiler {
     fn remove_last_pop(&mut self) {
         let last = self.scopes[self.scope_index].last_instruction.clone();
         let previous = self.scopes[self.scope_index].previous_instruction.clone();
+        let last_position = last.unwrap().position; // TODO: can unwrap?
 
         let old_ins = self.current_instructions();
         let mut new_ins = Instructions::new();
-        new_ins
-            .0
-            .extend_from_slice(&old_ins.0[..(last.unwrap().position)]); // TODO: can unwrap?
+        new_ins.0.extend_from_slice(&old_ins.0[..last_position]);
 
         self.scopes[self.scope_index].instructions = new_ins;
+        self.scopes[self.scope_index]
+            .source_map
+            .truncate(last_position);
         self.scopes[self.scope_index].last_instruction = previous;
     }
 
+    // Makes a compiled block leave exactly one value on the stack: the value
+    // of its trailing expression statement, or null otherwise. Blocks that end
+    // by returning never fall through, so they need no value.
+    fn leave_block_value(&mut self) {
+        if self.last_instruction_is(Opcode::OpPop) {
+            self.remove_last_pop();
+        } else if !self.last_instruction_is(Opcode::OpReturnValue) {
+            self.emit(Opcode::OpNull, Vec::new());
+        }
+    }
+
     fn replace_instruction(&mut self, pos: usize, new_instruction: Instructions) {
         let ins = self.current_instructions();
         let length = new_instruction.0.len();
@@ -361,12 +526,15 @@ impl Compiler {
             instructions: Instructions::new(),
             last_instruction: None,
             previous_instruction: None,
+            source_map: SourceMap::new(),
+            loops: Vec::new(),
         };
         Compiler {
             constants: Rc::clone(&constants),
             symbol_table: Rc::clone(&s),
             scopes: vec![main_scope],
             scope_index: 0,
+            span: Span::default(),
         }
     }
 
@@ -379,6 +547,8 @@ impl Compiler {
             instructions: Instructions::new(),
             last_instruction: None,
             previous_instruction: None,
+            source_map: SourceMap::new(),
+            loops: Vec::new(),
         };
         self.scopes.push(scope);
         self.scope_index += 1;
@@ -421,6 +591,35 @@ impl Compiler {
         self.scopes[self.scope_index].last_instruction = Some(last);
     }
 
+    fn store_symbol(&mut self, s: &Symbol) {
+        match s.scope {
+            SymbolScope::GlobalScope => {
+                self.emit(Opcode::OpSetGlobal, vec![s.index as isize]);
+            }
+            SymbolScope::LocalScope => {
+                self.emit(Opcode::OpSetLocal, vec![s.index as isize]);
+            }
+            SymbolScope::FreeScope => {
+                self.emit(Opcode::OpSetFree, vec![s.index as isize]);
+            }
+            SymbolScope::BuiltinScope | SymbolScope::FunctionScope => {}
+        }
+    }
+
+    // Pushes the box of a variable that a new closure captures, so that the
+    // closure shares it with the enclosing function.
+    fn capture_symbol(&mut self, s: &Symbol) {
+        match s.scope {
+            SymbolScope::LocalScope => {
+                self.emit(Opcode::OpCaptureLocal, vec![s.index as isize]);
+            }
+            SymbolScope::FreeScope => {
+                self.emit(Opcode::OpCaptureFree, vec![s.index as isize]);
+            }
+            _ => self.load_symbol(s),
+        }
+    }
+
     fn load_symbol(&mut self, s: &Symbol) {
         match s.scope {
             SymbolScope::GlobalScope => {
@@ -435,13 +634,57 @@ impl Compiler {
             SymbolScope::FreeScope => {
                 self.emit(Opcode::OpGetFree, vec![s.index as isize]);
             }
+            SymbolScope::FunctionScope => {
+                self.emit(Opcode::OpCurrentClosure, Vec::new());
+            }
+        }
+    }
+}
+
+// Rewrites every OpCall whose result is returned unchanged into OpTailCall,
+// so the VM can reuse the caller's frame.
+fn mark_tail_calls(ins: &mut Instructions) {
+    let mut pos = 0;
+    while pos < ins.0.len() {
+        let op = Opcode::from(ins.0[pos]);
+        let len = instruction_len(ins, pos);
+        if op == Opcode::OpCall && returns_directly(ins, pos + len) {
+            ins.0[pos] = Opcode::OpTailCall as u8;
         }
+        pos += len;
+    }
+}
+
+// Reports whether execution starting at pos reaches OpReturnValue through
+// nothing but unconditional jumps.
+fn returns_directly(ins: &Instructions, mut pos: usize) -> bool {
+    for _ in 0..ins.0.len() {
+        if pos >= ins.0.len() {
+            return false;
+        }
+        match Opcode::from(ins.0[pos]) {
+            Opcode::OpReturnValue => return true,
+            Opcode::OpJump => {
+                let src = ins.0[(pos + 1)..(pos + 3)].try_into().expect("wrong size");
+                pos = read_u16(src) as usize;
+            }
+            _ => return false,
+        }
+    }
+    false
+}
+
+fn instruction_len(ins: &Instructions, pos: usize) -> usize {
+    match lookup(ins.0[pos]) {
+        Ok(def) => 1 + def.operand_widths.iter().sum::<usize>(),
+        Err(_) => 1,
     }
 }
 
 pub struct Bytecode {
     pub instuctions: Instructions,
     pub constants: Rc<RefCell<Vec<Object>>>,
+    pub source_map: SourceMap,
 }
 
 #[derive(Debug, Clone)]
@@ -455,4 +698,14 @@ pub struct CompilationScope {
     pub instructions: Instructions,
     pub last_instruction: Option<EmittedInstruction>,
     pub previous_instruction: Option<EmittedInstruction>,
+    pub source_map: SourceMap,
+    pub loops: Vec<LoopContext>,
+}
+
+// Jump targets of the innermost enclosing while loops. Break jumps are
+// emitted with a placeholder operand and patched once the loop is compiled.
+#[derive(Debug)]
+pub struct LoopContext {
+    pub start: usize,
+    pub breaks: Vec<usize>,
 }
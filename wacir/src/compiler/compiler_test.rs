@@ -79,6 +79,16 @@ fn test_integer_arithmetic() {
                 make(Opcode::OpPop, &Vec::new()),
             ],
         },
+        CompilerTestCase {
+            input: "2 % 1",
+            expected_constants: vec![ExpectedType::from(2i64), ExpectedType::from(1i64)],
+            expected_instructions: vec![
+                make(Opcode::OpConstant, &vec![0]),
+                make(Opcode::OpConstant, &vec![1]),
+                make(Opcode::OpMod, &Vec::new()),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
         CompilerTestCase {
             input: "2 / 1",
             expected_constants: vec![ExpectedType::from(2i64), ExpectedType::from(1i64)],
@@ -119,7 +129,7 @@ fn run_compiler_tests(tests: Vec<CompilerTestCase>) {
     }
 }
 
-fn parse(input: &str) -> Result<Program, Vec<String>> {
+fn parse(input: &str) -> Result<Program, Vec<ParseError>> {
     let l = Lexer::new(input);
     let mut p = Parser::new(l);
     return p.parse_program();
@@ -166,13 +176,8 @@ fn test_constants(expected: &Vec<ExpectedType>, actual: Rc<RefCell<Vec<Object>>>
             ExpectedType::I64val(iv) => test_integer_object(*iv as i64, &actual.borrow()[i]),
             ExpectedType::Sval(sv) => test_string_object(sv, &actual.borrow()[i]),
             ExpectedType::VecInstructions(expected_instructions) => {
-                if let Object::CompiledFunction(CompiledFunction {
-                    instructions,
-                    num_locals: _,
-                    num_parameters: _,
-                }) = &actual.borrow()[i]
-                {
-                    test_instructions(expected_instructions, &instructions);
+                if let Object::CompiledFunction(function) = &actual.borrow()[i] {
+                    test_instructions(expected_instructions, &function.instructions);
                 } else {
                     assert!(
                         false,
@@ -230,11 +235,31 @@ fn test_boolean_expressions() {
         },
         CompilerTestCase {
             input: "1 < 2",
-            expected_constants: vec![ExpectedType::from(2i64), ExpectedType::from(1i64)],
+            expected_constants: vec![ExpectedType::from(1i64), ExpectedType::from(2i64)],
             expected_instructions: vec![
                 make(Opcode::OpConstant, &vec![0]),
                 make(Opcode::OpConstant, &vec![1]),
-                make(Opcode::OpGreaterThan, &Vec::new()),
+                make(Opcode::OpLessThan, &Vec::new()),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+        CompilerTestCase {
+            input: "1 <= 2",
+            expected_constants: vec![ExpectedType::from(1i64), ExpectedType::from(2i64)],
+            expected_instructions: vec![
+                make(Opcode::OpConstant, &vec![0]),
+                make(Opcode::OpConstant, &vec![1]),
+                make(Opcode::OpLessEqual, &Vec::new()),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+        CompilerTestCase {
+            input: "1 >= 2",
+            expected_constants: vec![ExpectedType::from(1i64), ExpectedType::from(2i64)],
+            expected_instructions: vec![
+                make(Opcode::OpConstant, &vec![0]),
+                make(Opcode::OpConstant, &vec![1]),
+                make(Opcode::OpGreaterEqual, &Vec::new()),
                 make(Opcode::OpPop, &Vec::new()),
             ],
         },
@@ -292,6 +317,26 @@ fn test_boolean_expressions() {
     run_compiler_tests(tests);
 }
 
+#[test]
+fn test_logical_expressions() {
+    let tests = vec![CompilerTestCase {
+        input: "true && false; 1 || 2",
+        expected_constants: vec![ExpectedType::from(1i64), ExpectedType::from(2i64)],
+        expected_instructions: vec![
+            make(Opcode::OpTrue, &Vec::new()),
+            make(Opcode::OpJumpNotTruthyOrPop, &vec![5]),
+            make(Opcode::OpFalse, &Vec::new()),
+            make(Opcode::OpPop, &Vec::new()),
+            make(Opcode::OpConstant, &vec![0]),
+            make(Opcode::OpJumpTruthyOrPop, &vec![15]),
+            make(Opcode::OpConstant, &vec![1]),
+            make(Opcode::OpPop, &Vec::new()),
+        ],
+    }];
+
+    run_compiler_tests(tests);
+}
+
 #[test]
 fn test_conditionals() {
     let tests = vec![
@@ -327,6 +372,70 @@ fn test_conditionals() {
                 make(Opcode::OpPop, &Vec::new()),
             ],
         },
+        CompilerTestCase {
+            input: "if (true) { let a = 1; }",
+            expected_constants: vec![ExpectedType::from(1i64)],
+            expected_instructions: vec![
+                make(Opcode::OpTrue, &Vec::new()),
+                make(Opcode::OpJumpNotTruthy, &vec![14]),
+                make(Opcode::OpConstant, &vec![0]),
+                make(Opcode::OpSetGlobal, &vec![0]),
+                make(Opcode::OpNull, &Vec::new()),
+                make(Opcode::OpJump, &vec![15]),
+                make(Opcode::OpNull, &Vec::new()),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+    ];
+
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_while_loops() {
+    let tests = vec![
+        CompilerTestCase {
+            input: "while (true) { 1 }; 2;",
+            expected_constants: vec![ExpectedType::from(1i64), ExpectedType::from(2i64)],
+            expected_instructions: vec![
+                make(Opcode::OpTrue, &Vec::new()),
+                make(Opcode::OpJumpNotTruthy, &vec![11]),
+                make(Opcode::OpConstant, &vec![0]),
+                make(Opcode::OpPop, &Vec::new()),
+                make(Opcode::OpJump, &vec![0]),
+                make(Opcode::OpConstant, &vec![1]),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+        CompilerTestCase {
+            input: "while (true) { break; continue; 1 }",
+            expected_constants: vec![ExpectedType::from(1i64)],
+            expected_instructions: vec![
+                make(Opcode::OpTrue, &Vec::new()),
+                make(Opcode::OpJumpNotTruthy, &vec![17]),
+                make(Opcode::OpJump, &vec![17]),
+                make(Opcode::OpJump, &vec![0]),
+                make(Opcode::OpConstant, &vec![0]),
+                make(Opcode::OpPop, &Vec::new()),
+                make(Opcode::OpJump, &vec![0]),
+            ],
+        },
+        CompilerTestCase {
+            input: "1; while (false) { while (true) { break; } break; }",
+            expected_constants: vec![ExpectedType::from(1i64)],
+            expected_instructions: vec![
+                make(Opcode::OpConstant, &vec![0]),
+                make(Opcode::OpPop, &Vec::new()),
+                make(Opcode::OpFalse, &Vec::new()),
+                make(Opcode::OpJumpNotTruthy, &vec![24]),
+                make(Opcode::OpTrue, &Vec::new()),
+                make(Opcode::OpJumpNotTruthy, &vec![18]),
+                make(Opcode::OpJump, &vec![18]),
+                make(Opcode::OpJump, &vec![8]),
+                make(Opcode::OpJump, &vec![24]),
+                make(Opcode::OpJump, &vec![4]),
+            ],
+        },
     ];
 
     run_compiler_tests(tests);
@@ -950,7 +1059,7 @@ fn test_builltins() {
             expected_constants: vec![ExpectedType::from(vec![
                 make(Opcode::OpGetBuiltin, &vec![0]),
                 make(Opcode::OpArray, &vec![0]),
-                make(Opcode::OpCall, &vec![1]),
+                make(Opcode::OpTailCall, &vec![1]),
                 make(Opcode::OpReturnValue, &Vec::new()),
             ])],
             expected_instructions: vec![
@@ -998,7 +1107,7 @@ fn test_closure() {
                     make(Opcode::OpReturnValue, &Vec::new()),
                 ]),
                 ExpectedType::from(vec![
-                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpCaptureLocal, &vec![0]),
                     make(Opcode::OpClosure, &vec![0, 1]),
                     make(Opcode::OpReturnValue, &Vec::new()),
                 ]),
@@ -1027,13 +1136,13 @@ fn test_closure() {
                     make(Opcode::OpReturnValue, &Vec::new()),
                 ]),
                 ExpectedType::from(vec![
-                    make(Opcode::OpGetFree, &vec![0]),
-                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpCaptureFree, &vec![0]),
+                    make(Opcode::OpCaptureLocal, &vec![0]),
                     make(Opcode::OpClosure, &vec![0, 2]),
                     make(Opcode::OpReturnValue, &Vec::new()),
                 ]),
                 ExpectedType::from(vec![
-                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpCaptureLocal, &vec![0]),
                     make(Opcode::OpClosure, &vec![1, 1]),
                     make(Opcode::OpReturnValue, &Vec::new()),
                 ]),
@@ -1076,15 +1185,15 @@ fn test_closure() {
                 ExpectedType::from(vec![
                     make(Opcode::OpConstant, &vec![2]),
                     make(Opcode::OpSetLocal, &vec![0]),
-                    make(Opcode::OpGetFree, &vec![0]),
-                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpCaptureFree, &vec![0]),
+                    make(Opcode::OpCaptureLocal, &vec![0]),
                     make(Opcode::OpClosure, &vec![4, 2]),
                     make(Opcode::OpReturnValue, &Vec::new()),
                 ]),
                 ExpectedType::from(vec![
                     make(Opcode::OpConstant, &vec![1]),
                     make(Opcode::OpSetLocal, &vec![0]),
-                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpCaptureLocal, &vec![0]),
                     make(Opcode::OpClosure, &vec![5, 1]),
                     make(Opcode::OpReturnValue, &Vec::new()),
                 ]),
@@ -1099,3 +1208,272 @@ fn test_closure() {
     ];
     run_compiler_tests(tests);
 }
+
+#[test]
+fn test_assignments() {
+    let tests = vec![
+        CompilerTestCase {
+            input: "let a = 1; a = 2;",
+            expected_constants: vec![ExpectedType::from(1i64), ExpectedType::from(2i64)],
+            expected_instructions: vec![
+                make(Opcode::OpConstant, &vec![0]),
+                make(Opcode::OpSetGlobal, &vec![0]),
+                make(Opcode::OpConstant, &vec![1]),
+                make(Opcode::OpSetGlobal, &vec![0]),
+                make(Opcode::OpGetGlobal, &vec![0]),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+        CompilerTestCase {
+            input: "let a = 1; let a = a + 1;",
+            expected_constants: vec![ExpectedType::from(1i64), ExpectedType::from(1i64)],
+            expected_instructions: vec![
+                make(Opcode::OpConstant, &vec![0]),
+                make(Opcode::OpSetGlobal, &vec![0]),
+                make(Opcode::OpGetGlobal, &vec![0]),
+                make(Opcode::OpConstant, &vec![1]),
+                make(Opcode::OpAdd, &Vec::new()),
+                make(Opcode::OpSetGlobal, &vec![1]),
+            ],
+        },
+        CompilerTestCase {
+            input: "fn(a) { a *= 2 }",
+            expected_constants: vec![
+                ExpectedType::from(2i64),
+                ExpectedType::from(vec![
+                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpConstant, &vec![0]),
+                    make(Opcode::OpMul, &Vec::new()),
+                    make(Opcode::OpSetLocal, &vec![0]),
+                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpReturnValue, &Vec::new()),
+                ]),
+            ],
+            expected_instructions: vec![
+                make(Opcode::OpClosure, &vec![1, 0]),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+        CompilerTestCase {
+            input: "fn(a) { fn() { a = 1 } }",
+            expected_constants: vec![
+                ExpectedType::from(1i64),
+                ExpectedType::from(vec![
+                    make(Opcode::OpConstant, &vec![0]),
+                    make(Opcode::OpSetFree, &vec![0]),
+                    make(Opcode::OpGetFree, &vec![0]),
+                    make(Opcode::OpReturnValue, &Vec::new()),
+                ]),
+                ExpectedType::from(vec![
+                    make(Opcode::OpCaptureLocal, &vec![0]),
+                    make(Opcode::OpClosure, &vec![1, 1]),
+                    make(Opcode::OpReturnValue, &Vec::new()),
+                ]),
+            ],
+            expected_instructions: vec![
+                make(Opcode::OpClosure, &vec![2, 0]),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+        CompilerTestCase {
+            input: "let a = [[1]]; a[0][0] -= 2;",
+            expected_constants: vec![
+                ExpectedType::from(1i64),
+                ExpectedType::from(0i64),
+                ExpectedType::from(0i64),
+                ExpectedType::from(2i64),
+            ],
+            expected_instructions: vec![
+                make(Opcode::OpConstant, &vec![0]),
+                make(Opcode::OpArray, &vec![1]),
+                make(Opcode::OpArray, &vec![1]),
+                make(Opcode::OpSetGlobal, &vec![0]),
+                make(Opcode::OpGetGlobal, &vec![0]),
+                make(Opcode::OpConstant, &vec![1]),
+                make(Opcode::OpConstant, &vec![2]),
+                make(Opcode::OpConstant, &vec![3]),
+                make(Opcode::OpSetIndex, &vec![2, AssignOperator::Sub as isize]),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+    ];
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_compile_errors() {
+    let program = parse("let a = 1;\nfn() { a + b }").unwrap();
+    let mut compiler = Compiler::new();
+    match compiler.compile(Node::Program(program)) {
+        Ok(_) => panic!("expected compile error"),
+        Err(err) => {
+            assert!(
+                err.kind == CompileErrorKind::UndefinedVariable(String::from("b")),
+                "wrong error kind. got={:?}",
+                err.kind
+            );
+            assert!(
+                err.to_string() == "2:12: undefined variable b",
+                "wrong error. got={}",
+                err
+            );
+        }
+    }
+
+    let tests = [
+        ("b = 1", "1:3: undefined variable b"),
+        ("let x = x;", "1:9: undefined variable x"),
+        ("len = 1", "1:5: cannot assign to len"),
+    ];
+    for tt in tests.iter() {
+        let program = parse(tt.0).unwrap();
+        let mut compiler = Compiler::new();
+        match compiler.compile(Node::Program(program)) {
+            Ok(_) => panic!("expected compile error for {}", tt.0),
+            Err(err) => assert!(
+                err.to_string() == tt.1,
+                "wrong error. expected={}, got={}",
+                tt.1,
+                err
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_recursive_functions() {
+    let tests = vec![
+        CompilerTestCase {
+            input: "
+            let countDown = fn(x) { countDown(x - 1); };
+            countDown(1);
+            ",
+            expected_constants: vec![
+                ExpectedType::from(1i64),
+                ExpectedType::from(vec![
+                    make(Opcode::OpGetGlobal, &vec![0]),
+                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpConstant, &vec![0]),
+                    make(Opcode::OpSub, &Vec::new()),
+                    make(Opcode::OpTailCall, &vec![1]),
+                    make(Opcode::OpReturnValue, &Vec::new()),
+                ]),
+                ExpectedType::from(1i64),
+            ],
+            expected_instructions: vec![
+                make(Opcode::OpClosure, &vec![1, 0]),
+                make(Opcode::OpSetGlobal, &vec![0]),
+                make(Opcode::OpGetGlobal, &vec![0]),
+                make(Opcode::OpConstant, &vec![2]),
+                make(Opcode::OpCall, &vec![1]),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+        CompilerTestCase {
+            input: "
+            let wrapper = fn() {
+                let countDown = fn(x) { countDown(x - 1); };
+                countDown(1);
+            };
+            wrapper();
+            ",
+            expected_constants: vec![
+                ExpectedType::from(1i64),
+                ExpectedType::from(vec![
+                    make(Opcode::OpGetFree, &vec![0]),
+                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpConstant, &vec![0]),
+                    make(Opcode::OpSub, &Vec::new()),
+                    make(Opcode::OpTailCall, &vec![1]),
+                    make(Opcode::OpReturnValue, &Vec::new()),
+                ]),
+                ExpectedType::from(1i64),
+                ExpectedType::from(vec![
+                    make(Opcode::OpCaptureLocal, &vec![0]),
+                    make(Opcode::OpClosure, &vec![1, 1]),
+                    make(Opcode::OpSetLocal, &vec![0]),
+                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpConstant, &vec![2]),
+                    make(Opcode::OpTailCall, &vec![1]),
+                    make(Opcode::OpReturnValue, &Vec::new()),
+                ]),
+            ],
+            expected_instructions: vec![
+                make(Opcode::OpClosure, &vec![3, 0]),
+                make(Opcode::OpSetGlobal, &vec![0]),
+                make(Opcode::OpGetGlobal, &vec![0]),
+                make(Opcode::OpCall, &vec![0]),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+    ];
+    run_compiler_tests(tests);
+}
+
+#[test]
+fn test_tail_calls() {
+    let tests = vec![
+        CompilerTestCase {
+            input: "fn(f) { f() + 1 }",
+            expected_constants: vec![
+                ExpectedType::from(1i64),
+                ExpectedType::from(vec![
+                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpCall, &vec![0]),
+                    make(Opcode::OpConstant, &vec![0]),
+                    make(Opcode::OpAdd, &Vec::new()),
+                    make(Opcode::OpReturnValue, &Vec::new()),
+                ]),
+            ],
+            expected_instructions: vec![
+                make(Opcode::OpClosure, &vec![1, 0]),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+        CompilerTestCase {
+            input: "fn(f) { if (true) { return f(); } else { f() } }",
+            expected_constants: vec![ExpectedType::from(vec![
+                // 0000
+                make(Opcode::OpTrue, &Vec::new()),
+                // 0001
+                make(Opcode::OpJumpNotTruthy, &vec![12]),
+                // 0004
+                make(Opcode::OpGetLocal, &vec![0]),
+                // 0006
+                make(Opcode::OpTailCall, &vec![0]),
+                // 0008
+                make(Opcode::OpReturnValue, &Vec::new()),
+                // 0009
+                make(Opcode::OpJump, &vec![16]),
+                // 0012
+                make(Opcode::OpGetLocal, &vec![0]),
+                // 0014
+                make(Opcode::OpTailCall, &vec![0]),
+                // 0016
+                make(Opcode::OpReturnValue, &Vec::new()),
+            ])],
+            expected_instructions: vec![
+                make(Opcode::OpClosure, &vec![0, 0]),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+        CompilerTestCase {
+            input: "fn(f) { f(); 1 }",
+            expected_constants: vec![
+                ExpectedType::from(1i64),
+                ExpectedType::from(vec![
+                    make(Opcode::OpGetLocal, &vec![0]),
+                    make(Opcode::OpCall, &vec![0]),
+                    make(Opcode::OpPop, &Vec::new()),
+                    make(Opcode::OpConstant, &vec![0]),
+                    make(Opcode::OpReturnValue, &Vec::new()),
+                ]),
+            ],
+            expected_instructions: vec![
+                make(Opcode::OpClosure, &vec![1, 0]),
+                make(Opcode::OpPop, &Vec::new()),
+            ],
+        },
+    ];
+    run_compiler_tests(tests);
+}
@@ -0,0 +1,34 @@
+// src/compiler/error.rs
+
+use crate::token::*;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileErrorKind {
+    UnknownOperator(String),
+    UndefinedVariable(String),
+    InvalidAssignment(String),
+}
+impl fmt::Display for CompileErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileErrorKind::UnknownOperator(operator) => {
+                write!(f, "unknown operator {}", operator)
+            }
+            CompileErrorKind::UndefinedVariable(name) => write!(f, "undefined variable {}", name),
+            CompileErrorKind::InvalidAssignment(name) => write!(f, "cannot assign to {}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub kind: CompileErrorKind,
+    pub span: Span,
+}
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.kind)
+    }
+}
+impl std::error::Error for CompileError {}
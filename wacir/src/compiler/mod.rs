@@ -1,6 +1,8 @@
 mod compiler;
+mod error;
 mod symbol_table;
 pub use compiler::*;
+pub use error::*;
 pub use symbol_table::*;
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 // src/symbol_table.rs
 
+use crate::object::*;
 use std::cell::*;
 use std::collections::*;
 use std::rc::*;
@@ -10,6 +11,7 @@ pub enum SymbolScope {
     LocalScope,
     BuiltinScope,
     FreeScope,
+    FunctionScope,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -52,31 +54,29 @@ impl SymbolTable {
 
     pub fn resolve(&mut self, name: &str) -> Option<Symbol> {
         match self.store.get(name) {
-            Some(s) => return Some(s.clone()),
-            None => {}
-        };
-        let mut need_define_free = false;
-        let mut saved_obj: Option<Symbol> = None;
-        {
-            if let Some(outer) = self.outer.as_ref() {
-                if let Some(obj) = outer.borrow_mut().resolve(name) {
-                    match obj.scope {
-                        SymbolScope::GlobalScope | SymbolScope::BuiltinScope => {
-                            return Some(obj);
-                        }
-                        _ => {
-                            need_define_free = true;
-                            saved_obj = Some(obj);
-                        }
-                    }
-                }
+            // The name of the function being compiled refers to the binding
+            // that holds the function, when there is one, so that reading it
+            // sees a reassignment and assigning to it updates the binding.
+            Some(s) if s.scope == SymbolScope::FunctionScope => {
+                let s = s.clone();
+                Some(self.resolve_outer(name).unwrap_or(s))
             }
+            Some(s) => Some(s.clone()),
+            None => self.resolve_outer(name),
         }
-        if need_define_free {
-            return Some(self.define_free(saved_obj.unwrap()));
-        }
+    }
 
-        return None;
+    // Resolves name in the enclosing tables. A local of an enclosing function
+    // becomes a free variable of this one.
+    fn resolve_outer(&mut self, name: &str) -> Option<Symbol> {
+        let obj = match self.outer.as_ref() {
+            Some(outer) => outer.borrow_mut().resolve(name)?,
+            None => return None,
+        };
+        match obj.scope {
+            SymbolScope::GlobalScope | SymbolScope::BuiltinScope => Some(obj),
+            _ => Some(self.define_free(obj)),
+        }
     }
 
     pub fn new_enclosed_symbol_table(outer: Rc<RefCell<SymbolTable>>) -> SymbolTable {
@@ -98,6 +98,24 @@ impl SymbolTable {
         symbol
     }
 
+    pub fn define_builtins(&mut self, builtins: &Builtins) {
+        for (i, builtin) in builtins.iter().enumerate() {
+            self.define_builtin(i, &builtin.name);
+        }
+    }
+
+    // Binds the name of the function being compiled, so its body can refer to
+    // the closure itself.
+    pub fn define_function_name(&mut self, name: &str) -> Symbol {
+        let symbol = Symbol {
+            name: String::from(name),
+            scope: SymbolScope::FunctionScope,
+            index: 0,
+        };
+        self.store.insert(String::from(name), symbol.clone());
+        symbol
+    }
+
     pub fn define_free(&mut self, original: Symbol) -> Symbol {
         let original_name = original.name.clone();
         self.free_symbols.push(original);
@@ -528,3 +528,44 @@ fn test_resolve_unresolvable_free() {
         );
     }
 }
+
+#[test]
+fn test_define_and_resolve_function_name() {
+    let mut global = SymbolTable::new();
+    global.define_function_name("a");
+
+    let expected = Symbol {
+        name: String::from("a"),
+        scope: SymbolScope::FunctionScope,
+        index: 0,
+    };
+    let result = global.resolve(&expected.name);
+    assert!(
+        result == Some(expected.clone()),
+        "expected {} to resolve to {:?}, got={:?}",
+        expected.name,
+        expected,
+        result
+    );
+}
+
+#[test]
+fn test_shadowing_function_name() {
+    let mut global = SymbolTable::new();
+    global.define_function_name("a");
+    global.define("a");
+
+    let expected = Symbol {
+        name: String::from("a"),
+        scope: SymbolScope::GlobalScope,
+        index: 0,
+    };
+    let result = global.resolve(&expected.name);
+    assert!(
+        result == Some(expected.clone()),
+        "expected {} to resolve to {:?}, got={:?}",
+        expected.name,
+        expected,
+        result
+    );
+}
@@ -0,0 +1,227 @@
+// src/engine/engine.rs
+
+use crate::ast::*;
+use crate::code::*;
+use crate::compiler::*;
+use crate::evaluator::*;
+use crate::lexer::*;
+use crate::object::*;
+use crate::parser::*;
+use crate::vm::*;
+use std::cell::*;
+use std::fmt;
+use std::rc::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Evaluator,
+    Vm,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineError {
+    Parse(Vec<ParseError>),
+    Compile(CompileError),
+    Runtime(RuntimeError),
+}
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::Parse(errors) => write!(
+                f,
+                "{}",
+                errors
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            ),
+            EngineError::Compile(err) => write!(f, "{}", err),
+            EngineError::Runtime(err) => write!(f, "{}", err),
+        }
+    }
+}
+impl std::error::Error for EngineError {}
+impl From<Vec<ParseError>> for EngineError {
+    fn from(errors: Vec<ParseError>) -> Self {
+        EngineError::Parse(errors)
+    }
+}
+impl From<CompileError> for EngineError {
+    fn from(err: CompileError) -> Self {
+        EngineError::Compile(err)
+    }
+}
+impl From<RuntimeError> for EngineError {
+    fn from(err: RuntimeError) -> Self {
+        EngineError::Runtime(err)
+    }
+}
+
+pub fn parse(input: &str) -> Result<Program, Vec<ParseError>> {
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l);
+    p.parse_program()
+}
+
+// Entry point for host applications. Keeps the global state of the chosen
+// backend alive across evaluations, the same way the REPL does.
+pub struct Engine {
+    backend: Backend,
+    env: Rc<RefCell<Environment>>,
+    symbol_table: Rc<RefCell<SymbolTable>>,
+    constants: Rc<RefCell<Vec<Object>>>,
+    globals: Rc<RefCell<Vec<Option<Object>>>>,
+    builtins: Rc<Builtins>,
+    arithmetic: Arithmetic,
+    vm_config: VmConfig,
+}
+impl Engine {
+    pub fn new(backend: Backend) -> Engine {
+        let builtins = Rc::new(new_builtins());
+        let mut symbol_table = SymbolTable::new();
+        symbol_table.define_builtins(&builtins);
+        Engine {
+            backend: backend,
+            env: Rc::new(RefCell::new(new_environment_with_builtins(Rc::clone(
+                &builtins,
+            )))),
+            symbol_table: Rc::new(RefCell::new(symbol_table)),
+            constants: Rc::new(RefCell::new(Vec::new())),
+            globals: Rc::new(RefCell::new(Vec::new())),
+            builtins: builtins,
+            arithmetic: Arithmetic::default(),
+            vm_config: VmConfig::default(),
+        }
+    }
+
+    // Makes a native function callable from scripts under name. An arity of
+    // None accepts any number of arguments.
+    pub fn register_builtin<F>(&mut self, name: &str, arity: Option<usize>, func: F)
+    where
+        F: Fn(&[Object]) -> Result<Object, RuntimeError> + 'static,
+    {
+        let index = Rc::make_mut(&mut self.builtins).register(name, arity, func);
+        self.symbol_table.borrow_mut().define_builtin(index, name);
+        self.env.borrow_mut().builtins = Some(Rc::clone(&self.builtins));
+    }
+
+    // Selects what integer arithmetic does on overflow. Checked by default.
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+        self.env.borrow_mut().arithmetic = arithmetic;
+    }
+
+    // Sets the stack and call depth limits of the VM backend.
+    pub fn set_vm_config(&mut self, config: VmConfig) {
+        self.vm_config = config;
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    pub fn eval(&mut self, input: &str) -> Result<Object, EngineError> {
+        let program = parse(input)?;
+        self.eval_program(program)
+    }
+
+    pub fn eval_program(&mut self, program: Program) -> Result<Object, EngineError> {
+        match self.backend {
+            Backend::Evaluator => Ok(try_evaluate(Node::Program(program), Rc::clone(&self.env))?),
+            Backend::Vm => {
+                let mut comp = Compiler::new_with_state(
+                    Rc::clone(&self.symbol_table),
+                    Rc::clone(&self.constants),
+                );
+                comp.compile(Node::Program(program))?;
+                let mut machine = Vm::new_with_state(
+                    comp.bytecode(),
+                    Rc::clone(&self.globals),
+                    Rc::clone(&self.builtins),
+                );
+                machine.set_arithmetic(self.arithmetic);
+                machine.set_config(self.vm_config);
+                machine.run()?;
+                Ok(machine.last_popped_stack_elem.unwrap_or(crate::vm::NULL))
+            }
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        match self.backend {
+            Backend::Evaluator => {
+                let env = self.env.borrow();
+                env.get(name).or_else(|| env.get_builtin(name))
+            }
+            Backend::Vm => {
+                let symbol = self.symbol_table.borrow_mut().resolve(name)?;
+                match symbol.scope {
+                    SymbolScope::GlobalScope => {
+                        self.globals.borrow().get(symbol.index as usize)?.clone()
+                    }
+                    SymbolScope::BuiltinScope => {
+                        self.builtins.get(name).map(|x| Object::Builtin(x.clone()))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Object) {
+        match self.backend {
+            Backend::Evaluator => {
+                self.env.borrow_mut().define(String::from(name), value);
+            }
+            Backend::Vm => {
+                let existing = self.symbol_table.borrow_mut().resolve(name);
+                let symbol = match existing {
+                    Some(symbol) if symbol.scope == SymbolScope::GlobalScope => symbol,
+                    _ => self.symbol_table.borrow_mut().define(name),
+                };
+                let index = symbol.index as usize;
+                let mut globals = self.globals.borrow_mut();
+                if index >= globals.len() {
+                    globals.resize(index + 1, None);
+                }
+                globals[index] = Some(value);
+            }
+        }
+    }
+
+    // Calls the function bound to `name` with already evaluated arguments.
+    pub fn call(&mut self, name: &str, args: Vec<Object>) -> Result<Object, EngineError> {
+        match self.get_global(name) {
+            Some(func) => self.call_function(func, args),
+            None => Err(
+                RuntimeError::new(RuntimeErrorKind::IdentifierNotFound(String::from(name))).into(),
+            ),
+        }
+    }
+
+    pub fn call_function(
+        &mut self,
+        func: Object,
+        mut args: Vec<Object>,
+    ) -> Result<Object, EngineError> {
+        match self.backend {
+            Backend::Evaluator => Ok(apply_function(func, &mut args)?),
+            Backend::Vm => {
+                let bytecode = Bytecode {
+                    instuctions: Instructions::new(),
+                    constants: Rc::clone(&self.constants),
+                    source_map: SourceMap::new(),
+                };
+                let mut machine = Vm::new_with_state(
+                    bytecode,
+                    Rc::clone(&self.globals),
+                    Rc::clone(&self.builtins),
+                );
+                machine.set_arithmetic(self.arithmetic);
+                machine.set_config(self.vm_config);
+                Ok(machine.call(func, args)?)
+            }
+        }
+    }
+}
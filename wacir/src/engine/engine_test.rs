@@ -0,0 +1,379 @@
+// src/engine/engine_test.rs
+
+use crate::compiler::*;
+use crate::engine::*;
+use crate::object::*;
+use crate::vm::*;
+use std::cell::*;
+use std::rc::*;
+
+const BACKENDS: [Backend; 2] = [Backend::Evaluator, Backend::Vm];
+
+#[test]
+fn test_eval_keeps_globals() {
+    for backend in BACKENDS.iter() {
+        let mut engine = Engine::new(*backend);
+        engine.eval("let a = 5;").unwrap();
+        let result = engine.eval("a * 2").unwrap();
+        assert!(
+            result == Object::Integer(Integer { value: 10 }),
+            "wrong result for {:?}. got={:?}",
+            backend,
+            result
+        );
+    }
+}
+
+#[test]
+fn test_get_and_set_globals() {
+    for backend in BACKENDS.iter() {
+        let mut engine = Engine::new(*backend);
+        engine.set_global("limit", Object::Integer(Integer { value: 3 }));
+        engine.eval("let doubled = limit + limit;").unwrap();
+        engine.set_global("limit", Object::Integer(Integer { value: 4 }));
+
+        let tests = [("doubled", 6), ("limit", 4)];
+        for tt in tests.iter() {
+            let got = engine.get_global(tt.0);
+            assert!(
+                got == Some(Object::Integer(Integer { value: tt.1 })),
+                "wrong global {} for {:?}. got={:?}",
+                tt.0,
+                backend,
+                got
+            );
+        }
+        assert!(
+            engine.get_global("missing").is_none(),
+            "unexpected global for {:?}",
+            backend
+        );
+    }
+}
+
+#[test]
+fn test_call_function() {
+    for backend in BACKENDS.iter() {
+        let mut engine = Engine::new(*backend);
+        engine
+            .eval("let base = 10; let add = fn(a, b) { base + a + b };")
+            .unwrap();
+
+        let result = engine
+            .call(
+                "add",
+                vec![
+                    Object::Integer(Integer { value: 1 }),
+                    Object::Integer(Integer { value: 2 }),
+                ],
+            )
+            .unwrap();
+        assert!(
+            result == Object::Integer(Integer { value: 13 }),
+            "wrong result for {:?}. got={:?}",
+            backend,
+            result
+        );
+
+        let result = engine
+            .call(
+                "len",
+                vec![Object::StringObj(StringObj {
+                    value: String::from("four"),
+                })],
+            )
+            .unwrap();
+        assert!(
+            result == Object::Integer(Integer { value: 4 }),
+            "wrong result for {:?}. got={:?}",
+            backend,
+            result
+        );
+    }
+}
+
+#[test]
+fn test_errors() {
+    for backend in BACKENDS.iter() {
+        let mut engine = Engine::new(*backend);
+        match engine.eval("let = 1;") {
+            Err(EngineError::Parse(_)) => {}
+            other => panic!("expected parse error for {:?}. got={:?}", backend, other),
+        }
+        engine.eval("let f = fn(x) { x + true };").unwrap();
+        match engine.call("f", vec![Object::Integer(Integer { value: 1 })]) {
+            Err(EngineError::Runtime(err)) => assert!(
+                err.to_string() == "1:19: type mismatch: INTEGER + BOOLEAN",
+                "wrong runtime error for {:?}. got={}",
+                backend,
+                err
+            ),
+            other => panic!("expected runtime error for {:?}. got={:?}", backend, other),
+        }
+        match engine.call("f", Vec::new()) {
+            Err(EngineError::Runtime(err)) => assert!(
+                err.kind == RuntimeErrorKind::WrongArgumentCount { want: 1, got: 0 },
+                "wrong runtime error for {:?}. got={:?}",
+                backend,
+                err
+            ),
+            other => panic!("expected runtime error for {:?}. got={:?}", backend, other),
+        }
+    }
+
+    let mut engine = Engine::new(Backend::Vm);
+    match engine.eval("undefined_name") {
+        Err(EngineError::Compile(err)) => assert!(
+            err.kind == CompileErrorKind::UndefinedVariable(String::from("undefined_name")),
+            "wrong compile error. got={:?}",
+            err
+        ),
+        other => panic!("expected compile error. got={:?}", other),
+    }
+}
+
+#[test]
+fn test_register_builtin() {
+    for backend in BACKENDS.iter() {
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+
+        let mut engine = Engine::new(*backend);
+        engine.register_builtin("double", Some(1), move |args| {
+            counter.set(counter.get() + 1);
+            match &args[0] {
+                Object::Integer(Integer { value }) => {
+                    Ok(Object::Integer(Integer { value: value * 2 }))
+                }
+                other => Err(RuntimeErrorKind::ArgumentTypeMismatch {
+                    function: String::from("double"),
+                    want: String::from("INTEGER"),
+                    got: String::from(other.get_type()),
+                }
+                .into()),
+            }
+        });
+
+        let result = engine
+            .eval("let f = fn(x) { double(x) + len([1]) }; f(20) + double(1)")
+            .unwrap();
+        assert!(
+            result == Object::Integer(Integer { value: 43 }),
+            "wrong result for {:?}. got={:?}",
+            backend,
+            result
+        );
+        assert!(
+            calls.get() == 2,
+            "wrong number of calls for {:?}. got={}",
+            backend,
+            calls.get()
+        );
+
+        let tests = [
+            ("double(1, 2)", "wrong number of arguments: want=1, got=2"),
+            (
+                "double(true)",
+                "argument to `double` must be INTEGER, got BOOLEAN",
+            ),
+        ];
+        for tt in tests.iter() {
+            match engine.eval(tt.0) {
+                Err(EngineError::Runtime(err)) => assert!(
+                    err.kind.to_string() == tt.1,
+                    "wrong error for {:?}. expected={}, got={}",
+                    backend,
+                    tt.1,
+                    err.kind
+                ),
+                other => panic!("expected runtime error for {:?}. got={:?}", backend, other),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_while_loops() {
+    for backend in BACKENDS.iter() {
+        let ticks = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&ticks);
+
+        let mut engine = Engine::new(*backend);
+        engine.register_builtin("tick", Some(0), move |_| {
+            counter.set(counter.get() + 1);
+            Ok(Object::Integer(Integer {
+                value: counter.get(),
+            }))
+        });
+
+        let result = engine
+            .eval(
+                "let n = 0;
+                while (true) {
+                    let n = tick();
+                    if (n < 3) { continue; }
+                    if (n == 5) { break; }
+                }
+                n",
+            )
+            .unwrap();
+        assert!(
+            result == Object::Integer(Integer { value: 5 }),
+            "wrong result for {:?}. got={:?}",
+            backend,
+            result
+        );
+        assert!(
+            ticks.get() == 5,
+            "wrong number of ticks for {:?}. got={}",
+            backend,
+            ticks.get()
+        );
+    }
+}
+
+#[test]
+fn test_assign_to_function_name() {
+    let tests = [
+        ("let f = fn() { f = 5; 1 }; f(); f", 5),
+        ("let f = fn() { f }; let g = f; f = 5; g()", 5),
+        ("let w = fn() { let f = fn() { f = 5; 1 }; f(); f }; w()", 5),
+        (
+            "let w = fn() { let f = fn() { f }; let g = f; f = 6; g() }; w()",
+            6,
+        ),
+        (
+            "let w = fn() { let f = fn(n) { if (n == 0) { 7 } else { f(n - 1) } }; f(3) }; w()",
+            7,
+        ),
+    ];
+    for backend in BACKENDS.iter() {
+        for tt in tests.iter() {
+            let mut engine = Engine::new(*backend);
+            let result = engine.eval(tt.0).unwrap();
+            assert!(
+                result == Object::Integer(Integer { value: tt.1 }),
+                "wrong result for {:?} on {:?}. got={:?}",
+                tt.0,
+                backend,
+                result
+            );
+        }
+    }
+}
+
+#[test]
+fn test_loop_control_in_expressions() {
+    for backend in BACKENDS.iter() {
+        let mut engine = Engine::new(*backend);
+        match engine.eval(
+            "let s = 0; let i = 0;
+            while (i < 3) { i += 1; s = s + (if (i == 2) { continue; } else { i }); }",
+        ) {
+            Err(EngineError::Parse(errors)) => assert!(
+                errors[0].to_string() == "2:60: continue inside expression",
+                "wrong parse error for {:?}. got={}",
+                backend,
+                errors[0]
+            ),
+            other => panic!("expected parse error for {:?}. got={:?}", backend, other),
+        }
+
+        let result = engine
+            .eval(
+                "let s = 0; let i = 0;
+                while (i < 5) {
+                    i += 1;
+                    if (i > 1) { if (i == 2) { continue; } }
+                    if (i == 4) { break; }
+                    s = s + i;
+                }
+                s",
+            )
+            .unwrap();
+        assert!(
+            result == Object::Integer(Integer { value: 4 }),
+            "wrong result for {:?}. got={:?}",
+            backend,
+            result
+        );
+    }
+}
+
+#[test]
+fn test_wrapping_arithmetic() {
+    for backend in BACKENDS.iter() {
+        let mut engine = Engine::new(*backend);
+        let err = engine.eval("9223372036854775807 + 1").unwrap_err();
+        assert!(
+            err.to_string() == "1:21: integer overflow",
+            "wrong error for {:?}. got={}",
+            backend,
+            err
+        );
+
+        engine.set_arithmetic(Arithmetic::Wrapping);
+        let tests = [
+            ("let f = fn(x) { x + 1 }; f(9223372036854775807)", i64::MIN),
+            ("-(-9223372036854775807 - 1)", i64::MIN),
+            ("(-9223372036854775807 - 1) / -1", i64::MIN),
+            (
+                "let a = [3037000500]; a[0] *= a[0]; a[0]",
+                -9223372036709301616,
+            ),
+        ];
+        for tt in tests.iter() {
+            let result = engine.eval(tt.0).unwrap();
+            assert!(
+                result == Object::Integer(Integer { value: tt.1 }),
+                "wrong result for {} on {:?}. got={:?}",
+                tt.0,
+                backend,
+                result
+            );
+        }
+        let result = engine
+            .call("f", vec![Object::Integer(Integer { value: i64::MAX })])
+            .unwrap();
+        assert!(
+            result == Object::Integer(Integer { value: i64::MIN }),
+            "wrong call result for {:?}. got={:?}",
+            backend,
+            result
+        );
+
+        let err = engine.eval("1 % 0").unwrap_err();
+        assert!(
+            err.to_string() == "1:3: division by zero",
+            "wrong error for {:?}. got={}",
+            backend,
+            err
+        );
+    }
+}
+
+#[test]
+fn test_vm_config() {
+    let mut engine = Engine::new(Backend::Vm);
+    engine.set_vm_config(VmConfig {
+        max_frames: 4,
+        ..VmConfig::default()
+    });
+    engine
+        .eval("let f = fn(n) { if (n == 0) { 0 } else { f(n - 1) + 1 } };")
+        .unwrap();
+    let result = engine.eval("f(2)").unwrap();
+    assert!(
+        result == Object::Integer(Integer { value: 2 }),
+        "wrong result. got={:?}",
+        result
+    );
+    match engine.call("f", vec![Object::Integer(Integer { value: 3 })]) {
+        Err(EngineError::Runtime(err)) => assert!(
+            err.kind == RuntimeErrorKind::MaxRecursionDepth,
+            "wrong runtime error. got={:?}",
+            err
+        ),
+        other => panic!("expected runtime error. got={:?}", other),
+    }
+}
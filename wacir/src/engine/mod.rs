@@ -0,0 +1,5 @@
+mod engine;
+pub use engine::*;
+
+#[cfg(test)]
+mod engine_test;
@@ -0,0 +1,7 @@
+// src/formatter/mod.rs
+
+mod formatter;
+pub use formatter::*;
+
+#[cfg(test)]
+mod formatter_test;
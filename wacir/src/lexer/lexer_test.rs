@@ -14,7 +14,7 @@ let add = fn(x, y) {
 };
 
 let result = add(five, ten);
-!-/*5;
+!-/ *5;
 5 < 10 > 5;
 
 if (5 < 10) { 
@@ -141,3 +141,432 @@ if (5 < 10) {
         );
     }
 }
+
+#[test]
+fn test_token_spans() {
+    let input = "let x = 5;\n  x == \"ab\";";
+
+    let tests = [
+        (TokenType::LET, 0, 3, 1, 1),
+        (TokenType::IDENT, 4, 5, 1, 5),
+        (TokenType::ASSIGN, 6, 7, 1, 7),
+        (TokenType::INT, 8, 9, 1, 9),
+        (TokenType::SEMICOLON, 9, 10, 1, 10),
+        (TokenType::IDENT, 13, 14, 2, 3),
+        (TokenType::EQ, 15, 17, 2, 5),
+        (TokenType::STRING, 18, 22, 2, 8),
+        (TokenType::SEMICOLON, 22, 23, 2, 12),
+        (TokenType::EOF, 23, 23, 2, 13),
+    ];
+
+    let mut l = Lexer::new(input);
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == tt.0,
+            "test[{}] - tokentype wrong. expected={:?}, got={:?}",
+            i,
+            tt.0,
+            tok.tk_type
+        );
+        let expected = Span {
+            start: tt.1,
+            end: tt.2,
+            line: tt.3,
+            column: tt.4,
+        };
+        assert!(
+            tok.span == expected,
+            "test[{}] - span wrong. expected={:?}, got={:?}",
+            i,
+            expected,
+            tok.span
+        );
+    }
+}
+
+#[test]
+fn test_loop_keywords() {
+    let input = "while (x) { break; continue; }";
+
+    let tests = [
+        (TokenType::WHILE, "while"),
+        (TokenType::LPAREN, "("),
+        (TokenType::IDENT, "x"),
+        (TokenType::RPAREN, ")"),
+        (TokenType::LBRACE, "{"),
+        (TokenType::BREAK, "break"),
+        (TokenType::SEMICOLON, ";"),
+        (TokenType::CONTINUE, "continue"),
+        (TokenType::SEMICOLON, ";"),
+        (TokenType::RBRACE, "}"),
+        (TokenType::EOF, ""),
+    ];
+
+    let mut l = Lexer::new(input);
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == tt.0 && tok.literal == tt.1,
+            "test[{}] - token wrong. expected={:?} {:?}, got={:?} {:?}",
+            i,
+            tt.0,
+            tt.1,
+            tok.tk_type,
+            tok.literal
+        );
+    }
+}
+
+#[test]
+fn test_assignment_operators() {
+    let input = "a = 1; a += 2; a -= 3; a *= 4; a /= 5; a == a";
+
+    let tests = [
+        (TokenType::IDENT, "a"),
+        (TokenType::ASSIGN, "="),
+        (TokenType::INT, "1"),
+        (TokenType::SEMICOLON, ";"),
+        (TokenType::IDENT, "a"),
+        (TokenType::PLUSASSIGN, "+="),
+        (TokenType::INT, "2"),
+        (TokenType::SEMICOLON, ";"),
+        (TokenType::IDENT, "a"),
+        (TokenType::MINUSASSIGN, "-="),
+        (TokenType::INT, "3"),
+        (TokenType::SEMICOLON, ";"),
+        (TokenType::IDENT, "a"),
+        (TokenType::ASTERISKASSIGN, "*="),
+        (TokenType::INT, "4"),
+        (TokenType::SEMICOLON, ";"),
+        (TokenType::IDENT, "a"),
+        (TokenType::SLASHASSIGN, "/="),
+        (TokenType::INT, "5"),
+        (TokenType::SEMICOLON, ";"),
+        (TokenType::IDENT, "a"),
+        (TokenType::EQ, "=="),
+        (TokenType::IDENT, "a"),
+        (TokenType::EOF, ""),
+    ];
+
+    let mut l = Lexer::new(input);
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == tt.0 && tok.literal == tt.1,
+            "test[{}] - token wrong. expected={:?} {:?}, got={:?} {:?}",
+            i,
+            tt.0,
+            tt.1,
+            tok.tk_type,
+            tok.literal
+        );
+    }
+}
+
+#[test]
+fn test_number_literals() {
+    let input = "5 3.14 1e-9 2.5E+3 7e2 1. x 4e y";
+
+    let tests = [
+        (TokenType::INT, "5"),
+        (TokenType::FLOAT, "3.14"),
+        (TokenType::FLOAT, "1e-9"),
+        (TokenType::FLOAT, "2.5E+3"),
+        (TokenType::FLOAT, "7e2"),
+        (TokenType::INT, "1"),
+        (TokenType::ILLEGAL, "."),
+        (TokenType::IDENT, "x"),
+        (TokenType::INT, "4"),
+        (TokenType::IDENT, "e"),
+        (TokenType::IDENT, "y"),
+        (TokenType::EOF, ""),
+    ];
+
+    let mut l = Lexer::new(input);
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == tt.0 && tok.literal == tt.1,
+            "test[{}] - token wrong. expected={:?} {:?}, got={:?} {:?}",
+            i,
+            tt.0,
+            tt.1,
+            tok.tk_type,
+            tok.literal
+        );
+    }
+}
+
+#[test]
+fn test_logical_operators() {
+    let input = "a && b || c & d";
+
+    let tests = [
+        (TokenType::IDENT, "a"),
+        (TokenType::AND, "&&"),
+        (TokenType::IDENT, "b"),
+        (TokenType::OR, "||"),
+        (TokenType::IDENT, "c"),
+        (TokenType::ILLEGAL, "&"),
+        (TokenType::IDENT, "d"),
+        (TokenType::EOF, ""),
+    ];
+
+    let mut l = Lexer::new(input);
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == tt.0 && tok.literal == tt.1,
+            "test[{}] - token wrong. expected={:?} {:?}, got={:?} {:?}",
+            i,
+            tt.0,
+            tt.1,
+            tok.tk_type,
+            tok.literal
+        );
+    }
+}
+
+#[test]
+fn test_comparison_operators() {
+    let input = "a < b <= c > d >= e";
+
+    let tests = [
+        (TokenType::IDENT, "a"),
+        (TokenType::LT, "<"),
+        (TokenType::IDENT, "b"),
+        (TokenType::LTEQ, "<="),
+        (TokenType::IDENT, "c"),
+        (TokenType::GT, ">"),
+        (TokenType::IDENT, "d"),
+        (TokenType::GTEQ, ">="),
+        (TokenType::IDENT, "e"),
+        (TokenType::EOF, ""),
+    ];
+
+    let mut l = Lexer::new(input);
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == tt.0 && tok.literal == tt.1,
+            "test[{}] - token wrong. expected={:?} {:?}, got={:?} {:?}",
+            i,
+            tt.0,
+            tt.1,
+            tok.tk_type,
+            tok.literal
+        );
+    }
+}
+
+#[test]
+fn test_string_escapes() {
+    let tests = [
+        (r#""a\nb""#, TokenType::STRING, "a\nb", None),
+        (r#""tab\there""#, TokenType::STRING, "tab\there", None),
+        (r#""say \"hi\"""#, TokenType::STRING, "say \"hi\"", None),
+        (r#""back\\slash""#, TokenType::STRING, "back\\slash", None),
+        (
+            r#""\u{48}\u{1F600}""#,
+            TokenType::STRING,
+            "H\u{1F600}",
+            None,
+        ),
+        (
+            r#""bad\q""#,
+            TokenType::ILLEGAL,
+            r#""bad\q""#,
+            Some(LexError::InvalidEscape(String::from(r"\q"))),
+        ),
+        (
+            r#""\u{D800}""#,
+            TokenType::ILLEGAL,
+            r#""\u{D800}""#,
+            Some(LexError::InvalidEscape(String::from(r"\u{D800}"))),
+        ),
+        (
+            r#""\u48""#,
+            TokenType::ILLEGAL,
+            r#""\u48""#,
+            Some(LexError::InvalidEscape(String::from(r"\u"))),
+        ),
+        (
+            r#""open"#,
+            TokenType::ILLEGAL,
+            r#""open"#,
+            Some(LexError::UnterminatedString),
+        ),
+        (
+            r#""open\"#,
+            TokenType::ILLEGAL,
+            r#""open\"#,
+            Some(LexError::UnterminatedString),
+        ),
+    ];
+
+    for tt in tests.iter() {
+        let mut l = Lexer::new(tt.0);
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == tt.1 && tok.literal == tt.2 && tok.error == tt.3,
+            "input {} - token wrong. expected={:?} {:?} {:?}, got={:?} {:?} {:?}",
+            tt.0,
+            tt.1,
+            tt.2,
+            tt.3,
+            tok.tk_type,
+            tok.literal,
+            tok.error
+        );
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == TokenType::EOF,
+            "input {} - expected EOF. got={:?}",
+            tt.0,
+            tok.tk_type
+        );
+    }
+}
+
+#[test]
+fn test_unicode_input() {
+    let input = "let 名字 = \"你好, 世界 🌏\";\nlet café_2 = 名字;";
+
+    let tests = [
+        (TokenType::LET, "let", 0, 1, 1),
+        (TokenType::IDENT, "名字", 4, 1, 5),
+        (TokenType::ASSIGN, "=", 11, 1, 8),
+        (TokenType::STRING, "你好, 世界 🌏", 13, 1, 10),
+        (TokenType::SEMICOLON, ";", 34, 1, 20),
+        (TokenType::LET, "let", 36, 2, 1),
+        (TokenType::IDENT, "café_2", 40, 2, 5),
+        (TokenType::ASSIGN, "=", 48, 2, 12),
+        (TokenType::IDENT, "名字", 50, 2, 14),
+        (TokenType::SEMICOLON, ";", 56, 2, 16),
+        (TokenType::EOF, "", 57, 2, 17),
+    ];
+
+    let mut l = Lexer::new(input);
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == tt.0 && tok.literal == tt.1,
+            "test[{}] - token wrong. expected={:?} {:?}, got={:?} {:?}",
+            i,
+            tt.0,
+            tt.1,
+            tok.tk_type,
+            tok.literal
+        );
+        assert!(
+            tok.span.start == tt.2 && tok.span.line == tt.3 && tok.span.column == tt.4,
+            "test[{}] - span wrong. expected={}:{}:{}, got={:?}",
+            i,
+            tt.2,
+            tt.3,
+            tt.4,
+            tok.span
+        );
+    }
+
+    let mut l = Lexer::new("a ¤ b");
+    let tests = [
+        (TokenType::IDENT, "a"),
+        (TokenType::ILLEGAL, "¤"),
+        (TokenType::IDENT, "b"),
+    ];
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == tt.0 && tok.literal == tt.1,
+            "test[{}] - token wrong. expected={:?} {:?}, got={:?} {:?}",
+            i,
+            tt.0,
+            tt.1,
+            tok.tk_type,
+            tok.literal
+        );
+    }
+}
+
+#[test]
+fn test_comments() {
+    let input = "// header\nlet x = 10 / 2; /* block\n comment */ x // done";
+
+    let mut l = Lexer::new(input);
+    let tests = [
+        (TokenType::LET, vec!["// header", "\n"]),
+        (TokenType::IDENT, vec![]),
+        (TokenType::ASSIGN, vec![]),
+        (TokenType::INT, vec![]),
+        (TokenType::SLASH, vec![]),
+        (TokenType::INT, vec![]),
+        (TokenType::SEMICOLON, vec![]),
+        (TokenType::IDENT, vec!["/* block\n comment */"]),
+        (TokenType::EOF, vec!["// done"]),
+    ];
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        let trivia: Vec<&str> = tok.trivia.iter().map(|x| &x.text[..]).collect();
+        assert!(
+            tok.tk_type == tt.0 && trivia == tt.1,
+            "test[{}] - token wrong. expected={:?} {:?}, got={:?} {:?}",
+            i,
+            tt.0,
+            tt.1,
+            tok.tk_type,
+            trivia
+        );
+    }
+
+    let mut l = Lexer::new("a /* one */ /* two */ b");
+    l.next_token();
+    let tok = l.next_token();
+    let expected = vec![
+        Trivia {
+            kind: TriviaKind::BlockComment,
+            text: String::from("/* one */"),
+            span: Span {
+                start: 2,
+                end: 11,
+                line: 1,
+                column: 3,
+            },
+        },
+        Trivia {
+            kind: TriviaKind::BlockComment,
+            text: String::from("/* two */"),
+            span: Span {
+                start: 12,
+                end: 21,
+                line: 1,
+                column: 13,
+            },
+        },
+    ];
+    assert!(
+        tok.literal == "b" && tok.trivia == expected,
+        "wrong trivia. got={:?} {:?}",
+        tok.literal,
+        tok.trivia
+    );
+
+    let tests = [
+        ("1 /* open", LexError::UnterminatedComment, "/* open"),
+        ("1 /* a /* b */ 2", LexError::NestedComment, "/* a /* b */"),
+    ];
+    for tt in tests.iter() {
+        let mut l = Lexer::new(tt.0);
+        l.next_token();
+        let tok = l.next_token();
+        assert!(
+            tok.tk_type == TokenType::ILLEGAL
+                && tok.error == Some(tt.1.clone())
+                && tok.literal == tt.2,
+            "input {} - wrong token. got={:?}",
+            tt.0,
+            tok
+        );
+    }
+}
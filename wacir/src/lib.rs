@@ -0,0 +1,21 @@
+// src/lib.rs
+
+#[macro_use]
+extern crate lazy_static;
+
+pub mod ast;
+pub mod bytecode;
+#[macro_use]
+pub mod code;
+pub mod compiler;
+pub mod engine;
+pub mod evaluator;
+pub mod formatter;
+pub mod lexer;
+pub mod object;
+pub mod parser;
+pub mod repl;
+pub mod token;
+pub mod vm;
+
+pub use engine::*;
@@ -1,22 +1,217 @@
 // src/main.rs
 
-#[macro_use]
-extern crate lazy_static;
-
-mod ast;
-#[macro_use]
-mod code;
-mod compiler;
-mod evaluator;
-mod lexer;
-mod object;
-mod parser;
-mod repl;
-mod token;
-mod vm;
+use wacir::ast::*;
+use wacir::bytecode::*;
+use wacir::compiler::*;
+use wacir::formatter::*;
+use wacir::lexer::*;
+use wacir::parser::*;
+use wacir::repl;
+use wacir::vm::*;
+
+#[cfg(test)]
+mod main_test;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 {
+        let code = match &args[1][..] {
+            "fmt" => fmt_files(&args[2..]),
+            "compile" => compile_file(&args[2..]),
+            "run" => run_compiled_file(&args[2..]),
+            "disasm" => disassemble_file(&args[2..]),
+            _ => run_file(&args[1]),
+        };
+        std::process::exit(code);
+    }
+
     println!("Hello, This is the Monkey programming language!");
     println!("Feel free to type in commands");
     repl::start(&mut std::io::stdin(), &mut std::io::stdout());
 }
+
+// Runs a whole script file and returns the process exit code.
+fn run_file(path: &str) -> i32 {
+    match read_source(path).and_then(|input| compile_source(path, &input)) {
+        Some(bytecode) => run_bytecode(path, bytecode),
+        None => 1,
+    }
+}
+
+fn read_source(path: &str) -> Option<String> {
+    match std::fs::read_to_string(path) {
+        Ok(input) => Some(input),
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            None
+        }
+    }
+}
+
+// Parses and compiles the script read from path, reporting any errors.
+fn compile_source(path: &str, input: &str) -> Option<Bytecode> {
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l);
+    let program = match p.parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            for msg in errors.iter() {
+                eprintln!("{}:{}", path, msg);
+            }
+            return None;
+        }
+    };
+
+    let mut comp = Compiler::new();
+    if let Err(err) = comp.compile(Node::Program(program)) {
+        eprintln!("{}:{}", path, err);
+        return None;
+    }
+    Some(comp.bytecode())
+}
+
+fn run_bytecode(path: &str, bytecode: Bytecode) -> i32 {
+    let mut machine = Vm::new(bytecode);
+    if let Err(err) = machine.run() {
+        eprintln!("{}:{}", path, err);
+        eprint!("{}", err.backtrace());
+        return 1;
+    }
+    0
+}
+
+// Compiles a script to a .mbc file: `compile in.monkey [-o out.mbc]`.
+fn compile_file(args: &[String]) -> i32 {
+    let mut input = None;
+    let mut output = None;
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "-o" && i + 1 < args.len() {
+            output = Some(std::path::PathBuf::from(&args[i + 1]));
+            i += 2;
+        } else if input.is_none() {
+            input = Some(&args[i]);
+            i += 1;
+        } else {
+            input = None;
+            break;
+        }
+    }
+    let input = match input {
+        Some(input) => input,
+        None => {
+            eprintln!("usage: wacir compile <file> [-o <output>]");
+            return 1;
+        }
+    };
+    let output = output.unwrap_or_else(|| std::path::Path::new(input).with_extension("mbc"));
+
+    let bytecode = match read_source(input).and_then(|source| compile_source(input, &source)) {
+        Some(bytecode) => bytecode,
+        None => return 1,
+    };
+    let data = match serialize(&bytecode) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("{}: {}", input, err);
+            return 1;
+        }
+    };
+    if let Err(err) = std::fs::write(&output, data) {
+        eprintln!("{}: {}", output.display(), err);
+        return 1;
+    }
+    0
+}
+
+// Loads and runs a .mbc file written by compile.
+fn run_compiled_file(args: &[String]) -> i32 {
+    if args.len() != 1 {
+        eprintln!("usage: wacir run <file.mbc>");
+        return 1;
+    }
+    let path = &args[0];
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            return 1;
+        }
+    };
+    match deserialize(&data) {
+        Ok(bytecode) => run_bytecode(path, bytecode),
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            1
+        }
+    }
+}
+
+// Prints the compiled bytecode of a script: `disasm file.monkey`.
+fn disassemble_file(args: &[String]) -> i32 {
+    if args.len() != 1 {
+        eprintln!("usage: wacir disasm <file>");
+        return 1;
+    }
+    let path = &args[0];
+    let input = match read_source(path) {
+        Some(input) => input,
+        None => return 1,
+    };
+    match compile_source(path, &input) {
+        Some(bytecode) => {
+            print!("{}", disassemble(&bytecode, &input));
+            0
+        }
+        None => 1,
+    }
+}
+
+// Rewrites each file in the canonical style. With --check, files are left
+// untouched and the ones that are not formatted are listed instead.
+fn fmt_files(args: &[String]) -> i32 {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if paths.is_empty() {
+        eprintln!("usage: wacir fmt [--check] <file>...");
+        return 1;
+    }
+
+    let mut code = 0;
+    for path in paths {
+        let input = match std::fs::read_to_string(path) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                code = 1;
+                continue;
+            }
+        };
+        let output = match format_source(&input) {
+            Ok(output) => output,
+            Err(FormatError::Parse(errors)) => {
+                for msg in errors.iter() {
+                    eprintln!("{}:{}", path, msg);
+                }
+                code = 1;
+                continue;
+            }
+            Err(err) => {
+                eprintln!("{}:{}", path, err);
+                code = 1;
+                continue;
+            }
+        };
+        if output == input {
+            continue;
+        }
+        if check {
+            println!("{}", path);
+            code = 1;
+        } else if let Err(err) = std::fs::write(path, output) {
+            eprintln!("{}: {}", path, err);
+            code = 1;
+        }
+    }
+    code
+}
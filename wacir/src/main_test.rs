@@ -0,0 +1,45 @@
+// src/main_test.rs
+
+use super::*;
+
+// Writes source to a file in the temporary directory and returns its path.
+fn write_script(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(format!("wacir-{}-{}", std::process::id(), name));
+    std::fs::write(&path, source).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn test_top_level_return() {
+    let script = write_script(
+        "return.monkey",
+        "let x = 5;\nif (x > 1) { return x; }\n1 / 0",
+    );
+    let compiled = script.replace(".monkey", ".mbc");
+
+    let code = run_file(&script);
+    assert!(
+        code == 0,
+        "wrong exit code running {}. got={}",
+        script,
+        code
+    );
+
+    let code = compile_file(&[script.clone(), String::from("-o"), compiled.clone()]);
+    assert!(
+        code == 0,
+        "wrong exit code compiling {}. got={}",
+        script,
+        code
+    );
+    let code = run_compiled_file(&[compiled.clone()]);
+    assert!(
+        code == 0,
+        "wrong exit code running {}. got={}",
+        compiled,
+        code
+    );
+
+    std::fs::remove_file(&script).unwrap();
+    std::fs::remove_file(&compiled).unwrap();
+}
@@ -1,20 +1,30 @@
 // src/environment.rs
 
+use crate::evaluator::*;
 use crate::object::*;
 use std::cell::*;
 use std::collections::*;
 use std::rc::*;
 
 pub fn new_enclosed_environment(outer: Rc<RefCell<Environment>>) -> Environment {
-    let mut env = new_environment();
-    env.outer = Some(outer);
-    env
+    Environment {
+        store: HashMap::new(),
+        outer: Some(outer),
+        builtins: None,
+        arithmetic: Arithmetic::default(),
+    }
 }
 
 pub fn new_environment() -> Environment {
+    new_environment_with_builtins(Rc::new(new_builtins()))
+}
+
+pub fn new_environment_with_builtins(builtins: Rc<Builtins>) -> Environment {
     Environment {
         store: HashMap::new(),
         outer: None,
+        builtins: Some(builtins),
+        arithmetic: Arithmetic::default(),
     }
 }
 
@@ -22,6 +32,8 @@ pub fn new_environment() -> Environment {
 pub struct Environment {
     pub store: HashMap<String, Object>,
     pub outer: Option<Rc<RefCell<Environment>>>,
+    pub builtins: Option<Rc<Builtins>>, // only set on the outermost environment
+    pub arithmetic: Arithmetic,         // only read from the outermost environment
 }
 impl Environment {
     pub fn get(&self, name: &str) -> Option<Object> {
@@ -32,8 +44,35 @@ impl Environment {
         }
         None
     }
-    pub fn set(&mut self, name: String, val: Object) -> Object {
+    pub fn get_builtin(&self, name: &str) -> Option<Object> {
+        if let Some(builtins) = &self.builtins {
+            return builtins.get(name).map(|x| Object::Builtin(x.clone()));
+        } else if let Some(o) = &self.outer {
+            return o.borrow().get_builtin(name);
+        }
+        None
+    }
+    pub fn arithmetic(&self) -> Arithmetic {
+        match &self.outer {
+            Some(o) => o.borrow().arithmetic(),
+            None => self.arithmetic,
+        }
+    }
+    // Binds name in this environment, shadowing any outer binding.
+    pub fn define(&mut self, name: String, val: Object) -> Object {
         self.store.insert(name, val.clone());
         val
     }
+    // Updates the binding of name in the environment that defines it.
+    // Reports false if name is not bound anywhere.
+    pub fn set(&mut self, name: &str, val: Object) -> bool {
+        if let Some(v) = self.store.get_mut(name) {
+            *v = val;
+            true
+        } else if let Some(o) = &self.outer {
+            o.borrow_mut().set(name, val)
+        } else {
+            false
+        }
+    }
 }
@@ -0,0 +1,188 @@
+// src/object/error.rs
+
+use crate::token::*;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+    TypeMismatch {
+        left: String,
+        operator: String,
+        right: String,
+    },
+    UnknownPrefixOperator {
+        operator: String,
+        right: String,
+    },
+    UnknownInfixOperator {
+        left: String,
+        operator: String,
+        right: String,
+    },
+    IdentifierNotFound(String),
+    NotAFunction(String),
+    WrongArgumentCount {
+        want: usize,
+        got: usize,
+    },
+    UnsupportedArgument {
+        function: String,
+        got: String,
+    },
+    ArgumentTypeMismatch {
+        function: String,
+        want: String,
+        got: String,
+    },
+    UnusableAsHashKey(String),
+    IndexNotSupported(String),
+    InvalidAssignment(String),
+    IndexOutOfRange {
+        index: i64,
+        length: usize,
+    },
+    UninitializedValue,
+    StackOverflow,
+    MaxRecursionDepth,
+    DivisionByZero,
+    IntegerOverflow,
+}
+impl fmt::Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeErrorKind::TypeMismatch {
+                left,
+                operator,
+                right,
+            } => write!(f, "type mismatch: {} {} {}", left, operator, right),
+            RuntimeErrorKind::UnknownPrefixOperator { operator, right } => {
+                write!(f, "unknown operator: {}{}", operator, right)
+            }
+            RuntimeErrorKind::UnknownInfixOperator {
+                left,
+                operator,
+                right,
+            } => write!(f, "unknown operator: {} {} {}", left, operator, right),
+            RuntimeErrorKind::IdentifierNotFound(name) => {
+                write!(f, "identifier not found: {}", name)
+            }
+            RuntimeErrorKind::NotAFunction(t) => write!(f, "not a function: {}", t),
+            RuntimeErrorKind::WrongArgumentCount { want, got } => {
+                write!(f, "wrong number of arguments: want={}, got={}", want, got)
+            }
+            RuntimeErrorKind::UnsupportedArgument { function, got } => {
+                write!(f, "argument to `{}` not supported, got {}", function, got)
+            }
+            RuntimeErrorKind::ArgumentTypeMismatch {
+                function,
+                want,
+                got,
+            } => write!(
+                f,
+                "argument to `{}` must be {}, got {}",
+                function, want, got
+            ),
+            RuntimeErrorKind::UnusableAsHashKey(t) => write!(f, "unusable as hash key: {}", t),
+            RuntimeErrorKind::IndexNotSupported(t) => {
+                write!(f, "index operator not supported: {}", t)
+            }
+            RuntimeErrorKind::InvalidAssignment(t) => write!(f, "invalid assignment: {}", t),
+            RuntimeErrorKind::IndexOutOfRange { index, length } => {
+                write!(f, "index out of range: index={}, length={}", index, length)
+            }
+            RuntimeErrorKind::UninitializedValue => write!(f, "uninitialized value"),
+            RuntimeErrorKind::StackOverflow => write!(f, "stack overflow"),
+            RuntimeErrorKind::MaxRecursionDepth => write!(f, "maximum recursion depth exceeded"),
+            RuntimeErrorKind::DivisionByZero => write!(f, "division by zero"),
+            RuntimeErrorKind::IntegerOverflow => write!(f, "integer overflow"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: Option<Span>,
+    pub trace: Vec<TraceFrame>, // innermost call first
+    frame_span: Option<Span>,   // where the function being unwound was executing
+}
+impl RuntimeError {
+    pub fn new(kind: RuntimeErrorKind) -> RuntimeError {
+        RuntimeError {
+            kind: kind,
+            span: None,
+            trace: Vec::new(),
+            frame_span: None,
+        }
+    }
+
+    // Attaches a position unless a more precise one was recorded already.
+    pub fn at(mut self, span: Span) -> RuntimeError {
+        if self.span.is_none() {
+            self.span = Some(span);
+        }
+        if self.frame_span.is_none() {
+            self.frame_span = Some(span);
+        }
+        self
+    }
+
+    // Records that the error left the function named function. Its position
+    // is the innermost one attached since the previous frame was recorded.
+    pub fn unwind(mut self, function: &str) -> RuntimeError {
+        self.trace.push(TraceFrame {
+            function: String::from(function),
+            span: self.frame_span.take(),
+        });
+        self
+    }
+
+    // Runs of identical frames, as left by deep recursion, are printed once.
+    pub fn backtrace(&self) -> String {
+        let mut out = String::new();
+        let mut i = 0;
+        while i < self.trace.len() {
+            let frame = &self.trace[i];
+            let repeats = self.trace[i + 1..]
+                .iter()
+                .take_while(|other| *other == frame)
+                .count();
+            out.push_str(&format!("    at {}\n", frame));
+            if repeats > 1 {
+                out.push_str(&format!("    ... repeated {} more times\n", repeats));
+            } else if repeats == 1 {
+                out.push_str(&format!("    at {}\n", frame));
+            }
+            i += repeats + 1;
+        }
+        out
+    }
+}
+impl From<RuntimeErrorKind> for RuntimeError {
+    fn from(kind: RuntimeErrorKind) -> Self {
+        RuntimeError::new(kind)
+    }
+}
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{}: {}", span, self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+impl std::error::Error for RuntimeError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceFrame {
+    pub function: String,
+    pub span: Option<Span>,
+}
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{} ({})", self.function, span),
+            None => write!(f, "{}", self.function),
+        }
+    }
+}
@@ -1,7 +1,9 @@
 mod environment;
+mod error;
 mod object;
 
 pub use environment::*;
+pub use error::*;
 pub use object::*;
 
 #[cfg(test)]
@@ -18,49 +18,72 @@ pub trait ObjectTrait {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Object {
     Integer(Integer),
+    Float(Float),
     Boolean(Boolean),
     Null(Null),
     ReturnValue(ReturnValue),
+    Break(Break),
+    Continue(Continue),
     ErrorObj(ErrorObj),
-    Function(Function),
+    Function(Rc<Function>),
     StringObj(StringObj),
-    Builtin(Builtin),
-    Array(Array),
-    Hash(Hash),
-    CompiledFunction(CompiledFunction),
-    Closure(Closure),
+    Builtin(Rc<Builtin>),
+    Array(Rc<RefCell<Array>>),
+    Hash(Rc<RefCell<Hash>>),
+    CompiledFunction(Rc<CompiledFunction>),
+    Closure(Rc<Closure>),
+    Upvalue(Upvalue),
+    Uninitialized(Uninitialized),
 }
 impl ObjectTrait for Object {
     fn get_type(&self) -> &str {
         match self {
             Object::Integer(i) => i.get_type(),
+            Object::Float(f) => f.get_type(),
             Object::Boolean(b) => b.get_type(),
             Object::Null(n) => n.get_type(),
             Object::ReturnValue(rv) => rv.get_type(),
+            Object::Break(b) => b.get_type(),
+            Object::Continue(c) => c.get_type(),
             Object::ErrorObj(e) => e.get_type(),
             Object::Function(f) => f.get_type(),
             Object::StringObj(s) => s.get_type(),
             Object::Builtin(b) => b.get_type(),
-            Object::Array(a) => a.get_type(),
-            Object::Hash(h) => h.get_type(),
+            Object::Array(_) => "ARRAY",
+            Object::Hash(_) => "HASH",
             Object::CompiledFunction(cf) => cf.get_type(),
             Object::Closure(c) => c.get_type(),
+            Object::Upvalue(u) => u.get_type(),
+            Object::Uninitialized(u) => u.get_type(),
         }
     }
     fn inspect(&self) -> String {
         match self {
             Object::Integer(i) => i.inspect(),
+            Object::Float(f) => f.inspect(),
             Object::Boolean(b) => b.inspect(),
             Object::Null(n) => n.inspect(),
             Object::ReturnValue(rv) => rv.inspect(),
+            Object::Break(b) => b.inspect(),
+            Object::Continue(c) => c.inspect(),
             Object::ErrorObj(e) => e.inspect(),
             Object::Function(f) => f.inspect(),
             Object::StringObj(s) => s.inspect(),
             Object::Builtin(b) => b.inspect(),
-            Object::Array(a) => a.inspect(),
-            Object::Hash(h) => h.inspect(),
+            // A container is held borrowed while its elements are printed, so
+            // one that contains itself is printed as `[...]` the second time.
+            Object::Array(a) => match a.try_borrow_mut() {
+                Ok(a) => a.inspect(),
+                Err(_) => String::from("[...]"),
+            },
+            Object::Hash(h) => match h.try_borrow_mut() {
+                Ok(h) => h.inspect(),
+                Err(_) => String::from("{...}"),
+            },
             Object::CompiledFunction(cf) => cf.inspect(),
             Object::Closure(c) => c.inspect(),
+            Object::Upvalue(u) => u.inspect(),
+            Object::Uninitialized(u) => u.inspect(),
         }
     }
 }
@@ -78,6 +101,22 @@ impl ObjectTrait for Integer {
     }
 }
 
+// Floats are compared with IEEE semantics, so NaN is not equal to itself.
+// They are deliberately not hashable.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Float {
+    pub value: f64,
+}
+impl Eq for Float {}
+impl ObjectTrait for Float {
+    fn get_type(&self) -> &str {
+        "FLOAT"
+    }
+    fn inspect(&self) -> String {
+        format!("{:?}", self.value)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Boolean {
     pub value: bool,
@@ -115,6 +154,28 @@ impl ObjectTrait for ReturnValue {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Break {}
+impl ObjectTrait for Break {
+    fn get_type(&self) -> &str {
+        "BREAK"
+    }
+    fn inspect(&self) -> String {
+        String::from("break")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Continue {}
+impl ObjectTrait for Continue {
+    fn get_type(&self) -> &str {
+        "CONTINUE"
+    }
+    fn inspect(&self) -> String {
+        String::from("continue")
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ErrorObj {
     pub message: String,
@@ -133,6 +194,7 @@ pub struct Function {
     pub parameters: Vec<Identifier>,
     pub body: BlockStatement,
     pub env: Rc<RefCell<Environment>>,
+    pub name: Option<String>, // set when the function is first bound by a let statement
 }
 impl ObjectTrait for Function {
     fn get_type(&self) -> &str {
@@ -178,11 +240,27 @@ impl ObjectTrait for StringObj {
     }
 }
 
-pub type BuiltinFunction = fn(&Vec<Object>) -> std::result::Result<Object, String>;
+pub type BuiltinFunction = Rc<dyn Fn(&[Object]) -> std::result::Result<Object, RuntimeError>>;
 
 pub struct Builtin {
+    pub name: String,
+    pub arity: Option<usize>, // None accepts any number of arguments
     pub func: BuiltinFunction,
 }
+impl Builtin {
+    pub fn call(&self, args: &[Object]) -> std::result::Result<Object, RuntimeError> {
+        if let Some(arity) = self.arity {
+            if args.len() != arity {
+                return Err(RuntimeErrorKind::WrongArgumentCount {
+                    want: arity,
+                    got: args.len(),
+                }
+                .into());
+            }
+        }
+        (self.func)(args)
+    }
+}
 impl ObjectTrait for Builtin {
     fn get_type(&self) -> &str {
         "BUILTIN"
@@ -193,7 +271,7 @@ impl ObjectTrait for Builtin {
 }
 impl std::fmt::Debug for Builtin {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Builtin")
+        write!(f, "Builtin({})", self.name)
     }
 }
 impl PartialEq for Builtin {
@@ -204,14 +282,105 @@ impl PartialEq for Builtin {
 impl Eq for Builtin {}
 impl Clone for Builtin {
     fn clone(&self) -> Self {
-        Builtin { func: self.func }
+        Builtin {
+            name: self.name.clone(),
+            arity: self.arity,
+            func: Rc::clone(&self.func),
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+// How integer arithmetic behaves when the result does not fit in an i64.
+// Checked arithmetic reports an error, wrapping arithmetic wraps around in
+// two's complement. Division by zero is an error either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arithmetic {
+    Checked,
+    Wrapping,
+}
+impl Default for Arithmetic {
+    fn default() -> Self {
+        Arithmetic::Checked
+    }
+}
+
+// Named native functions that scripts can call. An entry's position is the
+// index used by OpGetBuiltin, so entries are only appended or replaced.
+#[derive(Clone, Default)]
+pub struct Builtins {
+    entries: Vec<Rc<Builtin>>,
+}
+impl Builtins {
+    pub fn new() -> Builtins {
+        Builtins {
+            entries: Vec::new(),
+        }
+    }
+
+    // Adds func under name, replacing an earlier entry with the same name.
+    // Returns the index of the entry.
+    pub fn register<F>(&mut self, name: &str, arity: Option<usize>, func: F) -> usize
+    where
+        F: Fn(&[Object]) -> std::result::Result<Object, RuntimeError> + 'static,
+    {
+        let builtin = Rc::new(Builtin {
+            name: String::from(name),
+            arity: arity,
+            func: Rc::new(func),
+        });
+        match self.entries.iter().position(|x| x.name == name) {
+            Some(index) => {
+                self.entries[index] = builtin;
+                index
+            }
+            None => {
+                self.entries.push(builtin);
+                self.entries.len() - 1
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Rc<Builtin>> {
+        self.entries.iter().find(|x| x.name == name)
+    }
+
+    pub fn get_by_index(&self, index: usize) -> Option<&Rc<Builtin>> {
+        self.entries.get(index)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Rc<Builtin>> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+impl std::fmt::Debug for Builtins {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.entries.iter().map(|x| &x.name))
+            .finish()
+    }
+}
+
+// Arrays, hashes and closures are shared behind an Rc, so copying one out of a
+// variable is cheap. They compare equal only to themselves. Index assignment
+// modifies an array or hash in place, so every reference sees the change.
+#[derive(Debug, Clone)]
 pub struct Array {
     pub elements: Vec<Object>,
 }
+impl PartialEq for Array {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+impl Eq for Array {}
 impl ObjectTrait for Array {
     fn get_type(&self) -> &str {
         "ARRAY"
@@ -281,8 +450,8 @@ impl Debug for Hash {
 }
 impl Eq for Hash {}
 impl PartialEq for Hash {
-    fn eq(&self, _other: &Self) -> bool {
-        false
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
     }
 }
 impl ObjectTrait for Hash {
@@ -339,6 +508,8 @@ pub struct CompiledFunction {
     pub instructions: Instructions,
     pub num_locals: usize,
     pub num_parameters: usize,
+    pub source_map: SourceMap,
+    pub name: Option<String>, // set when the literal is bound by a let statement
 }
 impl ObjectTrait for CompiledFunction {
     fn get_type(&self) -> &str {
@@ -349,11 +520,17 @@ impl ObjectTrait for CompiledFunction {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Clone)]
 pub struct Closure {
-    pub func: CompiledFunction,
-    pub free: Vec<Option<Object>>,
+    pub func: Rc<CompiledFunction>,
+    pub free: Vec<Rc<RefCell<Object>>>,
 }
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+impl Eq for Closure {}
 impl ObjectTrait for Closure {
     fn get_type(&self) -> &str {
         "CLOSURE"
@@ -362,3 +539,32 @@ impl ObjectTrait for Closure {
         format!("Closure[{:p}]", &self)
     }
 }
+
+// A variable captured by a closure. The enclosing function's stack slot and
+// every closure capturing it share the same box, so an assignment through any
+// of them is seen by all. Upvalues only live on the VM stack.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Upvalue {
+    pub value: Rc<RefCell<Object>>,
+}
+impl ObjectTrait for Upvalue {
+    fn get_type(&self) -> &str {
+        "UPVALUE"
+    }
+    fn inspect(&self) -> String {
+        self.value.borrow().inspect()
+    }
+}
+
+// Fills the local slots of a VM frame until the let statement for the local
+// runs, so reading it too early is an error rather than null.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Uninitialized {}
+impl ObjectTrait for Uninitialized {
+    fn get_type(&self) -> &str {
+        "UNINITIALIZED"
+    }
+    fn inspect(&self) -> String {
+        String::from("uninitialized")
+    }
+}
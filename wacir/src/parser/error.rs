@@ -0,0 +1,61 @@
+// src/parser/error.rs
+
+use crate::token::*;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedToken {
+        expected: TokenType,
+        found: TokenType,
+    },
+    NoPrefixParseFn(TokenType),
+    InvalidInteger(String),
+    InvalidFloat(String),
+    UnexpectedEof,
+    OutsideLoop(String),
+    InsideExpression(String),
+    InvalidAssignmentTarget(String),
+    Lex(LexError),
+}
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedToken { expected, found } => write!(
+                f,
+                "expected next token to be {:?}, got {:?} instead",
+                expected, found
+            ),
+            ParseErrorKind::NoPrefixParseFn(t) => {
+                write!(f, "no prefix parse function for {:?} found", t)
+            }
+            ParseErrorKind::InvalidInteger(literal) => {
+                write!(f, "could not parse {} as integer", literal)
+            }
+            ParseErrorKind::InvalidFloat(literal) => {
+                write!(f, "could not parse {} as float", literal)
+            }
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected EOF"),
+            ParseErrorKind::OutsideLoop(keyword) => write!(f, "{} outside loop", keyword),
+            ParseErrorKind::InsideExpression(keyword) => {
+                write!(f, "{} inside expression", keyword)
+            }
+            ParseErrorKind::InvalidAssignmentTarget(target) => {
+                write!(f, "invalid assignment target: {}", target)
+            }
+            ParseErrorKind::Lex(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.kind)
+    }
+}
+impl std::error::Error for ParseError {}
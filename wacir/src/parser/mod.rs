@@ -1,4 +1,6 @@
+mod error;
 mod parser;
+pub use error::*;
 pub use parser::*;
 
 #[cfg(test)]
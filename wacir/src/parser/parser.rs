@@ -1,12 +1,13 @@
 // src/parser.rs
 
+use super::error::*;
 use crate::ast::*;
 use crate::lexer::*;
 use crate::token::*;
 use std::collections::HashMap;
 
-type PrefixParseFn = fn(&mut Parser) -> Result<Expression, String>;
-type InfixParseFn = fn(&mut Parser, Expression) -> Result<Expression, String>;
+type PrefixParseFn = fn(&mut Parser) -> Result<Expression, ParseError>;
+type InfixParseFn = fn(&mut Parser, Expression) -> Result<Expression, ParseError>;
 
 pub struct Parser {
     pub l: Lexer,
@@ -14,18 +15,27 @@ pub struct Parser {
     pub peek_token: Token,
     pub prefix_parse_fns: HashMap<TokenType, PrefixParseFn>,
     pub infix_parse_fns: HashMap<TokenType, InfixParseFn>,
+    loop_depth: usize,
+    // The first break or continue parsed in the current statement that leaves
+    // an enclosing loop.
+    loop_control: Option<Token>,
+    errors: Vec<ParseError>,
 }
 impl Parser {
     pub fn new(l: Lexer) -> Parser {
         let mut p = Parser {
             l: l,
-            cur_token: new_token(TokenType::ILLEGAL, 0),
-            peek_token: new_token(TokenType::ILLEGAL, 0),
+            cur_token: new_token(TokenType::ILLEGAL, '\0'),
+            peek_token: new_token(TokenType::ILLEGAL, '\0'),
             prefix_parse_fns: HashMap::new(),
             infix_parse_fns: HashMap::new(),
+            loop_depth: 0,
+            loop_control: None,
+            errors: Vec::new(),
         };
         p.register_prefix(TokenType::IDENT, |parser| parser.parse_identifier());
         p.register_prefix(TokenType::INT, |parser| parser.parse_integer_literal());
+        p.register_prefix(TokenType::FLOAT, |parser| parser.parse_float_literal());
         p.register_prefix(TokenType::BANG, |parser| parser.parse_prefix_expression());
         p.register_prefix(TokenType::MINUS, |parser| parser.parse_prefix_expression());
         p.register_prefix(TokenType::TRUE, |parser| parser.parse_boolean_literal());
@@ -53,24 +63,57 @@ impl Parser {
         p.register_infix(TokenType::ASTERISK, |parser, exp| {
             parser.parse_infix_expression(exp)
         });
+        p.register_infix(TokenType::PERCENT, |parser, exp| {
+            parser.parse_infix_expression(exp)
+        });
         p.register_infix(TokenType::EQ, |parser, exp| {
             parser.parse_infix_expression(exp)
         });
         p.register_infix(TokenType::NOTEQ, |parser, exp| {
             parser.parse_infix_expression(exp)
         });
+        p.register_infix(TokenType::AND, |parser, exp| {
+            parser.parse_infix_expression(exp)
+        });
+        p.register_infix(TokenType::OR, |parser, exp| {
+            parser.parse_infix_expression(exp)
+        });
         p.register_infix(TokenType::LT, |parser, exp| {
             parser.parse_infix_expression(exp)
         });
         p.register_infix(TokenType::GT, |parser, exp| {
             parser.parse_infix_expression(exp)
         });
+        p.register_infix(TokenType::LTEQ, |parser, exp| {
+            parser.parse_infix_expression(exp)
+        });
+        p.register_infix(TokenType::GTEQ, |parser, exp| {
+            parser.parse_infix_expression(exp)
+        });
         p.register_infix(TokenType::LPAREN, |parser, exp| {
             parser.parse_call_expression(exp)
         });
         p.register_infix(TokenType::LBRACKET, |parser, exp| {
             parser.parse_index_expression(exp)
         });
+        p.register_infix(TokenType::ASSIGN, |parser, exp| {
+            parser.parse_assign_expression(exp)
+        });
+        p.register_infix(TokenType::PLUSASSIGN, |parser, exp| {
+            parser.parse_assign_expression(exp)
+        });
+        p.register_infix(TokenType::MINUSASSIGN, |parser, exp| {
+            parser.parse_assign_expression(exp)
+        });
+        p.register_infix(TokenType::ASTERISKASSIGN, |parser, exp| {
+            parser.parse_assign_expression(exp)
+        });
+        p.register_infix(TokenType::SLASHASSIGN, |parser, exp| {
+            parser.parse_assign_expression(exp)
+        });
+        p.register_infix(TokenType::PERCENTASSIGN, |parser, exp| {
+            parser.parse_assign_expression(exp)
+        });
 
         p.next_token();
         p.next_token();
@@ -81,34 +124,74 @@ impl Parser {
         self.cur_token = std::mem::replace(&mut self.peek_token, self.l.next_token());
     }
 
-    pub fn parse_program(&mut self) -> Result<Program, Vec<String>> {
+    pub fn parse_program(&mut self) -> Result<Program, Vec<ParseError>> {
+        let (program, errors) = self.parse_program_with_errors();
+        if errors.len() != 0 {
+            return Err(errors);
+        }
+        Ok(program)
+    }
+
+    // Parses the whole input, recovering from each error so that every
+    // mistake is reported. Statements that failed to parse are left out of
+    // the returned program.
+    pub fn parse_program_with_errors(&mut self) -> (Program, Vec<ParseError>) {
         let mut statements: Vec<Statement> = Vec::new();
-        let mut errors = Vec::new();
         while self.cur_token.tk_type != TokenType::EOF {
             match self.parse_statement() {
                 Ok(stmt) => statements.push(stmt),
-                Err(err) => errors.push(err),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.synchronize();
+                }
             }
             self.next_token();
         }
-        if errors.len() != 0 {
-            return Err(errors);
-        }
-
-        Ok(Program {
+        let program = Program {
             statements: statements,
-        })
+            trailing: self.cur_token.trivia.clone(),
+        };
+        (program, std::mem::replace(&mut self.errors, Vec::new()))
+    }
+
+    // Skips tokens after an error until the end of the broken statement: a
+    // `;` or a `}` that closes the enclosing block. Braces opened while
+    // skipping are matched so a broken function body is skipped as a whole.
+    // Returns true when it stopped at the `}` of the enclosing block.
+    fn synchronize(&mut self) -> bool {
+        let mut depth = 0;
+        loop {
+            match self.cur_token.tk_type {
+                TokenType::EOF => return false,
+                TokenType::SEMICOLON if depth == 0 => return false,
+                TokenType::LBRACE => depth += 1,
+                TokenType::RBRACE if depth == 0 => return true,
+                TokenType::RBRACE => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if self.peek_token_is(TokenType::SEMICOLON) {
+                            self.next_token();
+                        }
+                        return false;
+                    }
+                }
+                _ => {}
+            }
+            self.next_token();
+        }
     }
 
-    fn parse_statement(&mut self) -> Result<Statement, String> {
+    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
         match self.cur_token.tk_type {
             TokenType::LET => self.parse_let_statement(),
             TokenType::RETURN => self.parse_return_statement(),
+            TokenType::WHILE => self.parse_while_statement(),
+            TokenType::BREAK | TokenType::CONTINUE => self.parse_loop_control_statement(),
             _ => self.parse_expression_statement(),
         }
     }
 
-    fn parse_let_statement(&mut self) -> Result<Statement, String> {
+    fn parse_let_statement(&mut self) -> Result<Statement, ParseError> {
         let token = self.cur_token.clone();
 
         self.expect_peek(TokenType::IDENT)?;
@@ -122,7 +205,10 @@ impl Parser {
 
         self.next_token();
 
-        let value = self.parse_expression(Precedence::LOWEST)?;
+        let mut value = self.parse_value_expression()?;
+        if let Expression::FunctionLiteral(function_literal) = &mut value {
+            function_literal.name = Some(name.value.clone());
+        }
 
         if self.peek_token_is(TokenType::SEMICOLON) {
             self.next_token();
@@ -141,7 +227,7 @@ impl Parser {
     fn peek_token_is(&self, t: TokenType) -> bool {
         self.peek_token.tk_type == t
     }
-    fn expect_peek(&mut self, t: TokenType) -> Result<(), String> {
+    fn expect_peek(&mut self, t: TokenType) -> Result<(), ParseError> {
         if self.peek_token_is(t.clone()) {
             self.next_token();
             Ok(())
@@ -150,18 +236,24 @@ impl Parser {
         }
     }
 
-    fn peek_error(&mut self, t: TokenType) -> String {
-        format!(
-            "expected next token to be {:?}, got {:?} instead",
-            t, self.peek_token.tk_type
-        )
+    fn peek_error(&mut self, t: TokenType) -> ParseError {
+        if let Some(err) = lex_error(&self.peek_token) {
+            return err;
+        }
+        ParseError {
+            kind: ParseErrorKind::UnexpectedToken {
+                expected: t.clone(),
+                found: self.peek_token.tk_type.clone(),
+            },
+            span: self.peek_token.span,
+        }
     }
 
-    fn parse_return_statement(&mut self) -> Result<Statement, String> {
+    fn parse_return_statement(&mut self) -> Result<Statement, ParseError> {
         let token = self.cur_token.clone();
         self.next_token();
 
-        let return_value = self.parse_expression(Precedence::LOWEST)?;
+        let return_value = self.parse_value_expression()?;
 
         if self.peek_token_is(TokenType::SEMICOLON) {
             self.next_token();
@@ -173,6 +265,57 @@ impl Parser {
         }))
     }
 
+    fn parse_while_statement(&mut self) -> Result<Statement, ParseError> {
+        let token = self.cur_token.clone();
+        self.expect_peek(TokenType::LPAREN)?;
+        self.next_token();
+        let condition = self.parse_value_expression()?;
+        self.expect_peek(TokenType::RPAREN)?;
+        self.expect_peek(TokenType::LBRACE)?;
+
+        self.loop_depth += 1;
+        let loop_control = self.loop_control.take();
+        let body = self.parse_block_statement();
+        self.loop_control = loop_control;
+        self.loop_depth -= 1;
+        let body = body?;
+
+        if self.peek_token_is(TokenType::SEMICOLON) {
+            self.next_token();
+        }
+
+        Ok(Statement::WhileStatement(WhileStatement {
+            token: token,
+            condition: condition,
+            body: body,
+        }))
+    }
+
+    fn parse_loop_control_statement(&mut self) -> Result<Statement, ParseError> {
+        let token = self.cur_token.clone();
+        if self.loop_depth == 0 {
+            return Err(ParseError {
+                kind: ParseErrorKind::OutsideLoop(token.literal.clone()),
+                span: token.span,
+            });
+        }
+
+        if self.peek_token_is(TokenType::SEMICOLON) {
+            self.next_token();
+        }
+
+        if self.loop_control.is_none() {
+            self.loop_control = Some(token.clone());
+        }
+        if token.tk_type == TokenType::BREAK {
+            Ok(Statement::BreakStatement(BreakStatement { token: token }))
+        } else {
+            Ok(Statement::ContinueStatement(ContinueStatement {
+                token: token,
+            }))
+        }
+    }
+
     fn register_prefix(&mut self, token_type: TokenType, func: PrefixParseFn) {
         self.prefix_parse_fns.insert(token_type, func);
     }
@@ -180,9 +323,23 @@ impl Parser {
         self.infix_parse_fns.insert(token_type, func);
     }
 
-    fn parse_expression_statement(&mut self) -> Result<Statement, String> {
+    // An if expression on its own is the only expression that may contain a
+    // break or continue for an enclosing loop: its value is discarded, so
+    // leaving it early leaves nothing half evaluated.
+    fn parse_expression_statement(&mut self) -> Result<Statement, ParseError> {
         let token = self.cur_token.clone();
-        let expression = self.parse_expression(Precedence::LOWEST)?;
+        let outer = self.loop_control.take();
+        let expression = self.parse_expression(Precedence::LOWEST);
+        let inner = std::mem::replace(&mut self.loop_control, outer);
+        let expression = expression?;
+        if let Some(loop_control) = inner {
+            match expression {
+                Expression::IfExpression(_) => {
+                    self.loop_control = self.loop_control.take().or(Some(loop_control))
+                }
+                _ => return Err(loop_control_in_expression(loop_control)),
+            }
+        }
         if self.peek_token_is(TokenType::SEMICOLON) {
             self.next_token();
         }
@@ -192,7 +349,20 @@ impl Parser {
         }))
     }
 
-    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, String> {
+    // Parses an expression whose value is used. A break or continue inside it
+    // would leave the loop with the expression half evaluated.
+    fn parse_value_expression(&mut self) -> Result<Expression, ParseError> {
+        let outer = self.loop_control.take();
+        let expression = self.parse_expression(Precedence::LOWEST);
+        let inner = std::mem::replace(&mut self.loop_control, outer);
+        let expression = expression?;
+        match inner {
+            Some(loop_control) => Err(loop_control_in_expression(loop_control)),
+            None => Ok(expression),
+        }
+    }
+
+    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, ParseError> {
         if let Some(prefix) = self.prefix_parse_fns.get(&self.cur_token.tk_type) {
             let mut left_exp = prefix(self)?;
             while !self.peek_token_is(TokenType::SEMICOLON) && precedence < self.peek_precedence() {
@@ -211,14 +381,14 @@ impl Parser {
         }
     }
 
-    fn parse_identifier(&mut self) -> Result<Expression, String> {
+    fn parse_identifier(&mut self) -> Result<Expression, ParseError> {
         Ok(Expression::Identifier(Identifier {
             token: self.cur_token.clone(),
             value: self.cur_token.literal.clone(),
         }))
     }
 
-    fn parse_integer_literal(&mut self) -> Result<Expression, String> {
+    fn parse_integer_literal(&mut self) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         if let Ok(value) = self.cur_token.literal.parse::<i64>() {
             Ok(Expression::IntegerLiteral(IntegerLiteral {
@@ -226,18 +396,39 @@ impl Parser {
                 value: value,
             }))
         } else {
-            Err(format!(
-                "could not parse {} as integer",
-                self.cur_token.literal
-            ))
+            Err(ParseError {
+                kind: ParseErrorKind::InvalidInteger(self.cur_token.literal.clone()),
+                span: self.cur_token.span,
+            })
         }
     }
 
-    fn no_prefix_parse_fn_error(&self, t: &TokenType) -> String {
-        format!("no prefix parse function for {:?} found", t)
+    fn parse_float_literal(&mut self) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        if let Ok(value) = self.cur_token.literal.parse::<f64>() {
+            Ok(Expression::FloatLiteral(FloatLiteral {
+                token: token,
+                value: value,
+            }))
+        } else {
+            Err(ParseError {
+                kind: ParseErrorKind::InvalidFloat(self.cur_token.literal.clone()),
+                span: self.cur_token.span,
+            })
+        }
     }
 
-    fn parse_prefix_expression(&mut self) -> Result<Expression, String> {
+    fn no_prefix_parse_fn_error(&self, t: &TokenType) -> ParseError {
+        if let Some(err) = lex_error(&self.cur_token) {
+            return err;
+        }
+        ParseError {
+            kind: ParseErrorKind::NoPrefixParseFn(t.clone()),
+            span: self.cur_token.span,
+        }
+    }
+
+    fn parse_prefix_expression(&mut self) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         let operator = self.cur_token.literal.clone();
         self.next_token();
@@ -257,7 +448,7 @@ impl Parser {
         get_precedence(&self.cur_token.tk_type)
     }
 
-    fn parse_infix_expression(&mut self, left: Expression) -> Result<Expression, String> {
+    fn parse_infix_expression(&mut self, left: Expression) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         let operator = self.cur_token.literal.clone();
         let precedence = self.cur_precedence();
@@ -271,25 +462,45 @@ impl Parser {
         }))
     }
 
-    fn parse_boolean_literal(&self) -> Result<Expression, String> {
+    fn parse_boolean_literal(&self) -> Result<Expression, ParseError> {
         Ok(Expression::BooleanLiteral(BooleanLiteral {
             token: self.cur_token.clone(),
             value: self.cur_token_is(TokenType::TRUE),
         }))
     }
 
-    fn parse_grouped_expression(&mut self) -> Result<Expression, String> {
+    // Assignment is right-associative: `a = b = c` assigns c to b, then to a.
+    fn parse_assign_expression(&mut self, target: Expression) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        if !is_assignable(&target) {
+            return Err(ParseError {
+                kind: ParseErrorKind::InvalidAssignmentTarget(target.string()),
+                span: token.span,
+            });
+        }
+        let operator = self.cur_token.literal.clone();
+        self.next_token();
+        let value = self.parse_expression(Precedence::LOWEST)?;
+        Ok(Expression::AssignExpression(AssignExpression {
+            token: token,
+            target: Box::new(target),
+            operator: operator,
+            value: Box::new(value),
+        }))
+    }
+
+    fn parse_grouped_expression(&mut self) -> Result<Expression, ParseError> {
         self.next_token();
         let exp = self.parse_expression(Precedence::LOWEST)?;
         self.expect_peek(TokenType::RPAREN)?;
         Ok(exp)
     }
 
-    fn parse_if_expression(&mut self) -> Result<Expression, String> {
+    fn parse_if_expression(&mut self) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         self.expect_peek(TokenType::LPAREN)?;
         self.next_token();
-        let condition = self.parse_expression(Precedence::LOWEST)?;
+        let condition = self.parse_value_expression()?;
         self.expect_peek(TokenType::RPAREN)?;
         self.expect_peek(TokenType::LBRACE)?;
 
@@ -312,26 +523,37 @@ impl Parser {
         }))
     }
 
-    fn parse_block_statement(&mut self) -> Result<BlockStatement, String> {
+    fn parse_block_statement(&mut self) -> Result<BlockStatement, ParseError> {
         let token = self.cur_token.clone();
         let mut statements: Vec<Statement> = Vec::new();
         self.next_token();
 
         while !self.cur_token_is(TokenType::RBRACE) {
             if self.cur_token_is(TokenType::EOF) {
-                return Err(String::from("EOF"));
+                return Err(ParseError {
+                    kind: ParseErrorKind::UnexpectedEof,
+                    span: self.cur_token.span,
+                });
+            }
+            match self.parse_statement() {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => {
+                    self.errors.push(err);
+                    if self.synchronize() || self.cur_token_is(TokenType::EOF) {
+                        continue;
+                    }
+                }
             }
-            let stmt = self.parse_statement()?;
-            statements.push(stmt);
             self.next_token();
         }
         Ok(BlockStatement {
             token: token,
             statements: statements,
+            trailing: self.cur_token.trivia.clone(),
         })
     }
 
-    fn parse_function_literal(&mut self) -> Result<Expression, String> {
+    fn parse_function_literal(&mut self) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         self.expect_peek(TokenType::LPAREN)?;
 
@@ -339,16 +561,23 @@ impl Parser {
 
         self.expect_peek(TokenType::LBRACE)?;
 
-        let body = self.parse_block_statement()?;
+        // break and continue cannot cross a function boundary.
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let loop_control = self.loop_control.take();
+        let body = self.parse_block_statement();
+        self.loop_control = loop_control;
+        self.loop_depth = loop_depth;
+        let body = body?;
 
         Ok(Expression::FunctionLiteral(FunctionLiteral {
             token: token,
             parameters: parameters,
             body: body,
+            name: None,
         }))
     }
 
-    fn parse_function_parameters(&mut self) -> Result<Vec<Identifier>, String> {
+    fn parse_function_parameters(&mut self) -> Result<Vec<Identifier>, ParseError> {
         if self.peek_token_is(TokenType::RPAREN) {
             self.next_token();
             return Ok(Vec::new());
@@ -376,7 +605,7 @@ impl Parser {
         Ok(identfiers)
     }
 
-    fn parse_call_expression(&mut self, function: Expression) -> Result<Expression, String> {
+    fn parse_call_expression(&mut self, function: Expression) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         let arguements = self.parse_expression_list(TokenType::RPAREN)?;
 
@@ -387,7 +616,7 @@ impl Parser {
         }))
     }
 
-    fn parse_call_arguments(&mut self) -> Result<Vec<Expression>, String> {
+    fn parse_call_arguments(&mut self) -> Result<Vec<Expression>, ParseError> {
         let mut args: Vec<Expression> = Vec::new();
         if self.peek_token_is(TokenType::RPAREN) {
             self.next_token();
@@ -410,14 +639,14 @@ impl Parser {
         Ok(args)
     }
 
-    fn parse_string_literal(&self) -> Result<Expression, String> {
+    fn parse_string_literal(&self) -> Result<Expression, ParseError> {
         Ok(Expression::StringLiteral(StringLiteral {
             token: self.cur_token.clone(),
             value: self.cur_token.literal.clone(),
         }))
     }
 
-    fn parse_array_literal(&mut self) -> Result<Expression, String> {
+    fn parse_array_literal(&mut self) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         let elements = self.parse_expression_list(TokenType::RBRACKET)?;
 
@@ -427,7 +656,7 @@ impl Parser {
         }))
     }
 
-    fn parse_expression_list(&mut self, end: TokenType) -> Result<Vec<Expression>, String> {
+    fn parse_expression_list(&mut self, end: TokenType) -> Result<Vec<Expression>, ParseError> {
         let mut list: Vec<Expression> = Vec::new();
 
         if self.peek_token_is(end.clone()) {
@@ -450,7 +679,7 @@ impl Parser {
         Ok(list)
     }
 
-    fn parse_index_expression(&mut self, left: Expression) -> Result<Expression, String> {
+    fn parse_index_expression(&mut self, left: Expression) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
 
         self.next_token();
@@ -465,7 +694,7 @@ impl Parser {
         }))
     }
 
-    fn parse_hash_literal(&mut self) -> Result<Expression, String> {
+    fn parse_hash_literal(&mut self) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         let mut pairs: HashMap<Expression, Expression> = HashMap::new();
         while !self.peek_token_is(TokenType::RBRACE) {
@@ -495,23 +724,59 @@ impl Parser {
 #[derive(PartialOrd, PartialEq)]
 pub enum Precedence {
     LOWEST,
+    ASSIGN,      // = or +=
+    OR,          // ||
+    AND,         // &&
     EQUALS,      // ==
-    LESSGREATER, // > or <
+    LESSGREATER, // >, <, >= or <=
     SUM,         // +
-    PRODUCT,     // *
+    PRODUCT,     // *, / or %
     PREFIX,      // -x or !x
     CALL,        // myFunction(X)
     INDEX,       // array[index]
 }
 
-fn get_precedence(t: &TokenType) -> Precedence {
+pub fn get_precedence(t: &TokenType) -> Precedence {
     match t {
+        TokenType::ASSIGN
+        | TokenType::PLUSASSIGN
+        | TokenType::MINUSASSIGN
+        | TokenType::ASTERISKASSIGN
+        | TokenType::SLASHASSIGN
+        | TokenType::PERCENTASSIGN => Precedence::ASSIGN,
+        TokenType::OR => Precedence::OR,
+        TokenType::AND => Precedence::AND,
         TokenType::EQ | TokenType::NOTEQ => Precedence::EQUALS,
-        TokenType::LT | TokenType::GT => Precedence::LESSGREATER,
+        TokenType::LT | TokenType::GT | TokenType::LTEQ | TokenType::GTEQ => {
+            Precedence::LESSGREATER
+        }
         TokenType::PLUS | TokenType::MINUS => Precedence::SUM,
-        TokenType::SLASH | TokenType::ASTERISK => Precedence::PRODUCT,
+        TokenType::SLASH | TokenType::ASTERISK | TokenType::PERCENT => Precedence::PRODUCT,
         TokenType::LPAREN => Precedence::CALL,
         TokenType::LBRACKET => Precedence::INDEX,
         _ => Precedence::LOWEST,
     }
 }
+
+// Reports why the lexer rejected token, if it recorded a reason.
+fn lex_error(token: &Token) -> Option<ParseError> {
+    token.error.clone().map(|err| ParseError {
+        kind: ParseErrorKind::Lex(err),
+        span: token.span,
+    })
+}
+
+fn is_assignable(exp: &Expression) -> bool {
+    match exp {
+        Expression::Identifier(_) => true,
+        Expression::IndexExpression(IndexExpression { left, .. }) => is_assignable(left),
+        _ => false,
+    }
+}
+
+fn loop_control_in_expression(token: Token) -> ParseError {
+    ParseError {
+        kind: ParseErrorKind::InsideExpression(token.literal),
+        span: token.span,
+    }
+}
@@ -16,19 +16,18 @@ const PROMPT: &str = ">> ";
 pub fn start(input: &mut dyn Read, output: &mut dyn Write) {
     let mut scanner = BufReader::new(input);
     let mut constants: Rc<RefCell<Vec<Object>>> = Rc::new(RefCell::new(Vec::new()));
-    let globals: Rc<RefCell<Vec<Option<Object>>>> = Rc::new(RefCell::new(vec![None; GLOBALS_SIZE]));
+    let globals: Rc<RefCell<Vec<Option<Object>>>> = Rc::new(RefCell::new(Vec::new()));
     let symbol_table: Rc<RefCell<SymbolTable>> = Rc::new(RefCell::new(SymbolTable::new()));
 
-    for (i, v) in get_builtin_names().iter().enumerate() {
-        symbol_table.borrow_mut().define_builtin(i, v);
-    }
+    symbol_table.borrow_mut().define_builtins(&new_builtins());
 
     loop {
         write!(output, "{}", PROMPT).unwrap();
         output.flush().unwrap();
         let mut line = String::new();
-        if scanner.read_line(&mut line).is_err() {
-            return;
+        match scanner.read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
         }
         let l = Lexer::new(&line);
         let mut p = Parser::new(l);
@@ -55,6 +54,7 @@ pub fn start(input: &mut dyn Read, output: &mut dyn Write) {
                                 writeln!(output, "{}", MONKEY_FACE).unwrap();
                                 writeln!(output, "Woops! Executing bytecode failed:\n {}", err)
                                     .unwrap();
+                                write!(output, "{}", err.backtrace()).unwrap();
                             }
                         }
                     }
@@ -82,7 +82,7 @@ const MONKEY_FACE: &str = r#"
            '-----'
 "#;
 
-fn print_parser_errors(output: &mut dyn Write, errors: &[String]) {
+fn print_parser_errors(output: &mut dyn Write, errors: &[ParseError]) {
     writeln!(output, "{}", MONKEY_FACE).unwrap();
     writeln!(output, "Woops! We ran into some monkey business here!").unwrap();
     writeln!(output, " parser errros:").unwrap();
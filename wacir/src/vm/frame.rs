@@ -2,18 +2,19 @@
 
 use crate::code::*;
 use crate::object::*;
+use std::rc::*;
 
 #[derive(Clone)]
 pub struct Frame {
-    pub cl: Closure,
-    pub ip: i64,
+    pub cl: Rc<Closure>,
+    pub ip: usize, // offset of the next instruction to execute
     pub base_pointer: usize,
 }
 impl Frame {
-    pub fn new(cl: Closure, base_pointer: usize) -> Frame {
+    pub fn new(cl: Rc<Closure>, base_pointer: usize) -> Frame {
         Frame {
             cl: cl,
-            ip: -1,
+            ip: 0,
             base_pointer: base_pointer,
         }
     }
@@ -8,61 +8,42 @@ use crate::object::*;
 
 use std::cell::*;
 use std::collections::*;
-use std::convert::TryInto;
 use std::rc::*;
 
-const STACK_SIZE: usize = 2048;
-pub const GLOBALS_SIZE: usize = 65536;
-const MAX_FRAMES: usize = 1024;
 pub const TRUE: Object = Object::Boolean(Boolean { value: true });
 pub const FALSE: Object = Object::Boolean(Boolean { value: false });
 pub const NULL: Object = Object::Null(Null {});
 
+// Limits on how far a VM may grow. The stack, the call frames and the globals
+// all start empty and grow as the program needs them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VmConfig {
+    pub max_stack_size: usize, // values on the stack, across all frames
+    pub max_frames: usize,     // calls in progress, including the main program
+}
+impl Default for VmConfig {
+    fn default() -> Self {
+        VmConfig {
+            max_stack_size: 1 << 20,
+            max_frames: 1024,
+        }
+    }
+}
+
 pub struct Vm {
     pub constants: Rc<RefCell<Vec<Object>>>,
-    pub stack: Vec<Option<Object>>,
+    pub stack: Vec<Object>,
     pub sp: usize, // Always points to the next value. Top of stack is stack[sp-1]
     globals: Rc<RefCell<Vec<Option<Object>>>>,
     frames: Vec<Frame>,
-    frame_index: usize,
+    builtins: Rc<Builtins>,
+    arithmetic: Arithmetic,
+    config: VmConfig,
     pub last_popped_stack_elem: Option<Object>,
 }
 impl Vm {
     pub fn new(bytecode: Bytecode) -> Vm {
-        let main_fn = CompiledFunction {
-            instructions: bytecode.instuctions,
-            num_locals: 0,
-            num_parameters: 0,
-        };
-        let main_closure = Closure {
-            func: main_fn,
-            free: Vec::new(),
-        };
-        let mut frames: Vec<Frame> = vec![
-            Frame::new(
-                Closure {
-                    func: CompiledFunction {
-                        instructions: Instructions::new(),
-                        num_locals: 0,
-                        num_parameters: 0,
-                    },
-                    free: Vec::new(),
-                },
-                0
-            );
-            MAX_FRAMES
-        ];
-        let main_frame = Frame::new(main_closure, 0);
-        frames[0] = main_frame;
-        Vm {
-            constants: Rc::clone(&bytecode.constants),
-            stack: vec![None; STACK_SIZE],
-            sp: 0, // Always points to the next value. Top of stack is stack[sp-1]
-            globals: Rc::new(RefCell::new(vec![None; GLOBALS_SIZE])),
-            frames: frames,
-            frame_index: 1,
-            last_popped_stack_elem: None,
-        }
+        Vm::new_with_globals_store(bytecode, Rc::new(RefCell::new(Vec::new())))
     }
 
     // pub fn stack_top(&self) -> Option<Object> {
@@ -73,29 +54,103 @@ impl Vm {
     //     }
     // }
 
-    pub fn run(&mut self) -> Result<(), String> {
-        let mut ip: usize;
-        let mut ins: &Instructions;
-        let mut op: Opcode;
-        while self.current_frame().ip < self.current_frame().instructions().0.len() as i64 - 1 {
-            self.current_frame().ip += 1;
+    // Selects what integer arithmetic does on overflow. Checked by default.
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
 
-            ip = self.current_frame().ip as usize;
-            ins = self.current_frame().instructions();
-            op = Opcode::from(ins.0[ip]);
+    pub fn set_config(&mut self, config: VmConfig) {
+        self.config = config;
+    }
+
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        let result = self.execute(0);
+        result.map_err(|err| self.locate(err, 0))
+    }
+
+    // Calls a closure or builtin from the host and runs it to completion.
+    pub fn call(&mut self, func: Object, args: Vec<Object>) -> Result<Object, RuntimeError> {
+        let saved_sp = self.sp;
+        let depth = self.frames.len();
+        let num_args = args.len();
+        let mut result = self.push(func);
+        for arg in args {
+            if result.is_ok() {
+                result = self.push(arg);
+            }
+        }
+        let result = result
+            .and_then(|_| self.execute_call(num_args))
+            .and_then(|_| self.execute(depth))
+            .map_err(|err| self.locate(err, depth));
+        match result {
+            Ok(_) => Ok(self.pop()),
+            Err(err) => {
+                self.frames.truncate(depth);
+                self.sp = saved_sp;
+                Err(err)
+            }
+        }
+    }
+
+    // Attaches the span of the instruction that was executing when err was
+    // raised, and a trace frame for each call above depth that was in progress.
+    // A tail call reuses its caller's frame, so the caller does not appear.
+    fn locate(&self, mut err: RuntimeError, depth: usize) -> RuntimeError {
+        for frame in self.frames[depth..].iter().rev() {
+            // ip is already past the instruction that failed or made the call.
+            let offset = frame.ip.saturating_sub(1);
+            if let Some(span) = frame.cl.func.source_map.lookup(offset) {
+                err = err.at(span);
+            }
+            err = err.unwind(frame.cl.func.name.as_deref().unwrap_or("<anonymous>"));
+        }
+        err
+    }
+
+    // Runs until the frame stack shrinks back to `depth` frames.
+    fn execute(&mut self, depth: usize) -> Result<(), RuntimeError> {
+        while self.frames.len() > depth {
+            // Holding the closure here keeps its instructions borrowed while
+            // execute_frame pushes and pops frames.
+            let cl = Rc::clone(&self.current_frame().cl);
+            let mut ip = self.current_frame().ip;
+            match self.execute_frame(&cl, &mut ip) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => {
+                    self.current_frame().ip = ip;
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Runs the current frame until another frame becomes current, or until it
+    // runs out of instructions, in which case it returns false. ip and the
+    // base pointer are kept in locals, and ip is only written back to the
+    // frame before a call.
+    fn execute_frame(&mut self, cl: &Rc<Closure>, ip: &mut usize) -> Result<bool, RuntimeError> {
+        let ins = &cl.func.instructions.0[..];
+        let base_pointer = self.current_frame().base_pointer;
+        while *ip < ins.len() {
+            let op = Opcode::from(ins[*ip]);
+            *ip += 1;
             match op {
                 Opcode::OpConstant => {
-                    let src = ins.0[(ip + 1)..(ip + 3)].try_into().expect("wrong size");
-                    let const_index = read_u16(src);
-                    self.current_frame().ip += 2;
-                    let obj = self.constants.borrow()[const_index as usize].clone();
+                    let const_index = read_u16_operand(ins, *ip);
+                    *ip += 2;
+                    let obj = self.constants.borrow()[const_index].clone();
                     self.push(obj)?;
                 }
-                Opcode::OpAdd | Opcode::OpSub | Opcode::OpMul | Opcode::OpDiv => {
-                    self.execute_binary_operation(op)?;
+                Opcode::OpAdd | Opcode::OpSub | Opcode::OpMul | Opcode::OpDiv | Opcode::OpMod => {
+                    if !self.execute_integer_fast_path(op) {
+                        self.execute_binary_operation(op)?;
+                    }
                 }
                 Opcode::OpPop => {
-                    self.pop();
+                    self.last_popped_stack_elem = Some(self.pop());
                 }
                 Opcode::OpTrue => {
                     self.push(TRUE)?;
@@ -103,8 +158,15 @@ impl Vm {
                 Opcode::OpFalse => {
                     self.push(FALSE)?;
                 }
-                Opcode::OpEqual | Opcode::OpNotEqual | Opcode::OpGreaterThan => {
-                    self.execute_comparison(op)?;
+                Opcode::OpEqual
+                | Opcode::OpNotEqual
+                | Opcode::OpGreaterThan
+                | Opcode::OpLessThan
+                | Opcode::OpGreaterEqual
+                | Opcode::OpLessEqual => {
+                    if !self.execute_integer_fast_path(op) {
+                        self.execute_comparison(op)?;
+                    }
                 }
                 Opcode::OpBang => {
                     self.execute_bang_operator()?;
@@ -113,42 +175,53 @@ impl Vm {
                     self.execute_minus_operator()?;
                 }
                 Opcode::OpJump => {
-                    let src = ins.0[(ip + 1)..(ip + 3)].try_into().expect("wrong size");
-                    let pos = read_u16(src) as i64;
-                    self.current_frame().ip = pos - 1;
+                    *ip = read_u16_operand(ins, *ip);
                 }
                 Opcode::OpJumpNotTruthy => {
-                    let src = ins.0[(ip + 1)..(ip + 3)].try_into().expect("wrong size");
-                    let pos = read_u16(src) as i64;
-                    self.current_frame().ip += 2;
+                    let pos = read_u16_operand(ins, *ip);
+                    *ip += 2;
                     let condition = self.pop();
                     if !is_truthy(&condition) {
-                        self.current_frame().ip = pos - 1;
+                        *ip = pos;
+                    }
+                }
+                Opcode::OpJumpNotTruthyOrPop | Opcode::OpJumpTruthyOrPop => {
+                    let pos = read_u16_operand(ins, *ip);
+                    *ip += 2;
+                    let truthy = is_truthy(&self.stack[self.sp - 1]);
+                    if truthy == (op == Opcode::OpJumpTruthyOrPop) {
+                        *ip = pos;
+                    } else {
+                        self.pop();
                     }
                 }
                 Opcode::OpNull => {
                     self.push(NULL)?;
                 }
                 Opcode::OpSetGlobal => {
-                    let src = ins.0[(ip + 1)..(ip + 3)].try_into().expect("wrong size");
-                    let global_index = read_u16(src) as usize;
-                    self.current_frame().ip += 2;
-                    self.globals.borrow_mut()[global_index] = self.pop();
+                    let global_index = read_u16_operand(ins, *ip);
+                    *ip += 2;
+                    let value = self.pop();
+                    // The REPL prints the value of a let statement too.
+                    self.last_popped_stack_elem = Some(value.clone());
+                    let mut globals = self.globals.borrow_mut();
+                    if global_index >= globals.len() {
+                        globals.resize(global_index + 1, None);
+                    }
+                    globals[global_index] = Some(value);
                 }
                 Opcode::OpGetGlobal => {
-                    let src = ins.0[(ip + 1)..(ip + 3)].try_into().expect("wrong size");
-                    let global_index = read_u16(src) as usize;
-                    self.current_frame().ip += 2;
-                    let obj = self.globals.borrow_mut()[global_index]
-                        .as_ref()
-                        .unwrap()
-                        .clone(); // TODO: can unwrap?
+                    let global_index = read_u16_operand(ins, *ip);
+                    *ip += 2;
+                    let obj = match self.globals.borrow().get(global_index) {
+                        Some(Some(obj)) => obj.clone(),
+                        _ => return Err(RuntimeErrorKind::UninitializedValue.into()),
+                    };
                     self.push(obj)?;
                 }
                 Opcode::OpArray => {
-                    let src = ins.0[(ip + 1)..(ip + 3)].try_into().expect("wrong size");
-                    let num_elements = read_u16(src) as usize;
-                    self.current_frame().ip += 2;
+                    let num_elements = read_u16_operand(ins, *ip);
+                    *ip += 2;
 
                     let array = self.build_array(self.sp - num_elements, self.sp);
                     self.sp -= num_elements;
@@ -156,9 +229,8 @@ impl Vm {
                     self.push(array)?;
                 }
                 Opcode::OpHash => {
-                    let src = ins.0[(ip + 1)..(ip + 3)].try_into().expect("wrong size");
-                    let num_elements = read_u16(src) as usize;
-                    self.current_frame().ip += 2;
+                    let num_elements = read_u16_operand(ins, *ip);
+                    *ip += 2;
                     let hash = self.build_hash(self.sp - num_elements, self.sp)?;
                     self.sp -= num_elements;
                     self.push(hash)?;
@@ -169,108 +241,216 @@ impl Vm {
                     self.execute_index_expression(&left, &index)?;
                 }
                 Opcode::OpCall => {
-                    let num_args = ins.0[ip + 1] as usize;
-                    self.current_frame().ip += 1;
+                    let num_args = ins[*ip] as usize;
+                    *ip += 1;
+                    self.current_frame().ip = *ip;
                     self.execute_call(num_args)?;
+                    return Ok(true);
+                }
+                Opcode::OpTailCall => {
+                    let num_args = ins[*ip] as usize;
+                    *ip += 1;
+                    self.current_frame().ip = *ip;
+                    self.execute_tail_call(num_args)?;
+                    return Ok(true);
                 }
                 Opcode::OpReturnValue => {
                     let return_value = self.pop();
+                    // A return in the main program ends it, yielding the value.
+                    if self.frames.len() == 1 {
+                        self.last_popped_stack_elem = Some(return_value);
+                        *ip = ins.len();
+                        return Ok(false);
+                    }
                     let base_pointer = self.pop_frame().base_pointer;
-                    self.sp = base_pointer - 1;
-                    self.push(return_value.unwrap())?;
+                    self.stack[base_pointer - 1] = return_value;
+                    self.sp = base_pointer;
+                    return Ok(true);
                 }
                 Opcode::OpReturn => {
+                    if self.frames.len() == 1 {
+                        self.last_popped_stack_elem = Some(NULL);
+                        *ip = ins.len();
+                        return Ok(false);
+                    }
                     let base_pointer = self.pop_frame().base_pointer;
-                    self.sp = base_pointer - 1;
-
-                    self.push(NULL)?;
+                    self.stack[base_pointer - 1] = NULL;
+                    self.sp = base_pointer;
+                    return Ok(true);
                 }
                 Opcode::OpSetLocal => {
-                    let local_index = ins.0[ip + 1] as usize;
-                    self.current_frame().ip += 1;
+                    let local_index = ins[*ip] as usize;
+                    *ip += 1;
 
-                    let base_pointer = self.current_frame().base_pointer;
-                    self.stack[base_pointer + local_index] = self.pop();
+                    let value = self.pop();
+                    let slot = &mut self.stack[base_pointer + local_index];
+                    if let Object::Upvalue(upvalue) = slot {
+                        *upvalue.value.borrow_mut() = value;
+                    } else {
+                        *slot = value;
+                    }
                 }
                 Opcode::OpGetLocal => {
-                    let local_index = ins.0[ip + 1] as usize;
-                    self.current_frame().ip += 1;
-                    let base_pointer = self.current_frame().base_pointer;
-                    let obj = self.stack[base_pointer + local_index].as_ref().unwrap(); // TODO: can unwrap?
-                    let obj_clone = obj.clone();
-                    self.push(obj_clone)?;
+                    let local_index = ins[*ip] as usize;
+                    *ip += 1;
+                    let obj = match &self.stack[base_pointer + local_index] {
+                        Object::Upvalue(upvalue) => upvalue.value.borrow().clone(),
+                        obj => obj.clone(),
+                    };
+                    if let Object::Uninitialized(_) = obj {
+                        return Err(RuntimeErrorKind::UninitializedValue.into());
+                    }
+                    self.push(obj)?;
                 }
                 Opcode::OpGetBuiltin => {
-                    let builtin_index = ins.0[ip + 1] as usize;
-                    self.current_frame().ip += 1;
+                    let builtin_index = ins[*ip] as usize;
+                    *ip += 1;
 
-                    let definition =
-                        get_builtin_by_name(&get_builtin_names()[builtin_index]).unwrap(); //TODO: can unwrap?
+                    let definition = Rc::clone(self.builtins.get_by_index(builtin_index).unwrap()); //TODO: can unwrap?
 
                     self.push(Object::Builtin(definition))?;
                 }
                 Opcode::OpClosure => {
-                    let src = ins.0[(ip + 1)..(ip + 3)].try_into().expect("wrong size");
-                    let const_index = read_u16(src) as usize;
-                    let num_free = ins.0[ip + 3] as usize;
-                    self.current_frame().ip += 3;
+                    let const_index = read_u16_operand(ins, *ip);
+                    let num_free = ins[*ip + 2] as usize;
+                    *ip += 3;
 
                     self.push_closure(const_index, num_free)?;
                 }
                 Opcode::OpGetFree => {
-                    let free_index = ins.0[ip + 1] as usize;
-                    self.current_frame().ip += 1;
+                    let free_index = ins[*ip] as usize;
+                    *ip += 1;
 
-                    let current_closure = &self.current_frame().cl;
-                    let free = current_closure.free[free_index].as_ref().unwrap().clone();
+                    let free = cl.free[free_index].borrow().clone();
+                    if let Object::Uninitialized(_) = free {
+                        return Err(RuntimeErrorKind::UninitializedValue.into());
+                    }
                     self.push(free)?;
                 }
+                Opcode::OpSetFree => {
+                    let free_index = ins[*ip] as usize;
+                    *ip += 1;
+
+                    let value = self.pop();
+                    *cl.free[free_index].borrow_mut() = value;
+                }
+                Opcode::OpCaptureLocal => {
+                    let local_index = ins[*ip] as usize;
+                    *ip += 1;
+
+                    // An uninitialized local is boxed as well: a function
+                    // captures the binding it is about to be stored in.
+                    let slot = base_pointer + local_index;
+                    let upvalue = match std::mem::replace(&mut self.stack[slot], NULL) {
+                        Object::Upvalue(upvalue) => upvalue,
+                        obj => Upvalue {
+                            value: Rc::new(RefCell::new(obj)),
+                        },
+                    };
+                    self.stack[slot] = Object::Upvalue(upvalue.clone());
+                    self.push(Object::Upvalue(upvalue))?;
+                }
+                Opcode::OpCaptureFree => {
+                    let free_index = ins[*ip] as usize;
+                    *ip += 1;
+
+                    let value = Rc::clone(&cl.free[free_index]);
+                    self.push(Object::Upvalue(Upvalue { value: value }))?;
+                }
+                Opcode::OpSetIndex => {
+                    let depth = ins[*ip] as usize;
+                    let operator = match AssignOperator::decode(ins[*ip + 1]) {
+                        Some(operator) => operator,
+                        None => {
+                            return Err(RuntimeErrorKind::InvalidAssignment(format!(
+                                "operator {}",
+                                ins[*ip + 1]
+                            ))
+                            .into())
+                        }
+                    };
+                    *ip += 2;
+
+                    let value = self.pop();
+                    let mut path = Vec::with_capacity(depth);
+                    for _ in 0..depth {
+                        path.push(self.pop());
+                    }
+                    path.reverse();
+                    let container = self.pop();
+
+                    let assigned = self.assign_index(&container, &path, value, operator)?;
+                    self.push(assigned)?;
+                }
+                Opcode::OpCurrentClosure => {
+                    self.push(Object::Closure(Rc::clone(cl)))?;
+                }
             }
         }
-        Ok(())
+        Ok(false)
     }
 
-    pub fn push(&mut self, o: Object) -> Result<(), String> {
-        if self.sp >= STACK_SIZE {
-            return Err(String::from("stack overflow"));
+    pub fn push(&mut self, o: Object) -> Result<(), RuntimeError> {
+        if self.sp < self.stack.len() {
+            self.stack[self.sp] = o;
+        } else if self.sp < self.config.max_stack_size {
+            self.stack.push(o);
+        } else {
+            return Err(RuntimeErrorKind::StackOverflow.into());
         }
-        self.stack[self.sp] = Some(o);
         self.sp += 1;
         Ok(())
     }
 
-    pub fn pop(&mut self) -> Option<Object> {
-        let o = std::mem::replace(&mut self.stack[self.sp - 1], None);
-        self.last_popped_stack_elem = o.clone();
+    pub fn pop(&mut self) -> Object {
         self.sp -= 1;
-        o
+        std::mem::replace(&mut self.stack[self.sp], NULL)
+    }
+
+    // Applies op to two integers on top of the stack in place, as long as
+    // that cannot fail. Returns false, leaving the stack alone, for anything
+    // else, including overflow and division.
+    fn execute_integer_fast_path(&mut self, op: Opcode) -> bool {
+        let result = match (&self.stack[self.sp - 2], &self.stack[self.sp - 1]) {
+            (Object::Integer(left), Object::Integer(right)) => {
+                integer_fast_path(op, left.value, right.value)
+            }
+            _ => None,
+        };
+        match result {
+            Some(result) => {
+                self.sp -= 1;
+                self.stack[self.sp - 1] = result;
+                true
+            }
+            None => false,
+        }
     }
 
     // pub fn last_popped_stack_elem(&self) -> Option<Object> {
     //     self.stack[self.sp].clone()
     // }
 
-    fn execute_binary_operation(&mut self, op: Opcode) -> Result<(), String> {
+    fn execute_binary_operation(&mut self, op: Opcode) -> Result<(), RuntimeError> {
         let right = self.pop();
         let left = self.pop();
-        if let Some(Object::Integer(Integer { value })) = &left {
+        if let Some((left_value, right_value)) = float_operands(&left, &right) {
+            return self.execute_binary_float_operation(op, left_value, right_value);
+        }
+        if let Object::Integer(Integer { value }) = &left {
             let left_value = value;
-            if let Some(Object::Integer(Integer { value })) = &right {
+            if let Object::Integer(Integer { value }) = &right {
                 let right_value = value;
                 return self.execute_binary_integer_operation(op, *left_value, *right_value);
             }
-        } else if let Some(Object::StringObj(StringObj { value })) = &left {
+        } else if let Object::StringObj(StringObj { value }) = &left {
             let left_value = value;
-            if let Some(Object::StringObj(StringObj { value })) = &right {
+            if let Object::StringObj(StringObj { value }) = &right {
                 let right_value = value;
                 return self.execute_binary_string_operation(op, left_value, right_value);
             }
         }
-        Err(format!(
-            "unsupported types for binary operation: {} {}",
-            get_type(&left),
-            get_type(&right),
-        ))
+        Err(infix_operator_error(&left, op, &right))
     }
 
     fn execute_binary_integer_operation(
@@ -278,38 +458,75 @@ impl Vm {
         op: Opcode,
         left_value: i64,
         right_value: i64,
-    ) -> Result<(), String> {
+    ) -> Result<(), RuntimeError> {
+        let result = integer_arithmetic(
+            operator_symbol(op),
+            left_value,
+            right_value,
+            self.arithmetic,
+        )?;
+        self.push(Object::Integer(Integer { value: result }))?;
+        Ok(())
+    }
+
+    fn execute_binary_float_operation(
+        &mut self,
+        op: Opcode,
+        left_value: f64,
+        right_value: f64,
+    ) -> Result<(), RuntimeError> {
         let result = match op {
             Opcode::OpAdd => left_value + right_value,
             Opcode::OpSub => left_value - right_value,
             Opcode::OpMul => left_value * right_value,
             Opcode::OpDiv => left_value / right_value,
-            _ => return Err(format!("unknown integer operator: {:?}", op)),
+            Opcode::OpMod => left_value % right_value,
+            _ => {
+                return Err(RuntimeErrorKind::UnknownInfixOperator {
+                    left: String::from("FLOAT"),
+                    operator: String::from(operator_symbol(op)),
+                    right: String::from("FLOAT"),
+                }
+                .into())
+            }
         };
-        self.push(Object::Integer(Integer { value: result }))?;
+        self.push(Object::Float(Float { value: result }))?;
         Ok(())
     }
 
-    fn execute_comparison(&mut self, op: Opcode) -> Result<(), String> {
+    fn execute_comparison(&mut self, op: Opcode) -> Result<(), RuntimeError> {
         let right = self.pop();
         let left = self.pop();
 
-        if let Some(Object::Integer(Integer { value })) = right {
+        if let Some((left_value, right_value)) = float_operands(&left, &right) {
+            return self.execute_float_comparison(op, left_value, right_value);
+        }
+
+        if let Object::Integer(Integer { value }) = right {
             let right_value = value;
-            if let Some(Object::Integer(Integer { value })) = left {
+            if let Object::Integer(Integer { value }) = left {
                 let left_value = value;
                 return self.execute_integer_comparison(op, left_value, right_value);
             }
         }
 
+        if let (Object::StringObj(l), Object::StringObj(r)) = (&left, &right) {
+            let result = match op {
+                Opcode::OpEqual => l.value == r.value,
+                Opcode::OpNotEqual => l.value != r.value,
+                Opcode::OpGreaterThan => l.value > r.value,
+                Opcode::OpLessThan => l.value < r.value,
+                Opcode::OpGreaterEqual => l.value >= r.value,
+                _ => l.value <= r.value,
+            };
+            return self.push(native_bool_to_boolean_object(result));
+        }
+
         match op {
             Opcode::OpEqual => return self.push(native_bool_to_boolean_object(left == right)),
             Opcode::OpNotEqual => return self.push(native_bool_to_boolean_object(left != right)),
             _ => {
-                return Err(format!(
-                    "unknown operator: {:?} ({:?} {:?})",
-                    op, left, right
-                ));
+                return Err(infix_operator_error(&left, op, &right));
             }
         }
     }
@@ -319,74 +536,109 @@ impl Vm {
         op: Opcode,
         left: i64,
         right: i64,
-    ) -> Result<(), String> {
+    ) -> Result<(), RuntimeError> {
+        match op {
+            Opcode::OpEqual => self.push(native_bool_to_boolean_object(right == left)),
+            Opcode::OpNotEqual => self.push(native_bool_to_boolean_object(right != left)),
+            Opcode::OpGreaterThan => self.push(native_bool_to_boolean_object(left > right)),
+            Opcode::OpLessThan => self.push(native_bool_to_boolean_object(left < right)),
+            Opcode::OpGreaterEqual => self.push(native_bool_to_boolean_object(left >= right)),
+            Opcode::OpLessEqual => self.push(native_bool_to_boolean_object(left <= right)),
+            _ => {
+                return Err(RuntimeErrorKind::UnknownInfixOperator {
+                    left: String::from("INTEGER"),
+                    operator: String::from(operator_symbol(op)),
+                    right: String::from("INTEGER"),
+                }
+                .into())
+            }
+        }
+    }
+
+    fn execute_float_comparison(
+        &mut self,
+        op: Opcode,
+        left: f64,
+        right: f64,
+    ) -> Result<(), RuntimeError> {
         match op {
             Opcode::OpEqual => self.push(native_bool_to_boolean_object(right == left)),
             Opcode::OpNotEqual => self.push(native_bool_to_boolean_object(right != left)),
             Opcode::OpGreaterThan => self.push(native_bool_to_boolean_object(left > right)),
-            _ => return Err(format!("unknown operator: {:?}", op)),
+            Opcode::OpLessThan => self.push(native_bool_to_boolean_object(left < right)),
+            Opcode::OpGreaterEqual => self.push(native_bool_to_boolean_object(left >= right)),
+            Opcode::OpLessEqual => self.push(native_bool_to_boolean_object(left <= right)),
+            _ => {
+                return Err(RuntimeErrorKind::UnknownInfixOperator {
+                    left: String::from("FLOAT"),
+                    operator: String::from(operator_symbol(op)),
+                    right: String::from("FLOAT"),
+                }
+                .into())
+            }
         }
     }
 
-    fn execute_bang_operator(&mut self) -> Result<(), String> {
+    fn execute_bang_operator(&mut self) -> Result<(), RuntimeError> {
         let operand = self.pop();
         match operand {
-            Some(TRUE) => self.push(FALSE),
-            Some(FALSE) => self.push(TRUE),
-            Some(NULL) => self.push(TRUE),
+            TRUE => self.push(FALSE),
+            FALSE => self.push(TRUE),
+            NULL => self.push(TRUE),
             _ => self.push(FALSE),
         }
     }
-    fn execute_minus_operator(&mut self) -> Result<(), String> {
+    fn execute_minus_operator(&mut self) -> Result<(), RuntimeError> {
         let operand = self.pop();
         match operand {
-            Some(Object::Integer(Integer { value })) => {
-                return self.push(Object::Integer(Integer { value: -value }));
+            Object::Integer(Integer { value }) => {
+                let value = integer_negate(value, self.arithmetic)?;
+                return self.push(Object::Integer(Integer { value: value }));
+            }
+            Object::Float(Float { value }) => {
+                return self.push(Object::Float(Float { value: -value }));
             }
             _ => {
-                return Err(format!(
-                    "unsupported type for negation: {}",
-                    get_type(&operand)
-                ))
+                return Err(RuntimeErrorKind::UnknownPrefixOperator {
+                    operator: String::from("-"),
+                    right: String::from(operand.get_type()),
+                }
+                .into())
             }
         }
     }
 
     pub fn new_with_globals_store(bytecode: Bytecode, s: Rc<RefCell<Vec<Option<Object>>>>) -> Vm {
+        Vm::new_with_state(bytecode, s, Rc::new(new_builtins()))
+    }
+
+    pub fn new_with_state(
+        bytecode: Bytecode,
+        s: Rc<RefCell<Vec<Option<Object>>>>,
+        builtins: Rc<Builtins>,
+    ) -> Vm {
         let main_fn = CompiledFunction {
-            instructions: bytecode.instuctions.clone(),
+            instructions: bytecode.instuctions,
             num_locals: 0,
             num_parameters: 0,
+            source_map: bytecode.source_map,
+            name: Some(String::from("<main>")),
         };
-        let main_closure = Closure {
-            func: main_fn,
+        let main_closure = Rc::new(Closure {
+            func: Rc::new(main_fn),
             free: Vec::new(),
-        };
-        let main_frame = Frame::new(main_closure, 0);
-        let mut frames: Vec<Frame> = vec![
-            Frame::new(
-                Closure {
-                    func: CompiledFunction {
-                        instructions: Instructions::new(),
-                        num_locals: 0,
-                        num_parameters: 0,
-                    },
-                    free: Vec::new()
-                },
-                0
-            );
-            MAX_FRAMES
-        ];
-        frames[0] = main_frame;
+        });
 
         Vm {
             constants: bytecode.constants,
 
-            stack: vec![None; STACK_SIZE],
+            stack: Vec::new(),
             sp: 0, // Always points to the next value. Top of stack is stack[sp-1]
             globals: Rc::clone(&s),
-            frames: frames,
-            frame_index: 1,
+            frames: vec![Frame::new(main_closure, 0)],
+            builtins: builtins,
+            arithmetic: Arithmetic::default(),
+            config: VmConfig::default(),
             last_popped_stack_elem: None,
         }
     }
@@ -396,9 +648,14 @@ impl Vm {
         op: Opcode,
         left: &str,
         right: &str,
-    ) -> Result<(), String> {
+    ) -> Result<(), RuntimeError> {
         if op != Opcode::OpAdd {
-            return Err(format!("unknown string operator: {:?}", op));
+            return Err(RuntimeErrorKind::UnknownInfixOperator {
+                left: String::from("STRING"),
+                operator: String::from(operator_symbol(op)),
+                right: String::from("STRING"),
+            }
+            .into());
         }
         self.push(Object::StringObj(StringObj {
             value: format!("{}{}", left, right),
@@ -409,58 +666,55 @@ impl Vm {
         let mut elements: Vec<Object> = vec![NULL; end_index - start_index];
         let mut i = start_index;
         while i < end_index {
-            elements[i - start_index] = std::mem::replace(&mut self.stack[i], None).unwrap();
+            elements[i - start_index] = std::mem::replace(&mut self.stack[i], NULL);
             i += 1;
         }
-        Object::Array(Array { elements: elements })
+        Object::Array(Rc::new(RefCell::new(Array { elements: elements })))
     }
 
-    fn build_hash(&self, start_index: usize, end_index: usize) -> Result<Object, String> {
+    fn build_hash(&self, start_index: usize, end_index: usize) -> Result<Object, RuntimeError> {
         let mut hashed_pairs: HashMap<HashKey, Object> = HashMap::new();
         let mut i = start_index;
         while i < end_index {
             let key = &self.stack[i];
             let value = &self.stack[i + 1];
 
-            if let Some(key_obj) = key {
-                if let Some(hashable) = key_obj.as_hashable() {
-                    let hash_key = hashable.hash_key();
-                    if let Some(value_obj) = value {
-                        hashed_pairs.insert(hash_key, value_obj.clone());
-                    } else {
-                        return Err(format!("uninitialized value"));
-                    }
-                } else {
-                    return Err(format!("unusable as hash key: {}", get_type(&key)));
-                }
+            if let Some(hashable) = key.as_hashable() {
+                hashed_pairs.insert(hashable.hash_key(), value.clone());
             } else {
-                return Err(format!("unusable as hash key: {}", get_type(&key)));
+                return Err(
+                    RuntimeErrorKind::UnusableAsHashKey(String::from(key.get_type())).into(),
+                );
             }
 
             i += 2;
         }
 
-        Ok(Object::Hash(Hash {
+        Ok(Object::Hash(Rc::new(RefCell::new(Hash {
             pairs: hashed_pairs,
-        }))
+        }))))
     }
 
     fn execute_index_expression(
         &mut self,
-        left: &Option<Object>,
-        index: &Option<Object>,
-    ) -> Result<(), String> {
-        if let Some(Object::Array(Array { elements })) = left {
-            if let Some(Object::Integer(Integer { value })) = index {
-                return self.execute_array_index(elements, *value);
+        left: &Object,
+        index: &Object,
+    ) -> Result<(), RuntimeError> {
+        if let Object::Array(array) = left {
+            if let Object::Integer(Integer { value }) = index {
+                return self.execute_array_index(&array.borrow().elements, *value);
             }
-        } else if let Some(Object::Hash(Hash { pairs })) = left {
-            return self.execute_hash_index(pairs, index);
+        } else if let Object::Hash(hash) = left {
+            return self.execute_hash_index(&hash.borrow().pairs, index);
         }
-        Err(format!("index operator not supported: {}", get_type(&left)))
+        Err(RuntimeErrorKind::IndexNotSupported(String::from(left.get_type())).into())
     }
 
-    fn execute_array_index(&mut self, elements: &Vec<Object>, index: i64) -> Result<(), String> {
+    fn execute_array_index(
+        &mut self,
+        elements: &Vec<Object>,
+        index: i64,
+    ) -> Result<(), RuntimeError> {
         let max = elements.len() as i64 - 1;
         if index < 0 || index > max {
             return self.push(NULL);
@@ -472,38 +726,37 @@ impl Vm {
     fn execute_hash_index(
         &mut self,
         pairs: &HashMap<HashKey, Object>,
-        index: &Option<Object>,
-    ) -> Result<(), String> {
-        if let Some(key) = index {
-            if let Some(hash_key) = key.as_hashable() {
-                if let Some(value) = pairs.get(&hash_key.hash_key()) {
-                    return self.push(value.clone());
-                } else {
-                    return self.push(NULL);
-                }
+        index: &Object,
+    ) -> Result<(), RuntimeError> {
+        if let Some(hash_key) = index.as_hashable() {
+            if let Some(value) = pairs.get(&hash_key.hash_key()) {
+                return self.push(value.clone());
             } else {
-                return Err(format!("unusable as hash key: {}", get_type(&index)));
+                return self.push(NULL);
             }
         } else {
-            return Err(format!("unusable as hash key: {}", get_type(&index)));
+            return Err(RuntimeErrorKind::UnusableAsHashKey(String::from(index.get_type())).into());
         }
     }
 
     fn current_frame(&mut self) -> &mut Frame {
-        &mut self.frames[self.frame_index - 1]
+        let index = self.frames.len() - 1;
+        &mut self.frames[index]
     }
 
-    fn push_frame(&mut self, f: Frame) {
-        self.frames[self.frame_index] = f;
-        self.frame_index += 1;
+    fn push_frame(&mut self, f: Frame) -> Result<(), RuntimeError> {
+        if self.frames.len() >= self.config.max_frames {
+            return Err(RuntimeErrorKind::MaxRecursionDepth.into());
+        }
+        self.frames.push(f);
+        Ok(())
     }
 
     fn pop_frame(&mut self) -> Frame {
-        self.frame_index -= 1;
-        self.frames[self.frame_index].clone()
+        self.frames.pop().unwrap()
     }
 
-    // fn call_function(&mut self, func: CompiledFunction, num_args: usize) -> Result<(), String> {
+    // fn call_function(&mut self, func: CompiledFunction, num_args: usize) -> Result<(), RuntimeError> {
     //     if num_args != func.num_parameters {
     //         return Err(format!(
     //             "wrong number of arguments: want={}, got={}",
@@ -517,53 +770,141 @@ impl Vm {
     //     return Ok(());
     // }
 
-    fn execute_call(&mut self, num_args: usize) -> Result<(), String> {
+    fn execute_call(&mut self, num_args: usize) -> Result<(), RuntimeError> {
         let callee = self.stack[self.sp - 1 - num_args].clone();
-        if let Some(Object::Closure(cl)) = callee {
+        if let Object::Closure(cl) = callee {
             return self.call_closure(&cl, num_args);
-        } else if let Some(Object::Builtin(builtin)) = callee {
+        } else if let Object::Builtin(builtin) = callee {
             return self.call_builtin(&builtin, num_args);
         } else {
-            return Err(format!("calling non-function and no-built-in"));
+            return Err(RuntimeErrorKind::NotAFunction(String::from(callee.get_type())).into());
+        }
+    }
+
+    // Like execute_call, but a closure callee takes over the current frame:
+    // callee and arguments are moved down to where the caller's callee and
+    // arguments were, so the stack does not grow.
+    fn execute_tail_call(&mut self, num_args: usize) -> Result<(), RuntimeError> {
+        let callee = self.stack[self.sp - 1 - num_args].clone();
+        if let Object::Closure(cl) = callee {
+            if num_args != cl.func.num_parameters {
+                return Err(RuntimeErrorKind::WrongArgumentCount {
+                    want: cl.func.num_parameters,
+                    got: num_args,
+                }
+                .into());
+            }
+
+            let base_pointer = self.current_frame().base_pointer;
+            self.reserve_stack(base_pointer + cl.func.num_locals)?;
+            let start = self.sp - 1 - num_args;
+            for i in 0..=num_args {
+                self.stack[base_pointer - 1 + i] =
+                    std::mem::replace(&mut self.stack[start + i], NULL);
+            }
+            let num_locals = cl.func.num_locals;
+            *self.current_frame() = Frame::new(cl, base_pointer);
+            self.clear_locals(base_pointer + num_args, base_pointer + num_locals);
+            self.sp = base_pointer + num_locals;
+            Ok(())
+        } else {
+            self.execute_call(num_args)
+        }
+    }
+
+    // Grows the stack to at least len slots.
+    fn reserve_stack(&mut self, len: usize) -> Result<(), RuntimeError> {
+        if len > self.config.max_stack_size {
+            return Err(RuntimeErrorKind::StackOverflow.into());
+        }
+        if len > self.stack.len() {
+            self.stack.resize(len, NULL);
+        }
+        Ok(())
+    }
+
+    // Empties the local slots of a new frame. A slot left over from an earlier
+    // frame may hold an upvalue, which the new frame must not write through.
+    fn clear_locals(&mut self, start: usize, end: usize) {
+        for slot in self.stack[start..end].iter_mut() {
+            *slot = Object::Uninitialized(Uninitialized {});
+        }
+    }
+
+    // Stores value at container[path[0]][path[1]]..., combining it with the
+    // old element first if operator is a compound assignment. The innermost
+    // container is modified in place; returns the stored element.
+    fn assign_index(
+        &mut self,
+        container: &Object,
+        path: &[Object],
+        value: Object,
+        operator: AssignOperator,
+    ) -> Result<Object, RuntimeError> {
+        if path.len() > 1 {
+            let element = self.index_value(container, &path[0])?;
+            return self.assign_index(&element, &path[1..], value, operator);
         }
+        let assigned = if let Some(op) = operator.opcode() {
+            let element = self.index_value(container, &path[0])?;
+            self.push(element)?;
+            self.push(value)?;
+            self.execute_binary_operation(op)?;
+            self.pop()
+        } else {
+            value
+        };
+        set_index(container, &path[0], assigned.clone())?;
+        Ok(assigned)
     }
 
-    fn call_builtin(&mut self, builtin: &Builtin, num_args: usize) -> Result<(), String> {
+    fn index_value(&mut self, left: &Object, index: &Object) -> Result<Object, RuntimeError> {
+        self.execute_index_expression(left, index)?;
+        Ok(self.pop())
+    }
+
+    fn call_builtin(&mut self, builtin: &Builtin, num_args: usize) -> Result<(), RuntimeError> {
         let mut v: Vec<Object> = Vec::new();
         for i in 0..num_args {
-            v.push(std::mem::replace(&mut self.stack[self.sp - num_args + i], None).unwrap());
+            v.push(std::mem::replace(
+                &mut self.stack[self.sp - num_args + i],
+                NULL,
+            ));
         }
-        let func = builtin.func;
-        let result = func(&v)?;
+        let result = builtin.call(&v)?;
         self.sp = self.sp - num_args - 1;
         self.push(result)?;
         Ok(())
     }
 
-    fn call_closure(&mut self, cl: &Closure, num_args: usize) -> Result<(), String> {
+    fn call_closure(&mut self, cl: &Rc<Closure>, num_args: usize) -> Result<(), RuntimeError> {
         if num_args != cl.func.num_parameters {
-            return Err(format!(
-                "wrong number of arguments: want={}, got={}",
-                cl.func.num_parameters, num_args
-            ));
+            return Err(RuntimeErrorKind::WrongArgumentCount {
+                want: cl.func.num_parameters,
+                got: num_args,
+            }
+            .into());
         }
 
         let num_locals = cl.func.num_locals;
-        let frame = Frame::new(cl.clone(), self.sp - num_args);
+        let frame = Frame::new(Rc::clone(cl), self.sp - num_args);
         let base_pointer = frame.base_pointer;
-        self.push_frame(frame);
+        self.reserve_stack(base_pointer + num_locals)?;
+        self.push_frame(frame)?;
+        self.clear_locals(base_pointer + num_args, base_pointer + num_locals);
         self.sp = base_pointer + num_locals;
         Ok(())
     }
 
-    fn push_closure(&mut self, const_index: usize, num_free: usize) -> Result<(), String> {
+    fn push_closure(&mut self, const_index: usize, num_free: usize) -> Result<(), RuntimeError> {
         let constant = self.constants.borrow()[const_index].clone();
         if let Object::CompiledFunction(function) = constant {
-            let mut free: Vec<Option<Object>> = vec![None; num_free];
-            let mut i = 0;
-            while i < num_free {
-                free[i] = self.stack[self.sp - num_free + i].clone();
-                i += 1;
+            let mut free: Vec<Rc<RefCell<Object>>> = Vec::with_capacity(num_free);
+            for i in (self.sp - num_free)..self.sp {
+                free.push(match std::mem::replace(&mut self.stack[i], NULL) {
+                    Object::Upvalue(upvalue) => upvalue.value,
+                    obj => Rc::new(RefCell::new(obj)),
+                });
             }
             self.sp -= num_free;
 
@@ -571,13 +912,34 @@ impl Vm {
                 func: function,
                 free: free,
             };
-            self.push(Object::Closure(closure))
+            self.push(Object::Closure(Rc::new(closure)))
         } else {
-            Err(format!("not a function: {:?}", constant))
+            Err(RuntimeErrorKind::NotAFunction(String::from(constant.get_type())).into())
         }
     }
 }
 
+fn integer_fast_path(op: Opcode, left: i64, right: i64) -> Option<Object> {
+    let value = match op {
+        Opcode::OpAdd => left.checked_add(right)?,
+        Opcode::OpSub => left.checked_sub(right)?,
+        Opcode::OpMul => left.checked_mul(right)?,
+        Opcode::OpEqual => return Some(native_bool_to_boolean_object(left == right)),
+        Opcode::OpNotEqual => return Some(native_bool_to_boolean_object(left != right)),
+        Opcode::OpGreaterThan => return Some(native_bool_to_boolean_object(left > right)),
+        Opcode::OpLessThan => return Some(native_bool_to_boolean_object(left < right)),
+        Opcode::OpGreaterEqual => return Some(native_bool_to_boolean_object(left >= right)),
+        Opcode::OpLessEqual => return Some(native_bool_to_boolean_object(left <= right)),
+        _ => return None,
+    };
+    Some(Object::Integer(Integer { value: value }))
+}
+
+// Reads the two-byte operand at offset.
+fn read_u16_operand(ins: &[u8], offset: usize) -> usize {
+    u16::from_be_bytes([ins[offset], ins[offset + 1]]) as usize
+}
+
 fn native_bool_to_boolean_object(input: bool) -> Object {
     if input {
         Object::Boolean(Boolean { value: true })
@@ -586,18 +948,51 @@ fn native_bool_to_boolean_object(input: bool) -> Object {
     }
 }
 
-pub fn get_type(obj: &Option<Object>) -> &str {
-    if obj.is_some() {
-        obj.as_ref().unwrap().get_type()
+fn infix_operator_error(left: &Object, op: Opcode, right: &Object) -> RuntimeError {
+    let left = String::from(left.get_type());
+    let operator = String::from(operator_symbol(op));
+    let right = String::from(right.get_type());
+    if left != right {
+        RuntimeErrorKind::TypeMismatch {
+            left: left,
+            operator: operator,
+            right: right,
+        }
+        .into()
     } else {
-        "None"
+        RuntimeErrorKind::UnknownInfixOperator {
+            left: left,
+            operator: operator,
+            right: right,
+        }
+        .into()
+    }
+}
+
+// Maps an operator opcode back to the source operator it was compiled from.
+fn operator_symbol(op: Opcode) -> &'static str {
+    match op {
+        Opcode::OpAdd => "+",
+        Opcode::OpSub => "-",
+        Opcode::OpMul => "*",
+        Opcode::OpDiv => "/",
+        Opcode::OpMod => "%",
+        Opcode::OpEqual => "==",
+        Opcode::OpNotEqual => "!=",
+        Opcode::OpGreaterThan => ">",
+        Opcode::OpLessThan => "<",
+        Opcode::OpGreaterEqual => ">=",
+        Opcode::OpLessEqual => "<=",
+        Opcode::OpMinus => "-",
+        Opcode::OpBang => "!",
+        _ => "?",
     }
 }
 
-fn is_truthy(obj: &Option<Object>) -> bool {
-    if let Some(Object::Boolean(Boolean { value })) = obj {
+fn is_truthy(obj: &Object) -> bool {
+    if let Object::Boolean(Boolean { value }) = obj {
         return *value;
-    } else if let Some(NULL) = obj {
+    } else if let Object::Null(_) = obj {
         return false;
     }
     true
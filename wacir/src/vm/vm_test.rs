@@ -6,9 +6,11 @@ use crate::lexer::*;
 use crate::object::*;
 use crate::parser::*;
 use crate::vm::*;
+use std::cell::*;
 use std::collections::*;
+use std::rc::*;
 
-fn parse(input: &str) -> Result<Program, Vec<String>> {
+fn parse(input: &str) -> Result<Program, Vec<ParseError>> {
     let l = Lexer::new(input);
     let mut p = Parser::new(l);
     return p.parse_program();
@@ -28,6 +30,20 @@ fn test_integer_object(expected: i64, actual: &Object) -> Result<(), String> {
     Ok(())
 }
 
+fn test_float_object(expected: f64, actual: &Object) -> Result<(), String> {
+    if let Object::Float(Float { value }) = actual {
+        if *value != expected {
+            return Err(format!(
+                "object has wrong value. got={}, want={}",
+                value, expected
+            ));
+        }
+    } else {
+        return Err(format!("object is not Float. got={:?}", actual));
+    }
+    Ok(())
+}
+
 struct VmTestCase<'a> {
     input: &'a str,
     expected: Object,
@@ -48,7 +64,9 @@ fn run_vm_tests(tests: Vec<VmTestCase>) {
                             }
                             Err(err) => test_expected_object(
                                 &tt.expected,
-                                &Object::ErrorObj(ErrorObj { message: err }),
+                                &Object::ErrorObj(ErrorObj {
+                                    message: err.kind.to_string(),
+                                }),
                             ),
                         }
                     }
@@ -70,6 +88,13 @@ fn test_expected_object(expected: &Object, actual: &Object) {
                 assert!(false, "test_integer_object failed: {}", err);
             }
         }
+    } else if let Object::Float(Float { value }) = expected {
+        match test_float_object(*value, actual) {
+            Ok(_) => {}
+            Err(err) => {
+                assert!(false, "test_float_object failed: {}", err);
+            }
+        }
     } else if let Object::Boolean(Boolean { value }) = expected {
         match test_boolean_object(*value, actual) {
             Ok(_) => {}
@@ -89,10 +114,12 @@ fn test_expected_object(expected: &Object, actual: &Object) {
                 assert!(false, "test_string_object failed: {}", err);
             }
         }
-    } else if let Object::Array(Array { elements }) = expected {
-        let expected_elements = elements;
-        if let Object::Array(Array { elements }) = actual {
-            let actual_elements = elements;
+    } else if let Object::Array(array) = expected {
+        let array = array.borrow();
+        let expected_elements = &array.elements;
+        if let Object::Array(array) = actual {
+            let array = array.borrow();
+            let actual_elements = &array.elements;
             assert!(
                 expected_elements.len() == actual_elements.len(),
                 "wrong num of elements. want={}, got={}",
@@ -115,10 +142,12 @@ fn test_expected_object(expected: &Object, actual: &Object) {
         } else {
             assert!(false, "object not Array: {:?}", actual);
         }
-    } else if let Object::Hash(Hash { pairs }) = expected {
-        let expected_pairs = pairs;
-        if let Object::Hash(Hash { pairs }) = actual {
-            let actual_pairs = pairs;
+    } else if let Object::Hash(hash) = expected {
+        let hash = hash.borrow();
+        let expected_pairs = &hash.pairs;
+        if let Object::Hash(hash) = actual {
+            let hash = hash.borrow();
+            let actual_pairs = &hash.pairs;
             assert!(
                 expected_pairs.len() == actual_pairs.len(),
                 "hash has wrong number of pairs. want={}, got={}",
@@ -145,21 +174,21 @@ fn test_expected_object(expected: &Object, actual: &Object) {
                     assert!(false, "error");
                 }
             }
-        } else if let Object::ErrorObj(ErrorObj { message }) = expected {
-            let expected_message = message;
-            if let Object::ErrorObj(ErrorObj { message }) = actual {
-                assert!(
-                    expected_message == message,
-                    "wrong error message. expected={:?}, got={:?}",
-                    expected_message,
-                    message
-                );
-            } else {
-                assert!(false, "object is not Error: {:?}", actual);
-            }
         } else {
             assert!(false, "object is not Hash. got={:?}", actual);
         }
+    } else if let Object::ErrorObj(ErrorObj { message }) = expected {
+        let expected_message = message;
+        if let Object::ErrorObj(ErrorObj { message }) = actual {
+            assert!(
+                expected_message == message,
+                "wrong error message. expected={:?}, got={:?}",
+                expected_message,
+                message
+            );
+        } else {
+            assert!(false, "object is not Error: {:?}", actual);
+        }
     }
 }
 
@@ -234,6 +263,134 @@ fn test_integer_arithmetic() {
             input: "(5 + 10 * 2 + 15 / 3) * 2 + -10",
             expected: Object::Integer(Integer { value: 50 }),
         },
+        VmTestCase {
+            input: "7 % 3",
+            expected: Object::Integer(Integer { value: 1 }),
+        },
+        VmTestCase {
+            input: "-7 % 3",
+            expected: Object::Integer(Integer { value: -1 }),
+        },
+        VmTestCase {
+            input: "-7 / 2",
+            expected: Object::Integer(Integer { value: -3 }),
+        },
+        VmTestCase {
+            input: "2 + 7 % 4 * 2",
+            expected: Object::Integer(Integer { value: 8 }),
+        },
+        VmTestCase {
+            input: "let a = [10]; a[0] %= 4; a[0]",
+            expected: Object::Integer(Integer { value: 2 }),
+        },
+        VmTestCase {
+            input: "1 / 0",
+            expected: Object::ErrorObj(ErrorObj {
+                message: String::from("division by zero"),
+            }),
+        },
+        VmTestCase {
+            input: "let a = 5; a %= 0",
+            expected: Object::ErrorObj(ErrorObj {
+                message: String::from("division by zero"),
+            }),
+        },
+        VmTestCase {
+            input: "9223372036854775807 + 1",
+            expected: Object::ErrorObj(ErrorObj {
+                message: String::from("integer overflow"),
+            }),
+        },
+        VmTestCase {
+            input: "-9223372036854775807 - 2",
+            expected: Object::ErrorObj(ErrorObj {
+                message: String::from("integer overflow"),
+            }),
+        },
+        VmTestCase {
+            input: "4611686018427387904 * 2",
+            expected: Object::ErrorObj(ErrorObj {
+                message: String::from("integer overflow"),
+            }),
+        },
+        VmTestCase {
+            input: "(-9223372036854775807 - 1) / -1",
+            expected: Object::ErrorObj(ErrorObj {
+                message: String::from("integer overflow"),
+            }),
+        },
+        VmTestCase {
+            input: "(-9223372036854775807 - 1) % -1",
+            expected: Object::ErrorObj(ErrorObj {
+                message: String::from("integer overflow"),
+            }),
+        },
+        VmTestCase {
+            input: "-(-9223372036854775807 - 1)",
+            expected: Object::ErrorObj(ErrorObj {
+                message: String::from("integer overflow"),
+            }),
+        },
+    ];
+
+    run_vm_tests(tests);
+}
+
+#[test]
+fn test_float_arithmetic() {
+    let tests = vec![
+        VmTestCase {
+            input: "2.5",
+            expected: Object::Float(Float { value: 2.5 }),
+        },
+        VmTestCase {
+            input: "-2.5",
+            expected: Object::Float(Float { value: -2.5 }),
+        },
+        VmTestCase {
+            input: "0.5 + 0.25",
+            expected: Object::Float(Float { value: 0.75 }),
+        },
+        VmTestCase {
+            input: "1 + 0.5",
+            expected: Object::Float(Float { value: 1.5 }),
+        },
+        VmTestCase {
+            input: "7 / 2.0",
+            expected: Object::Float(Float { value: 3.5 }),
+        },
+        VmTestCase {
+            input: "5.5 % 2",
+            expected: Object::Float(Float { value: 1.5 }),
+        },
+        VmTestCase {
+            input: "1e3 - 1",
+            expected: Object::Float(Float { value: 999.0 }),
+        },
+        VmTestCase {
+            input: "1.5 < 2",
+            expected: Object::Boolean(Boolean { value: true }),
+        },
+        VmTestCase {
+            input: "2.5 > 2.5",
+            expected: Object::Boolean(Boolean { value: false }),
+        },
+        VmTestCase {
+            input: "1 == 1.0",
+            expected: Object::Boolean(Boolean { value: true }),
+        },
+        VmTestCase {
+            input: "1.5 + true",
+            expected: Object::ErrorObj(ErrorObj {
+                message: String::from("type mismatch: FLOAT + BOOLEAN"),
+            }),
+        },
+        VmTestCase {
+            input: r#"{1.5: "x"}"#,
+            expected: Object::ErrorObj(ErrorObj {
+                message: String::from("unusable as hash key: FLOAT"),
+            }),
+        },
     ];
 
     run_vm_tests(tests);
@@ -346,6 +503,51 @@ fn test_boolean_expressions() {
             input: "!(if (false) { 5; })",
             expected: TRUE,
         },
+        VmTestCase {
+            input: "1 <= 1",
+            expected: TRUE,
+        },
+        VmTestCase {
+            input: "2 <= 1",
+            expected: FALSE,
+        },
+        VmTestCase {
+            input: "1 >= 2",
+            expected: FALSE,
+        },
+        VmTestCase {
+            input: "2 >= 2",
+            expected: TRUE,
+        },
+        VmTestCase {
+            input: "2.5 >= 2",
+            expected: TRUE,
+        },
+        VmTestCase {
+            input: r#""a" < "b""#,
+            expected: TRUE,
+        },
+        VmTestCase {
+            input: r#""abc" > "abd""#,
+            expected: FALSE,
+        },
+        VmTestCase {
+            input: r#""ab" <= "abc""#,
+            expected: TRUE,
+        },
+        VmTestCase {
+            input: r#""b" >= "abc""#,
+            expected: TRUE,
+        },
+        VmTestCase {
+            input: r#""x" != "y""#,
+            expected: TRUE,
+        },
+        VmTestCase {
+            input:
+                "let log = [0]; let f = fn(x) { log[0] = log[0] * 10 + x; x }; f(1) < f(2); log[0]",
+            expected: Object::Integer(Integer { value: 12 }),
+        },
     ];
 
     run_vm_tests(tests);
@@ -365,6 +567,54 @@ fn test_boolean_object(expected: bool, actual: &Object) -> Result<(), String> {
     Ok(())
 }
 
+#[test]
+fn test_logical_expressions() {
+    let tests = vec![
+        VmTestCase {
+            input: "true && false",
+            expected: Object::Boolean(Boolean { value: false }),
+        },
+        VmTestCase {
+            input: "false || true",
+            expected: Object::Boolean(Boolean { value: true }),
+        },
+        VmTestCase {
+            input: "1 && 2",
+            expected: Object::Integer(Integer { value: 2 }),
+        },
+        VmTestCase {
+            input: "0 || 5",
+            expected: Object::Integer(Integer { value: 0 }),
+        },
+        VmTestCase {
+            input: "if (false) { 1 } || 3",
+            expected: Object::Integer(Integer { value: 3 }),
+        },
+        VmTestCase {
+            input: "if (false) { 1 } && 3",
+            expected: Object::Null(Null {}),
+        },
+        VmTestCase {
+            input: "let a = 1; false && (a = 2); a",
+            expected: Object::Integer(Integer { value: 1 }),
+        },
+        VmTestCase {
+            input: "let a = 1; true || (a = 2); a",
+            expected: Object::Integer(Integer { value: 1 }),
+        },
+        VmTestCase {
+            input: "let a = 1; true && (a = 2); a",
+            expected: Object::Integer(Integer { value: 2 }),
+        },
+        VmTestCase {
+            input: "let f = fn(x) { x > 0 && x < 10 || x == 42 }; f(5) && f(42) && !f(11)",
+            expected: Object::Boolean(Boolean { value: true }),
+        },
+    ];
+
+    run_vm_tests(tests);
+}
+
 #[test]
 fn test_conditionals() {
     let tests = vec![
@@ -408,6 +658,170 @@ fn test_conditionals() {
             input: "if ((if (false) { 10 })) { 10 } else { 20 }",
             expected: Object::Integer(Integer { value: 20 }),
         },
+        VmTestCase {
+            input: "if (true) { let a = 1; }",
+            expected: NULL,
+        },
+        VmTestCase {
+            input: "let f = fn() { if (true) { let a = 1; } 5 }; f()",
+            expected: Object::Integer(Integer { value: 5 }),
+        },
+    ];
+
+    run_vm_tests(tests);
+}
+
+#[test]
+fn test_assignments() {
+    let tests = vec![
+        VmTestCase {
+            input: "let a = 1; a = 2; a",
+            expected: Object::Integer(Integer { value: 2 }),
+        },
+        VmTestCase {
+            input: "let a = 1; let a = a + 1; a",
+            expected: Object::Integer(Integer { value: 2 }),
+        },
+        VmTestCase {
+            input: "let a = 1; let b = 2; a = b = 3; a + b",
+            expected: Object::Integer(Integer { value: 6 }),
+        },
+        VmTestCase {
+            input: "let a = 5; a += 2; a -= 1; a *= 3; a /= 2",
+            expected: Object::Integer(Integer { value: 9 }),
+        },
+        VmTestCase {
+            input: "let a = 1; let f = fn() { a = 10; }; f(); a",
+            expected: Object::Integer(Integer { value: 10 }),
+        },
+        VmTestCase {
+            input: "let i = 0; let s = 0; while (i < 5) { i += 1; s += i; } s",
+            expected: Object::Integer(Integer { value: 15 }),
+        },
+        VmTestCase {
+            input: "let f = fn(n) { let s = 0; while (n > 0) { s += n; n -= 1; } s }; f(4)",
+            expected: Object::Integer(Integer { value: 10 }),
+        },
+        VmTestCase {
+            input: "let a = [1, 2, 3]; a[1] = 5; a[0] + a[1] + a[2]",
+            expected: Object::Integer(Integer { value: 9 }),
+        },
+        VmTestCase {
+            input: "let a = [1, 2]; let b = a; a[0] = 9; b[0]",
+            expected: Object::Integer(Integer { value: 9 }),
+        },
+        VmTestCase {
+            input: "let a = [[1, 2], [3, 4]]; a[1][0] += 10; a[1][0]",
+            expected: Object::Integer(Integer { value: 13 }),
+        },
+        VmTestCase {
+            input: "let f = fn() { let a = [1]; a[0] = 7 }; f()",
+            expected: Object::Integer(Integer { value: 7 }),
+        },
+        VmTestCase {
+            input: r#"let h = {"a": 1}; h["a"] += 1; h["b"] = 5; h["a"] + h["b"]"#,
+            expected: Object::Integer(Integer { value: 7 }),
+        },
+    ];
+
+    run_vm_tests(tests);
+}
+
+#[test]
+fn test_closures_share_variables() {
+    let tests = vec![
+        VmTestCase {
+            input: "
+            let counter = fn() { let n = 0; fn() { n += 1 } };
+            let c = counter();
+            c(); c();
+            let d = counter();
+            d();
+            c()
+            ",
+            expected: Object::Integer(Integer { value: 3 }),
+        },
+        VmTestCase {
+            input: "
+            let f = fn(n) {
+                let get = fn() { n };
+                n = 5;
+                get()
+            };
+            f(1)
+            ",
+            expected: Object::Integer(Integer { value: 5 }),
+        },
+        VmTestCase {
+            input: "
+            let f = fn() {
+                let x = 1;
+                let inc = fn() { fn() { x += 1 } };
+                inc()();
+                inc()();
+                x
+            };
+            f()
+            ",
+            expected: Object::Integer(Integer { value: 3 }),
+        },
+        VmTestCase {
+            input: "
+            let make = fn(v) { let get = fn() { v }; get };
+            let a = make(1);
+            let b = make(2);
+            a() + b()
+            ",
+            expected: Object::Integer(Integer { value: 3 }),
+        },
+    ];
+
+    run_vm_tests(tests);
+}
+
+#[test]
+fn test_top_level_return() {
+    let tests = vec![
+        VmTestCase {
+            input: "return 5; 6",
+            expected: Object::Integer(Integer { value: 5 }),
+        },
+        VmTestCase {
+            input: "let x = 2; if (x > 1) { return x * 5; } 1 / 0",
+            expected: Object::Integer(Integer { value: 10 }),
+        },
+        VmTestCase {
+            input: "let i = 0; while (true) { i += 1; if (i == 3) { return i; } }",
+            expected: Object::Integer(Integer { value: 3 }),
+        },
+    ];
+
+    run_vm_tests(tests);
+}
+
+#[test]
+fn test_while_loops() {
+    let tests = vec![
+        VmTestCase {
+            input: "while (false) { 1 }; 2",
+            expected: Object::Integer(Integer { value: 2 }),
+        },
+        VmTestCase {
+            input: "while (true) { break; } 3",
+            expected: Object::Integer(Integer { value: 3 }),
+        },
+        VmTestCase {
+            input: "let f = fn() { while (true) { return 5; } }; f()",
+            expected: Object::Integer(Integer { value: 5 }),
+        },
+        VmTestCase {
+            input: "let f = fn(n) { while (n > 0) { if (n > 2) { return n; } break; } 0 }; f(1)",
+            expected: Object::Integer(Integer { value: 0 }),
+        },
+        VmTestCase {
+            input: "let f = fn() { while (true) { while (true) { break; } return 3; } }; f()",
+            expected: Object::Integer(Integer { value: 3 }),
+        },
     ];
 
     run_vm_tests(tests);
@@ -478,29 +892,29 @@ fn test_array_literals() {
     let tests = vec![
         VmTestCase {
             input: "[]",
-            expected: Object::Array(Array {
+            expected: Object::Array(Rc::new(RefCell::new(Array {
                 elements: Vec::new(),
-            }),
+            }))),
         },
         VmTestCase {
             input: "[1, 2, 3]",
-            expected: Object::Array(Array {
+            expected: Object::Array(Rc::new(RefCell::new(Array {
                 elements: vec![
                     Object::Integer(Integer { value: 1 }),
                     Object::Integer(Integer { value: 2 }),
                     Object::Integer(Integer { value: 3 }),
                 ],
-            }),
+            }))),
         },
         VmTestCase {
             input: "[1 + 2, 3 * 4, 5 + 6]",
-            expected: Object::Array(Array {
+            expected: Object::Array(Rc::new(RefCell::new(Array {
                 elements: vec![
                     Object::Integer(Integer { value: 3 }),
                     Object::Integer(Integer { value: 12 }),
                     Object::Integer(Integer { value: 11 }),
                 ],
-            }),
+            }))),
         },
     ];
 
@@ -532,17 +946,17 @@ fn test_hash_literals() {
     let tests = vec![
         VmTestCase {
             input: "{}",
-            expected: Object::Hash(Hash {
+            expected: Object::Hash(Rc::new(RefCell::new(Hash {
                 pairs: HashMap::new(),
-            }),
+            }))),
         },
         VmTestCase {
             input: "{1: 2, 2: 3}",
-            expected: Object::Hash(Hash { pairs: pairs2 }),
+            expected: Object::Hash(Rc::new(RefCell::new(Hash { pairs: pairs2 }))),
         },
         VmTestCase {
             input: "{1 + 1: 2 * 2, 3 + 3: 4 * 4}",
-            expected: Object::Hash(Hash { pairs: pairs3 }),
+            expected: Object::Hash(Rc::new(RefCell::new(Hash { pairs: pairs3 }))),
         },
     ];
 
@@ -597,6 +1011,46 @@ fn test_index_expressions() {
     run_vm_tests(tests);
 }
 
+#[test]
+fn test_shared_containers() {
+    let tests = vec![
+        VmTestCase {
+            input: "let a = [1, 2]; let b = a; a == b",
+            expected: TRUE,
+        },
+        VmTestCase {
+            input: "[1] == [1]",
+            expected: FALSE,
+        },
+        VmTestCase {
+            input: "let h = {}; h == h",
+            expected: TRUE,
+        },
+        VmTestCase {
+            input: "let f = fn() { 1 }; f == f",
+            expected: TRUE,
+        },
+        VmTestCase {
+            input: "let a = [1, 2]; let b = a; b[0] = 9; a[0] + b[0]",
+            expected: Object::Integer(Integer { value: 18 }),
+        },
+        VmTestCase {
+            input: "let a = {1: 1}; let b = a; b[1] += 1; a[1]",
+            expected: Object::Integer(Integer { value: 2 }),
+        },
+        VmTestCase {
+            input: "let a = [[1]]; let b = a[0]; b[0] = 5; a[0][0]",
+            expected: Object::Integer(Integer { value: 5 }),
+        },
+        VmTestCase {
+            input: "let a = [0]; let f = fn(x) { x[0] = 7; }; f(a); a[0]",
+            expected: Object::Integer(Integer { value: 7 }),
+        },
+    ];
+
+    run_vm_tests(tests);
+}
+
 #[test]
 fn test_calling_functions_without_arguments() {
     let tests = vec![
@@ -828,7 +1282,7 @@ fn test_calling_functions_with_wrong_arguments() {
             Err(err) => {
                 assert!(
                     Object::ErrorObj(ErrorObj {
-                        message: err.clone()
+                        message: err.kind.to_string()
                     }) == tt.expected,
                     "wrong Vm error: want={:?}, got={}",
                     tt.expected,
@@ -911,12 +1365,12 @@ fn test_builtin_functions() {
         },
         VmTestCase {
             input: r#"rest([1, 2, 3])"#,
-            expected: Object::Array(Array {
+            expected: Object::Array(Rc::new(RefCell::new(Array {
                 elements: vec![
                     Object::Integer(Integer { value: 2 }),
                     Object::Integer(Integer { value: 3 }),
                 ],
-            }),
+            }))),
         },
         VmTestCase {
             input: r#"rest([])"#,
@@ -924,9 +1378,9 @@ fn test_builtin_functions() {
         },
         VmTestCase {
             input: r#"push([], 1)"#,
-            expected: Object::Array(Array {
+            expected: Object::Array(Rc::new(RefCell::new(Array {
                 elements: vec![Object::Integer(Integer { value: 1 })],
-            }),
+            }))),
         },
         VmTestCase {
             input: r#"push(1, 1)"#,
@@ -1019,6 +1473,160 @@ fn test_closures() {
     run_vm_tests(tests);
 }
 
+#[test]
+fn test_recursive_functions() {
+    let tests = vec![
+        VmTestCase {
+            input: "
+            let countDown = fn(x) {
+                if (x == 0) {
+                    return 0;
+                } else {
+                    countDown(x - 1);
+                }
+            };
+            countDown(1);
+            ",
+            expected: Object::Integer(Integer { value: 0 }),
+        },
+        VmTestCase {
+            input: "
+            let countDown = fn(x) {
+                if (x == 0) {
+                    return 0;
+                } else {
+                    countDown(x - 1);
+                }
+            };
+            let wrapper = fn() {
+                countDown(1);
+            };
+            wrapper();
+            ",
+            expected: Object::Integer(Integer { value: 0 }),
+        },
+        VmTestCase {
+            input: "
+            let wrapper = fn() {
+                let countDown = fn(x) {
+                    if (x == 0) {
+                        return 0;
+                    } else {
+                        countDown(x - 1);
+                    }
+                };
+                countDown(1);
+            };
+            wrapper();
+            ",
+            expected: Object::Integer(Integer { value: 0 }),
+        },
+        VmTestCase {
+            input: "
+            let f = fn() {
+                let g = fn(x) { if (x == 0) { 10 } else { g(x - 1) } };
+                g(3)
+            };
+            f();
+            ",
+            expected: Object::Integer(Integer { value: 10 }),
+        },
+    ];
+    run_vm_tests(tests);
+}
+
+#[test]
+fn test_tail_calls() {
+    let tests = vec![
+        VmTestCase {
+            input: "
+            let count = fn(n, acc) {
+                if (n == 0) { acc } else { count(n - 1, acc + 1) }
+            };
+            count(100000, 0);
+            ",
+            expected: Object::Integer(Integer { value: 100000 }),
+        },
+        VmTestCase {
+            input: "
+            let down = fn(n) { if (n > 0) { return down(n - 1); } n };
+            down(100000);
+            ",
+            expected: Object::Integer(Integer { value: 0 }),
+        },
+        VmTestCase {
+            input: "
+            let outer = fn(x) {
+                let inner = fn(n) { if (n == 0) { x } else { inner(n - 1) } };
+                inner(50000)
+            };
+            outer(7) + 1;
+            ",
+            expected: Object::Integer(Integer { value: 8 }),
+        },
+        VmTestCase {
+            input: "fn() { len([1, 2]) }();",
+            expected: Object::Integer(Integer { value: 2 }),
+        },
+    ];
+    run_vm_tests(tests);
+}
+
+#[test]
+fn test_limits() {
+    let small = VmConfig {
+        max_stack_size: 64,
+        max_frames: 10,
+    };
+    let count = "let f = fn(n) { if (n == 0) { 0 } else { f(n - 1) + 1 } };";
+    let tests = [
+        (
+            format!("{} f(100000)", count),
+            VmConfig::default(),
+            Err(RuntimeErrorKind::MaxRecursionDepth),
+        ),
+        // The main program and nine calls.
+        (format!("{} f(8)", count), small, Ok(8)),
+        (
+            format!("{} f(9)", count),
+            small,
+            Err(RuntimeErrorKind::MaxRecursionDepth),
+        ),
+        (
+            format!("len([{}])", vec!["1"; 100].join(", ")),
+            small,
+            Err(RuntimeErrorKind::StackOverflow),
+        ),
+        (
+            format!("len([{}])", vec!["1"; 60000].join(", ")),
+            VmConfig::default(),
+            Ok(60000),
+        ),
+    ];
+
+    for (input, config, expected) in tests.iter() {
+        let mut comp = Compiler::new();
+        comp.compile(Node::Program(parse(input).unwrap())).unwrap();
+        let mut vm = Vm::new(comp.bytecode());
+        vm.set_config(*config);
+        match (vm.run(), expected) {
+            (Ok(_), Ok(value)) => {
+                let result = vm.last_popped_stack_elem.unwrap();
+                if let Err(err) = test_integer_object(*value, &result) {
+                    panic!("{}", err);
+                }
+            }
+            (Err(err), Err(kind)) => assert!(
+                err.kind == *kind,
+                "wrong Vm error: want={}, got={}",
+                kind,
+                err
+            ),
+            (result, _) => panic!("wrong result. want={:?}, got={:?}", expected, result),
+        }
+    }
+}
+
 #[test]
 fn test_recursive_fibonacci() {
     let tests = vec![VmTestCase {
@@ -1040,3 +1648,93 @@ fn test_recursive_fibonacci() {
     }];
     run_vm_tests(tests);
 }
+
+#[test]
+fn test_runtime_error_positions() {
+    let tests = [
+        (
+            "let a = 1;\n  a + true",
+            "2:5: type mismatch: INTEGER + BOOLEAN",
+        ),
+        (
+            "fn(a, b) { a + b; }(1);",
+            "1:20: wrong number of arguments: want=2, got=1",
+        ),
+        (
+            "let f = fn() { -\"a\" };\nf()",
+            "1:16: unknown operator: -STRING",
+        ),
+        ("len(1, 2)", "1:4: wrong number of arguments: want=1, got=2"),
+        (
+            "let a = [1, 2];\na[2] = 3",
+            "2:6: index out of range: index=2, length=2",
+        ),
+        (
+            "let a = 1;\na += true",
+            "2:3: type mismatch: INTEGER + BOOLEAN",
+        ),
+        (
+            "let f = fn() {\n  if (false) { let x = 1; }\n  x\n};\nf()",
+            "3:3: uninitialized value",
+        ),
+    ];
+
+    for tt in tests.iter() {
+        let mut comp = Compiler::new();
+        comp.compile(Node::Program(parse(tt.0).unwrap())).unwrap();
+        let mut vm = Vm::new(comp.bytecode());
+        match vm.run() {
+            Ok(_) => panic!("expected VM error but resulted in none."),
+            Err(err) => assert!(
+                err.to_string() == tt.1,
+                "wrong Vm error: want={}, got={}",
+                tt.1,
+                err
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_runtime_error_backtrace() {
+    let tests = [
+        ("1 / 0", "    at <main> (1:3)\n"),
+        (
+            "let inner = fn(x) {\n  x / 0\n};\nlet outer = fn() { inner(1) + 1 };\nouter()",
+            "    at inner (2:5)\n    at outer (4:25)\n    at <main> (5:6)\n",
+        ),
+        // f's frame is reused by its tail call to g.
+        (
+            "let f = fn(g) { g() };\nf(fn() { [] + 1 })",
+            "    at <anonymous> (2:13)\n    at <main> (2:2)\n",
+        ),
+        (
+            "let f = fn() { len(1) };\nlet g = f;\ng()",
+            "    at f (1:19)\n    at <main> (3:2)\n",
+        ),
+        (
+            "let f = fn(n) { if (n == 0) { 1 / 0 } else { 1 + f(n - 1) } };\nf(5);",
+            "    at f (1:33)\n    at f (1:51)\n    ... repeated 4 more times\n    at <main> (2:2)\n",
+        ),
+        (
+            "let f = fn(n) { f(n + 1) + 1 };\nf(0)",
+            "    at f (1:18)\n    ... repeated 1022 more times\n    at <main> (2:2)\n",
+        ),
+    ];
+
+    for tt in tests.iter() {
+        let mut comp = Compiler::new();
+        comp.compile(Node::Program(parse(tt.0).unwrap())).unwrap();
+        let mut vm = Vm::new(comp.bytecode());
+        match vm.run() {
+            Ok(_) => panic!("expected VM error but resulted in none."),
+            Err(err) => assert!(
+                err.backtrace() == tt.1,
+                "wrong backtrace for {:?}: want={:?}, got={:?}",
+                tt.0,
+                tt.1,
+                err.backtrace()
+            ),
+        }
+    }
+}
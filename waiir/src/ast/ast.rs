@@ -7,6 +7,7 @@ use std::hash::Hasher;
 
 pub trait NodeTrait {
     fn string(&self) -> String;
+    fn span(&self) -> Span;
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +24,13 @@ impl NodeTrait for Node {
             Node::Expression(e) => e.string(),
         }
     }
+    fn span(&self) -> Span {
+        match self {
+            Node::Program(x) => x.span(),
+            Node::Statement(x) => x.span(),
+            Node::Expression(x) => x.span(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -31,6 +39,9 @@ pub enum Statement {
     ReturnStatement(ReturnStatement),
     ExpressionStatement(ExpressionStatement),
     BlockStatement(BlockStatement),
+    WhileStatement(WhileStatement),
+    BreakStatement(BreakStatement),
+    ContinueStatement(ContinueStatement),
 }
 impl NodeTrait for Statement {
     fn string(&self) -> String {
@@ -39,6 +50,20 @@ impl NodeTrait for Statement {
             Statement::ReturnStatement(return_stmt) => return_stmt.string(),
             Statement::ExpressionStatement(expr_stmt) => expr_stmt.string(),
             Statement::BlockStatement(block_stmt) => block_stmt.string(),
+            Statement::WhileStatement(while_stmt) => while_stmt.string(),
+            Statement::BreakStatement(break_stmt) => break_stmt.string(),
+            Statement::ContinueStatement(continue_stmt) => continue_stmt.string(),
+        }
+    }
+    fn span(&self) -> Span {
+        match self {
+            Statement::LetStatement(x) => x.span(),
+            Statement::ReturnStatement(x) => x.span(),
+            Statement::ExpressionStatement(x) => x.span(),
+            Statement::BlockStatement(x) => x.span(),
+            Statement::WhileStatement(x) => x.span(),
+            Statement::BreakStatement(x) => x.span(),
+            Statement::ContinueStatement(x) => x.span(),
         }
     }
 }
@@ -47,6 +72,7 @@ impl NodeTrait for Statement {
 pub enum Expression {
     Identifier(Identifier),
     IntegerLiteral(IntegerLiteral),
+    FloatLiteral(FloatLiteral),
     PrefixExpression(PrefixExpression),
     InfixExpression(InfixExpression),
     BooleanLiteral(BooleanLiteral),
@@ -57,12 +83,14 @@ pub enum Expression {
     ArrayLiteral(ArrayLiteral),
     IndexExpression(IndexExpression),
     HashLiteral(HashLiteral),
+    AssignExpression(AssignExpression),
 }
 impl NodeTrait for Expression {
     fn string(&self) -> String {
         match self {
             Expression::Identifier(ident) => ident.string(),
             Expression::IntegerLiteral(integer_literal) => integer_literal.string(),
+            Expression::FloatLiteral(float_literal) => float_literal.string(),
             Expression::PrefixExpression(prefix_expr) => prefix_expr.string(),
             Expression::InfixExpression(infix_expr) => infix_expr.string(),
             Expression::BooleanLiteral(bo) => bo.string(),
@@ -73,6 +101,25 @@ impl NodeTrait for Expression {
             Expression::ArrayLiteral(array_literal) => array_literal.string(),
             Expression::IndexExpression(index_expr) => index_expr.string(),
             Expression::HashLiteral(hash_literal) => hash_literal.string(),
+            Expression::AssignExpression(assign_expr) => assign_expr.string(),
+        }
+    }
+    fn span(&self) -> Span {
+        match self {
+            Expression::Identifier(x) => x.span(),
+            Expression::IntegerLiteral(x) => x.span(),
+            Expression::FloatLiteral(x) => x.span(),
+            Expression::PrefixExpression(x) => x.span(),
+            Expression::InfixExpression(x) => x.span(),
+            Expression::BooleanLiteral(x) => x.span(),
+            Expression::IfExpression(x) => x.span(),
+            Expression::FunctionLiteral(x) => x.span(),
+            Expression::CallExpression(x) => x.span(),
+            Expression::StringLiteral(x) => x.span(),
+            Expression::ArrayLiteral(x) => x.span(),
+            Expression::IndexExpression(x) => x.span(),
+            Expression::HashLiteral(x) => x.span(),
+            Expression::AssignExpression(x) => x.span(),
         }
     }
 }
@@ -80,6 +127,7 @@ impl NodeTrait for Expression {
 #[derive(Debug, Clone)]
 pub struct Program {
     pub statements: Vec<Statement>,
+    pub trailing: Vec<Trivia>, // comments and line breaks after the last statement
 }
 impl NodeTrait for Program {
     fn string(&self) -> String {
@@ -89,6 +137,12 @@ impl NodeTrait for Program {
         }
         out
     }
+    fn span(&self) -> Span {
+        match self.statements.first() {
+            Some(s) => s.span(),
+            None => Span::default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -106,6 +160,9 @@ impl NodeTrait for LetStatement {
             self.value.string(),
         )
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -117,6 +174,9 @@ impl NodeTrait for Identifier {
     fn string(&self) -> String {
         self.value.clone()
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -128,6 +188,9 @@ impl NodeTrait for ReturnStatement {
     fn string(&self) -> String {
         format!("{} {};", self.token.literal, self.return_value.string())
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -139,6 +202,55 @@ impl NodeTrait for ExpressionStatement {
     fn string(&self) -> String {
         self.expression.string()
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WhileStatement {
+    pub token: Token,
+    pub condition: Expression,
+    pub body: BlockStatement,
+}
+impl NodeTrait for WhileStatement {
+    fn string(&self) -> String {
+        format!(
+            "{}{} {}",
+            self.token.literal,
+            self.condition.string(),
+            self.body.string()
+        )
+    }
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BreakStatement {
+    pub token: Token,
+}
+impl NodeTrait for BreakStatement {
+    fn string(&self) -> String {
+        format!("{};", self.token.literal)
+    }
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ContinueStatement {
+    pub token: Token,
+}
+impl NodeTrait for ContinueStatement {
+    fn string(&self) -> String {
+        format!("{};", self.token.literal)
+    }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -150,6 +262,23 @@ impl NodeTrait for IntegerLiteral {
     fn string(&self) -> String {
         self.token.literal.clone()
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FloatLiteral {
+    pub token: Token,
+    pub value: f64,
+}
+impl NodeTrait for FloatLiteral {
+    fn string(&self) -> String {
+        self.token.literal.clone()
+    }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -162,6 +291,9 @@ impl NodeTrait for PrefixExpression {
     fn string(&self) -> String {
         format!("({}{})", self.operator, self.right.string())
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -180,6 +312,33 @@ impl NodeTrait for InfixExpression {
             self.right.string()
         )
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
+}
+
+// An assignment to a name or to an element of an array or hash. The target
+// is always an Identifier, or an IndexExpression whose left side is itself a
+// valid target. The operator is "=" or a compound form such as "+=".
+#[derive(Debug, Clone)]
+pub struct AssignExpression {
+    pub token: Token,
+    pub target: Box<Expression>,
+    pub operator: String,
+    pub value: Box<Expression>,
+}
+impl NodeTrait for AssignExpression {
+    fn string(&self) -> String {
+        format!(
+            "({} {} {})",
+            self.target.string(),
+            self.operator,
+            self.value.string()
+        )
+    }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -191,6 +350,9 @@ impl NodeTrait for BooleanLiteral {
     fn string(&self) -> String {
         self.token.literal.clone()
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -213,12 +375,16 @@ impl NodeTrait for IfExpression {
         }
         out
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct BlockStatement {
     pub token: Token,
     pub statements: Vec<Statement>,
+    pub trailing: Vec<Trivia>, // comments and line breaks before the closing brace
 }
 impl NodeTrait for BlockStatement {
     fn string(&self) -> String {
@@ -229,6 +395,9 @@ impl NodeTrait for BlockStatement {
         }
         out
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -250,6 +419,9 @@ impl NodeTrait for FunctionLiteral {
             self.body.string()
         )
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -270,6 +442,9 @@ impl NodeTrait for CallExpression {
                 .join(", ")
         )
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -281,6 +456,9 @@ impl NodeTrait for StringLiteral {
     fn string(&self) -> String {
         format!("{}", self.value)
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -299,6 +477,9 @@ impl NodeTrait for ArrayLiteral {
                 .join(", ")
         )
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -311,6 +492,9 @@ impl NodeTrait for IndexExpression {
     fn string(&self) -> String {
         format!("({}[{}])", self.left.string(), self.index.string())
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 
 #[derive(Clone)]
@@ -329,6 +513,9 @@ impl NodeTrait for HashLiteral {
                 .join(", ")
         )
     }
+    fn span(&self) -> Span {
+        self.token.span
+    }
 }
 impl std::fmt::Debug for HashLiteral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -1,19 +1,12 @@
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-mod ast;
-mod evaluator;
-mod lexer;
-mod object;
-mod parser;
-mod repl;
-mod token;
-
-use crate::lexer::*;
-use crate::object::*;
-use crate::parser::*;
 use std::cell::*;
 use std::rc::*;
+use waiir::evaluator;
+use waiir::lexer::*;
+use waiir::object::*;
+use waiir::parser::*;
 
 fn main() {
   let input = "
@@ -1,6 +1,5 @@
 // src/evaluator/evaluator.rs
 
-use super::builtins::*;
 use crate::ast::*;
 use crate::object::*;
 use std::cell::*;
@@ -12,12 +11,27 @@ pub const FALSE: Boolean = Boolean { value: false };
 pub const NULL: Null = Null {};
 
 pub fn evaluate(program: Program, env: Rc<RefCell<Environment>>) -> Object {
-    match eval(Node::Program(program), env) {
+    match try_evaluate(program, env) {
         Ok(v) => v,
-        Err(err) => Object::ErrorObj(ErrorObj { message: err }),
+        Err(err) => Object::ErrorObj(ErrorObj {
+            message: err.kind.to_string(),
+        }),
     }
 }
-fn eval(node: Node, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
+
+pub fn try_evaluate(
+    program: Program,
+    env: Rc<RefCell<Environment>>,
+) -> Result<Object, RuntimeError> {
+    eval(Node::Program(program), env).map_err(|err| err.unwind("<main>"))
+}
+
+fn eval(node: Node, env: Rc<RefCell<Environment>>) -> Result<Object, RuntimeError> {
+    let span = node.span();
+    eval_node(node, env).map_err(|err| err.at(span))
+}
+
+fn eval_node(node: Node, env: Rc<RefCell<Environment>>) -> Result<Object, RuntimeError> {
     match node {
         Node::Program(program) => eval_program(program, Rc::clone(&env)),
         Node::Statement(Statement::ExpressionStatement(ExpressionStatement {
@@ -27,6 +41,9 @@ fn eval(node: Node, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
         Node::Expression(Expression::IntegerLiteral(IntegerLiteral { token: _, value })) => {
             Ok(Object::Integer(Integer { value: value }))
         }
+        Node::Expression(Expression::FloatLiteral(FloatLiteral { token: _, value })) => {
+            Ok(Object::Float(Float { value: value }))
+        }
         Node::Expression(Expression::BooleanLiteral(BooleanLiteral { token: _, value })) => {
             Ok(Object::Boolean(native_bool_to_boolean_object(value)))
         }
@@ -36,7 +53,8 @@ fn eval(node: Node, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
             right,
         })) => {
             let right_obj = eval(Node::Expression(*right), Rc::clone(&env))?;
-            eval_prefix_expression(&operator, right_obj)
+            let arithmetic = env.borrow().arithmetic();
+            eval_prefix_expression(&operator, right_obj, arithmetic)
         }
         Node::Expression(Expression::InfixExpression(InfixExpression {
             token: _,
@@ -45,8 +63,12 @@ fn eval(node: Node, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
             right,
         })) => {
             let left_obj = eval(Node::Expression(*left), Rc::clone(&env))?;
+            if operator == "&&" || operator == "||" {
+                return eval_logical_expression(&operator, left_obj, *right, env);
+            }
             let right_obj = eval(Node::Expression(*right), Rc::clone(&env))?;
-            eval_infix_expression(&operator, &left_obj, &right_obj)
+            let arithmetic = env.borrow().arithmetic();
+            eval_infix_expression(&operator, &left_obj, &right_obj, arithmetic)
         }
         Node::Statement(Statement::BlockStatement(block)) => {
             eval_block_statement(block, Rc::clone(&env))
@@ -63,13 +85,23 @@ fn eval(node: Node, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
                 value: Box::new(val),
             }))
         }
+        Node::Statement(Statement::WhileStatement(while_stmt)) => {
+            eval_while_statement(while_stmt, Rc::clone(&env))
+        }
+        Node::Statement(Statement::BreakStatement(_)) => Ok(Object::Break(Break {})),
+        Node::Statement(Statement::ContinueStatement(_)) => Ok(Object::Continue(Continue {})),
         Node::Statement(Statement::LetStatement(LetStatement {
             token: _,
             name,
             value,
         })) => {
-            let val = eval(Node::Expression(value), Rc::clone(&env))?;
-            Ok(env.borrow_mut().set(name.value, val))
+            let mut val = eval(Node::Expression(value), Rc::clone(&env))?;
+            if let Object::Function(Function { name: fn_name, .. }) = &mut val {
+                if fn_name.is_none() {
+                    *fn_name = Some(name.value.clone());
+                }
+            }
+            Ok(env.borrow_mut().define(name.value, val))
         }
         Node::Expression(Expression::Identifier(ident)) => eval_identifier(ident, Rc::clone(&env)),
         Node::Expression(Expression::FunctionLiteral(FunctionLiteral {
@@ -80,6 +112,7 @@ fn eval(node: Node, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
             parameters: parameters,
             body: body,
             env: Rc::clone(&env),
+            name: None,
         })),
         Node::Expression(Expression::CallExpression(CallExpression {
             token: _,
@@ -111,10 +144,13 @@ fn eval(node: Node, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
         Node::Expression(Expression::HashLiteral(hash_literal)) => {
             eval_hash_literal(hash_literal, Rc::clone(&env))
         }
+        Node::Expression(Expression::AssignExpression(assign_expr)) => {
+            eval_assign_expression(assign_expr, Rc::clone(&env))
+        }
     }
 }
 
-fn eval_program(program: Program, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
+fn eval_program(program: Program, env: Rc<RefCell<Environment>>) -> Result<Object, RuntimeError> {
     let mut result: Object = Object::Null(NULL);
     for statement in program.statements.into_iter() {
         result = eval(Node::Statement(statement), Rc::clone(&env))?;
@@ -133,19 +169,23 @@ pub fn native_bool_to_boolean_object(input: bool) -> Boolean {
     }
 }
 
-fn eval_prefix_expression(operator: &str, right: Object) -> Result<Object, String> {
+fn eval_prefix_expression(
+    operator: &str,
+    right: Object,
+    arithmetic: Arithmetic,
+) -> Result<Object, RuntimeError> {
     match operator {
         "!" => eval_bang_operator_expression(right),
-        "-" => eval_minus_prefix_operator_expression(right),
-        _ => Err(format!(
-            "unknown operator: {}{}",
-            operator,
-            right.get_type()
-        )),
+        "-" => eval_minus_prefix_operator_expression(right, arithmetic),
+        _ => Err(RuntimeErrorKind::UnknownPrefixOperator {
+            operator: String::from(operator),
+            right: String::from(right.get_type()),
+        }
+        .into()),
     }
 }
 
-fn eval_bang_operator_expression(right: Object) -> Result<Object, String> {
+fn eval_bang_operator_expression(right: Object) -> Result<Object, RuntimeError> {
     match right {
         Object::Boolean(TRUE) => Ok(Object::Boolean(FALSE)),
         Object::Boolean(FALSE) => Ok(Object::Boolean(TRUE)),
@@ -154,22 +194,39 @@ fn eval_bang_operator_expression(right: Object) -> Result<Object, String> {
     }
 }
 
-fn eval_minus_prefix_operator_expression(right: Object) -> Result<Object, String> {
-    if let Object::Integer(Integer { value }) = right {
-        Ok(Object::Integer(Integer { value: -value }))
-    } else {
-        Err(format!("unknown operator: -{}", right.get_type()))
+fn eval_minus_prefix_operator_expression(
+    right: Object,
+    arithmetic: Arithmetic,
+) -> Result<Object, RuntimeError> {
+    match right {
+        Object::Integer(Integer { value }) => Ok(Object::Integer(Integer {
+            value: integer_negate(value, arithmetic)?,
+        })),
+        Object::Float(Float { value }) => Ok(Object::Float(Float { value: -value })),
+        _ => Err(RuntimeErrorKind::UnknownPrefixOperator {
+            operator: String::from("-"),
+            right: String::from(right.get_type()),
+        }
+        .into()),
     }
 }
 
-fn eval_infix_expression(operator: &str, left: &Object, right: &Object) -> Result<Object, String> {
+fn eval_infix_expression(
+    operator: &str,
+    left: &Object,
+    right: &Object,
+    arithmetic: Arithmetic,
+) -> Result<Object, RuntimeError> {
+    if let Some((left_val, right_val)) = float_operands(left, right) {
+        return eval_float_infix_expression(operator, left_val, right_val);
+    }
     if left.get_type() != right.get_type() {
-        return Err(format!(
-            "type mismatch: {} {} {}",
-            left.get_type(),
-            operator,
-            right.get_type()
-        ));
+        return Err(RuntimeErrorKind::TypeMismatch {
+            left: String::from(left.get_type()),
+            operator: String::from(operator),
+            right: String::from(right.get_type()),
+        }
+        .into());
     }
     if let Object::StringObj(value) = left {
         let left_val = value;
@@ -181,7 +238,7 @@ fn eval_infix_expression(operator: &str, left: &Object, right: &Object) -> Resul
         let left_val = value;
         if let Object::Integer(Integer { value }) = right {
             let right_val = value;
-            return eval_integer_infix_expression(operator, *left_val, *right_val);
+            return eval_integer_infix_expression(operator, *left_val, *right_val, arithmetic);
         }
     }
     return match operator {
@@ -191,42 +248,168 @@ fn eval_infix_expression(operator: &str, left: &Object, right: &Object) -> Resul
         "!=" => Ok(Object::Boolean(native_bool_to_boolean_object(
             left != right,
         ))),
-        _ => Err(format!(
-            "unknown operator: {} {} {}",
-            left.get_type(),
-            operator,
-            right.get_type(),
-        )),
+        _ => Err(unknown_infix_operator(left, operator, right)),
+    };
+}
+
+fn unknown_infix_operator(left: &Object, operator: &str, right: &Object) -> RuntimeError {
+    RuntimeErrorKind::UnknownInfixOperator {
+        left: String::from(left.get_type()),
+        operator: String::from(operator),
+        right: String::from(right.get_type()),
+    }
+    .into()
+}
+
+fn eval_integer_infix_expression(
+    operator: &str,
+    left: i64,
+    right: i64,
+    arithmetic: Arithmetic,
+) -> Result<Object, RuntimeError> {
+    match operator {
+        "<" => Ok(Object::Boolean(native_bool_to_boolean_object(left < right))),
+        ">" => Ok(Object::Boolean(native_bool_to_boolean_object(left > right))),
+        "<=" => Ok(Object::Boolean(native_bool_to_boolean_object(
+            left <= right,
+        ))),
+        ">=" => Ok(Object::Boolean(native_bool_to_boolean_object(
+            left >= right,
+        ))),
+        "==" => Ok(Object::Boolean(native_bool_to_boolean_object(
+            left == right,
+        ))),
+        "!=" => Ok(Object::Boolean(native_bool_to_boolean_object(
+            left != right,
+        ))),
+        _ => Ok(Object::Integer(Integer {
+            value: integer_arithmetic(operator, left, right, arithmetic)?,
+        })),
+    }
+}
+
+// Applies an arithmetic operator to two integers. Division and remainder
+// truncate toward zero, like Rust's `/` and `%`.
+fn integer_arithmetic(
+    operator: &str,
+    left: i64,
+    right: i64,
+    arithmetic: Arithmetic,
+) -> Result<i64, RuntimeError> {
+    if right == 0 && (operator == "/" || operator == "%") {
+        return Err(RuntimeErrorKind::DivisionByZero.into());
+    }
+    let result = match (operator, arithmetic) {
+        ("+", Arithmetic::Checked) => left.checked_add(right),
+        ("-", Arithmetic::Checked) => left.checked_sub(right),
+        ("*", Arithmetic::Checked) => left.checked_mul(right),
+        ("/", Arithmetic::Checked) => left.checked_div(right),
+        ("%", Arithmetic::Checked) => left.checked_rem(right),
+        ("+", Arithmetic::Wrapping) => Some(left.wrapping_add(right)),
+        ("-", Arithmetic::Wrapping) => Some(left.wrapping_sub(right)),
+        ("*", Arithmetic::Wrapping) => Some(left.wrapping_mul(right)),
+        ("/", Arithmetic::Wrapping) => Some(left.wrapping_div(right)),
+        ("%", Arithmetic::Wrapping) => Some(left.wrapping_rem(right)),
+        _ => {
+            return Err(RuntimeErrorKind::UnknownInfixOperator {
+                left: String::from("INTEGER"),
+                operator: String::from(operator),
+                right: String::from("INTEGER"),
+            }
+            .into())
+        }
     };
+    result.ok_or_else(|| RuntimeErrorKind::IntegerOverflow.into())
+}
+
+fn integer_negate(value: i64, arithmetic: Arithmetic) -> Result<i64, RuntimeError> {
+    match arithmetic {
+        Arithmetic::Checked => value
+            .checked_neg()
+            .ok_or_else(|| RuntimeErrorKind::IntegerOverflow.into()),
+        Arithmetic::Wrapping => Ok(value.wrapping_neg()),
+    }
 }
 
-fn eval_integer_infix_expression(operator: &str, left: i64, right: i64) -> Result<Object, String> {
+// Returns both operands as floats when at least one of them is a float and
+// the other is numeric, so integers are promoted in mixed arithmetic.
+fn float_operands(left: &Object, right: &Object) -> Option<(f64, f64)> {
+    match (left, right) {
+        (Object::Float(Float { value: l }), Object::Float(Float { value: r })) => Some((*l, *r)),
+        (Object::Float(Float { value: l }), Object::Integer(Integer { value: r })) => {
+            Some((*l, *r as f64))
+        }
+        (Object::Integer(Integer { value: l }), Object::Float(Float { value: r })) => {
+            Some((*l as f64, *r))
+        }
+        _ => None,
+    }
+}
+
+fn eval_float_infix_expression(
+    operator: &str,
+    left: f64,
+    right: f64,
+) -> Result<Object, RuntimeError> {
     match operator {
-        "+" => Ok(Object::Integer(Integer {
+        "+" => Ok(Object::Float(Float {
             value: left + right,
         })),
-        "-" => Ok(Object::Integer(Integer {
+        "-" => Ok(Object::Float(Float {
             value: left - right,
         })),
-        "*" => Ok(Object::Integer(Integer {
+        "*" => Ok(Object::Float(Float {
             value: left * right,
         })),
-        "/" => Ok(Object::Integer(Integer {
+        "/" => Ok(Object::Float(Float {
             value: left / right,
         })),
+        "%" => Ok(Object::Float(Float {
+            value: left % right,
+        })),
         "<" => Ok(Object::Boolean(native_bool_to_boolean_object(left < right))),
         ">" => Ok(Object::Boolean(native_bool_to_boolean_object(left > right))),
+        "<=" => Ok(Object::Boolean(native_bool_to_boolean_object(
+            left <= right,
+        ))),
+        ">=" => Ok(Object::Boolean(native_bool_to_boolean_object(
+            left >= right,
+        ))),
         "==" => Ok(Object::Boolean(native_bool_to_boolean_object(
             left == right,
         ))),
         "!=" => Ok(Object::Boolean(native_bool_to_boolean_object(
             left != right,
         ))),
-        _ => Err(format!("unknown operator: INTEGER {} INTEGER", operator)),
+        _ => Err(RuntimeErrorKind::UnknownInfixOperator {
+            left: String::from("FLOAT"),
+            operator: String::from(operator),
+            right: String::from("FLOAT"),
+        }
+        .into()),
     }
 }
 
-fn eval_if_expression(ie: IfExpression, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
+// `&&` and `||` only evaluate their right operand when the left one does not
+// decide the result, and return whichever operand decided it.
+fn eval_logical_expression(
+    operator: &str,
+    left: Object,
+    right: Expression,
+    env: Rc<RefCell<Environment>>,
+) -> Result<Object, RuntimeError> {
+    let truthy = is_truthy(left.clone());
+    match operator {
+        "&&" if !truthy => Ok(left),
+        "||" if truthy => Ok(left),
+        _ => eval(Node::Expression(right), env),
+    }
+}
+
+fn eval_if_expression(
+    ie: IfExpression,
+    env: Rc<RefCell<Environment>>,
+) -> Result<Object, RuntimeError> {
     let condition = eval(Node::Expression(*ie.condition), Rc::clone(&env))?;
     if is_truthy(condition) {
         return eval(
@@ -255,31 +438,56 @@ fn is_truthy(obj: Object) -> bool {
 fn eval_block_statement(
     block: BlockStatement,
     env: Rc<RefCell<Environment>>,
-) -> Result<Object, String> {
+) -> Result<Object, RuntimeError> {
     let mut result: Object = Object::Null(NULL);
     for statement in block.statements.into_iter() {
         result = eval(Node::Statement(statement), Rc::clone(&env))?;
-        if let Object::ReturnValue(_) = result {
-            return Ok(result);
+        match result {
+            Object::ReturnValue(_) | Object::Break(_) | Object::Continue(_) => return Ok(result),
+            _ => {}
         }
     }
     Ok(result)
 }
 
-fn eval_identifier(node: Identifier, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
+fn eval_while_statement(
+    while_stmt: WhileStatement,
+    env: Rc<RefCell<Environment>>,
+) -> Result<Object, RuntimeError> {
+    loop {
+        let condition = eval(
+            Node::Expression(while_stmt.condition.clone()),
+            Rc::clone(&env),
+        )?;
+        if !is_truthy(condition) {
+            break;
+        }
+        match eval_block_statement(while_stmt.body.clone(), Rc::clone(&env))? {
+            Object::Break(_) => break,
+            Object::ReturnValue(rv) => return Ok(Object::ReturnValue(rv)),
+            _ => {}
+        }
+    }
+    Ok(Object::Null(NULL))
+}
+
+fn eval_identifier(
+    node: Identifier,
+    env: Rc<RefCell<Environment>>,
+) -> Result<Object, RuntimeError> {
     if let Some(val) = env.borrow().get(&node.value) {
         Ok(val)
-    } else if let Some(builtin) = get_builtin(&node.value) {
+    } else if let Some(builtin) = env.borrow().get_builtin(&node.value) {
         Ok(builtin)
     } else {
-        Err(format!("identifier not found: {}", node.value))
+        Err(RuntimeErrorKind::IdentifierNotFound(node.value).into())
     }
 }
 
 fn eval_expressions(
     exps: Vec<Expression>,
     env: Rc<RefCell<Environment>>,
-) -> Result<Vec<Object>, String> {
+) -> Result<Vec<Object>, RuntimeError> {
     let mut result: Vec<Object> = Vec::new();
     for e in exps.into_iter() {
         let evaluated = eval(Node::Expression(e), Rc::clone(&env))?;
@@ -288,30 +496,39 @@ fn eval_expressions(
     Ok(result)
 }
 
-fn apply_function(func: Object, args: &mut Vec<Object>) -> Result<Object, String> {
+pub fn apply_function(func: Object, args: &mut Vec<Object>) -> Result<Object, RuntimeError> {
     if let Object::Function(function) = func {
-        let extended_env = Rc::new(RefCell::new(extend_function_env(&function, args)));
+        if args.len() != function.parameters.len() {
+            return Err(RuntimeErrorKind::WrongArgumentCount {
+                want: function.parameters.len(),
+                got: args.len(),
+            }
+            .into());
+        }
+        let extended_env = share_environment(extend_function_env(&function, args));
+        let name = function.name.unwrap_or_else(|| String::from("<anonymous>"));
         let evaluated = eval(
             Node::Statement(Statement::BlockStatement(function.body)),
             Rc::clone(&extended_env),
-        )?;
+        )
+        .map_err(|err| err.unwind(&name))?;
         unwrap_return_value(evaluated)
-    } else if let Object::Builtin(Builtin { func }) = func {
-        func(&args)
+    } else if let Object::Builtin(builtin) = func {
+        builtin.call(&args)
     } else {
-        Err(format!("not a function: {:?}", func.get_type()))
+        Err(RuntimeErrorKind::NotAFunction(String::from(func.get_type())).into())
     }
 }
 
 fn extend_function_env(func: &Function, args: &mut Vec<Object>) -> Environment {
     let mut env = new_enclosed_environment(Some(Rc::clone(&func.env)));
     for (param_idx, param) in func.parameters.iter().enumerate() {
-        env.set(param.value.clone(), args[param_idx].clone());
+        env.define(param.value.clone(), args[param_idx].clone());
     }
     env
 }
 
-fn unwrap_return_value(obj: Object) -> Result<Object, String> {
+fn unwrap_return_value(obj: Object) -> Result<Object, RuntimeError> {
     if let Object::ReturnValue(ReturnValue { value }) = obj {
         return Ok(*value);
     }
@@ -322,21 +539,36 @@ fn eval_string_infix_expression(
     operator: &str,
     left: &StringObj,
     right: &StringObj,
-) -> Result<Object, String> {
-    if operator != "+" {
-        return Err(format!(
-            "unknown operator: {} {} {}",
-            left.get_type(),
-            operator,
-            right.get_type()
-        ));
+) -> Result<Object, RuntimeError> {
+    let (left, right) = (&left.value, &right.value);
+    match operator {
+        "+" => Ok(Object::StringObj(StringObj {
+            value: format!("{}{}", left, right),
+        })),
+        "<" => Ok(Object::Boolean(native_bool_to_boolean_object(left < right))),
+        ">" => Ok(Object::Boolean(native_bool_to_boolean_object(left > right))),
+        "<=" => Ok(Object::Boolean(native_bool_to_boolean_object(
+            left <= right,
+        ))),
+        ">=" => Ok(Object::Boolean(native_bool_to_boolean_object(
+            left >= right,
+        ))),
+        "==" => Ok(Object::Boolean(native_bool_to_boolean_object(
+            left == right,
+        ))),
+        "!=" => Ok(Object::Boolean(native_bool_to_boolean_object(
+            left != right,
+        ))),
+        _ => Err(RuntimeErrorKind::UnknownInfixOperator {
+            left: String::from("STRING"),
+            operator: String::from(operator),
+            right: String::from("STRING"),
+        }
+        .into()),
     }
-    return Ok(Object::StringObj(StringObj {
-        value: format!("{}{}", left.value, right.value),
-    }));
 }
 
-fn eval_index_expression(left: &Object, index: &Object) -> Result<Object, String> {
+fn eval_index_expression(left: &Object, index: &Object) -> Result<Object, RuntimeError> {
     if let Object::Array(Array { elements }) = left {
         if let Object::Integer(Integer { value }) = index {
             return eval_array_index_expression(&elements, *value);
@@ -344,10 +576,10 @@ fn eval_index_expression(left: &Object, index: &Object) -> Result<Object, String
     } else if let Object::Hash(hash_obj) = left {
         return eval_hash_index_expression(hash_obj, index);
     }
-    Err(format!("index operator not supported: {}", left.get_type()))
+    Err(RuntimeErrorKind::IndexNotSupported(String::from(left.get_type())).into())
 }
 
-fn eval_array_index_expression(elements: &Vec<Object>, idx: i64) -> Result<Object, String> {
+fn eval_array_index_expression(elements: &Vec<Object>, idx: i64) -> Result<Object, RuntimeError> {
     let max = elements.len() as i64 - 1;
     if idx < 0 || idx > max {
         return Ok(Object::Null(NULL));
@@ -355,7 +587,116 @@ fn eval_array_index_expression(elements: &Vec<Object>, idx: i64) -> Result<Objec
     return Ok(elements[idx as usize].clone());
 }
 
-fn eval_hash_literal(node: HashLiteral, env: Rc<RefCell<Environment>>) -> Result<Object, String> {
+// Evaluates the target's variable and indexes first, then the value. Arrays
+// and hashes are values, so assigning to an element stores an updated copy of
+// the container back into the variable.
+fn eval_assign_expression(
+    node: AssignExpression,
+    env: Rc<RefCell<Environment>>,
+) -> Result<Object, RuntimeError> {
+    let mut target = *node.target;
+    let mut indexes = Vec::new();
+    while let Expression::IndexExpression(IndexExpression { left, index, .. }) = target {
+        indexes.push(*index);
+        target = *left;
+    }
+    let name = match target {
+        Expression::Identifier(ident) => ident.value,
+        other => return Err(RuntimeErrorKind::InvalidAssignment(other.string()).into()),
+    };
+    let operator = node.operator.trim_end_matches('=');
+
+    let current = if indexes.is_empty() && operator.is_empty() {
+        None
+    } else {
+        match env.borrow().get(&name) {
+            Some(obj) => Some(obj),
+            None => return Err(RuntimeErrorKind::IdentifierNotFound(name).into()),
+        }
+    };
+    indexes.reverse();
+    let indexes = eval_expressions(indexes, Rc::clone(&env))?;
+    let value = eval(Node::Expression(*node.value), Rc::clone(&env))?;
+
+    let arithmetic = env.borrow().arithmetic();
+    let (root, assigned) = match current {
+        Some(current) if !indexes.is_empty() => {
+            assign_index(current, &indexes, value, operator, arithmetic)?
+        }
+        Some(current) => {
+            let assigned = eval_infix_expression(operator, &current, &value, arithmetic)?;
+            (assigned.clone(), assigned)
+        }
+        None => (value.clone(), value),
+    };
+    if !env.borrow_mut().set(&name, root) {
+        return Err(RuntimeErrorKind::IdentifierNotFound(name).into());
+    }
+    Ok(assigned)
+}
+
+// Stores value at container[path[0]][path[1]]..., combining it with the old
+// element first if operator is not empty. Returns the updated container and
+// the stored element.
+fn assign_index(
+    container: Object,
+    path: &[Object],
+    value: Object,
+    operator: &str,
+    arithmetic: Arithmetic,
+) -> Result<(Object, Object), RuntimeError> {
+    let element = if path.len() > 1 || !operator.is_empty() {
+        Some(eval_index_expression(&container, &path[0])?)
+    } else {
+        None
+    };
+    let (new_element, assigned) = match element {
+        Some(element) if path.len() > 1 => {
+            assign_index(element, &path[1..], value, operator, arithmetic)?
+        }
+        Some(element) => {
+            let assigned = eval_infix_expression(operator, &element, &value, arithmetic)?;
+            (assigned.clone(), assigned)
+        }
+        None => (value.clone(), value),
+    };
+    Ok((set_index(container, &path[0], new_element)?, assigned))
+}
+
+// Returns a copy of container with the element at index replaced by value.
+pub fn set_index(container: Object, index: &Object, value: Object) -> Result<Object, RuntimeError> {
+    match container {
+        Object::Array(Array { mut elements }) => {
+            if let Object::Integer(Integer { value: idx }) = index {
+                if *idx < 0 || *idx as usize >= elements.len() {
+                    return Err(RuntimeErrorKind::IndexOutOfRange {
+                        index: *idx,
+                        length: elements.len(),
+                    }
+                    .into());
+                }
+                elements[*idx as usize] = value;
+                Ok(Object::Array(Array { elements: elements }))
+            } else {
+                Err(RuntimeErrorKind::IndexNotSupported(String::from("ARRAY")).into())
+            }
+        }
+        Object::Hash(Hash { mut pairs }) => {
+            if let Some(key) = index.as_hashable() {
+                pairs.insert(key.hash_key(), value);
+                Ok(Object::Hash(Hash { pairs: pairs }))
+            } else {
+                Err(RuntimeErrorKind::UnusableAsHashKey(String::from(index.get_type())).into())
+            }
+        }
+        other => Err(RuntimeErrorKind::IndexNotSupported(String::from(other.get_type())).into()),
+    }
+}
+
+fn eval_hash_literal(
+    node: HashLiteral,
+    env: Rc<RefCell<Environment>>,
+) -> Result<Object, RuntimeError> {
     let mut pairs: HashMap<HashKey, Object> = HashMap::new();
 
     for (key_node, value_node) in node.pairs.into_iter() {
@@ -365,19 +706,19 @@ fn eval_hash_literal(node: HashLiteral, env: Rc<RefCell<Environment>>) -> Result
             let hashed = hash_key.hash_key();
             pairs.insert(hashed, value);
         } else {
-            panic!("unusable as hash key: {}", key.get_type());
+            return Err(RuntimeErrorKind::UnusableAsHashKey(String::from(key.get_type())).into());
         }
     }
     Ok(Object::Hash(Hash { pairs: pairs }))
 }
 
-fn eval_hash_index_expression(hash: &Hash, index: &Object) -> Result<Object, String> {
+fn eval_hash_index_expression(hash: &Hash, index: &Object) -> Result<Object, RuntimeError> {
     if let Some(key) = index.as_hashable() {
         if let Some(pair) = hash.pairs.get(&key.hash_key()) {
             return Ok(pair.clone());
         }
         Ok(Object::Null(NULL))
     } else {
-        Err(format!("unusable as hash key: {}", index.get_type()))
+        Err(RuntimeErrorKind::UnusableAsHashKey(String::from(index.get_type())).into())
     }
 }
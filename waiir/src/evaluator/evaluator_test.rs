@@ -27,6 +27,11 @@ fn test_eval_integer_expression() {
         ("3 * 3 * 3 + 10", 37),
         ("3 * (3 * 3) + 10", 37),
         ("(5 + 10 * 2 + 15 / 3) * 2 + -10", 50),
+        ("7 % 3", 1),
+        ("-7 % 3", -1),
+        ("-7 / 2", -3),
+        ("2 + 7 % 4 * 2", 8),
+        ("let a = 10; a %= 4; a", 2),
     ];
 
     for tt in tests.iter() {
@@ -59,6 +64,39 @@ fn test_integer_object(obj: &Object, expected: i64) {
     }
 }
 
+#[test]
+fn test_eval_float_expression() {
+    let tests = [
+        ("2.5", 2.5),
+        ("-2.5", -2.5),
+        ("1e-9", 1e-9),
+        ("0.5 + 0.25", 0.75),
+        ("1 + 0.5", 1.5),
+        ("0.5 * 4", 2.0),
+        ("7 / 2.0", 3.5),
+        ("5.5 % 2", 1.5),
+        ("let x = 1.5; x *= 2; x", 3.0),
+    ];
+
+    for tt in tests.iter() {
+        let evaluated = test_eval(tt.0);
+        test_float_object(&evaluated, tt.1);
+    }
+}
+
+fn test_float_object(obj: &Object, expected: f64) {
+    if let Object::Float(Float { value }) = obj {
+        assert!(
+            *value == expected,
+            "object has wrong value. got={}, want={}",
+            value,
+            expected
+        );
+    } else {
+        panic!("object is not Float. got={:?}", obj);
+    }
+}
+
 #[test]
 fn test_eval_boolean_expression() {
     let tests = [
@@ -81,6 +119,21 @@ fn test_eval_boolean_expression() {
         ("(1 < 2) == false", false),
         ("(1 > 2) == true", false),
         ("(1 > 2) == false", true),
+        ("1.5 < 2", true),
+        ("2.5 > 2.5", false),
+        ("1 == 1.0", true),
+        ("0.1 + 0.2 != 0.3", true),
+        ("1 <= 1", true),
+        ("2 <= 1", false),
+        ("1 >= 2", false),
+        ("2 >= 2", true),
+        ("2.5 >= 2", true),
+        (r#""a" < "b""#, true),
+        (r#""abc" > "abd""#, false),
+        (r#""ab" <= "abc""#, true),
+        (r#""b" >= "abc""#, true),
+        (r#""x" == "x""#, true),
+        (r#""x" != "y""#, true),
     ];
     for tt in tests.iter() {
         let evaluated = test_eval(tt.0);
@@ -178,6 +231,99 @@ fn test_return_statements() {
     }
 }
 
+#[test]
+fn test_logical_expressions() {
+    let tests = [
+        ("true && false", false),
+        ("true || false", true),
+        ("false && undefined", false),
+        ("true || undefined", true),
+        ("1 < 2 && 2 < 3", true),
+        ("let a = 1; false && (a = 2); a == 1", true),
+        ("let a = 1; true && (a = 2); a == 2", true),
+    ];
+    for tt in tests.iter() {
+        let evaluated = test_eval(tt.0);
+        test_boolean_object(evaluated, tt.1);
+    }
+
+    let tests = [
+        ("1 && 2", 2),
+        ("0 || 5", 0),
+        ("if (false) { 1 } || 3", 3),
+        ("false || 4", 4),
+    ];
+    for tt in tests.iter() {
+        let evaluated = test_eval(tt.0);
+        test_integer_object(&evaluated, tt.1);
+    }
+    test_null_object(test_eval("if (false) { 1 } && 3"));
+}
+
+#[test]
+fn test_while_statements() {
+    let tests = [
+        ("while (false) { 1 }", None),
+        ("while (true) { break; }", None),
+        ("while (true) { if (true) { break; } 1 }", None),
+        ("let f = fn() { while (true) { return 5; } }; f()", Some(5)),
+        (
+            "let f = fn(n) { while (n > 0) { if (n > 2) { return n; } break; } 0 }; f(1)",
+            Some(0),
+        ),
+        (
+            "let f = fn() { while (true) { while (true) { break; } return 3; } }; f()",
+            Some(3),
+        ),
+        (
+            "let i = 0; let s = 0; while (i < 5) { i += 1; if (i == 2) { if (true) { continue; } } s += i; } s",
+            Some(13),
+        ),
+    ];
+
+    for tt in tests.iter() {
+        let evaluated = test_eval(tt.0);
+        match tt.1 {
+            Some(expected) => test_integer_object(&evaluated, expected),
+            None => test_null_object(evaluated),
+        }
+    }
+}
+
+#[test]
+fn test_assignments() {
+    let tests = [
+        ("let a = 1; a = 2; a", 2),
+        ("let a = 1; a = a + 1", 2),
+        ("let a = 1; let b = 2; a = b = 3; a + b", 6),
+        ("let a = 5; a += 2; a -= 1; a *= 3; a /= 2", 9),
+        ("let a = 1; let f = fn() { a = 10; }; f(); a", 10),
+        ("let a = 1; let f = fn() { let a = 2; a = 3; }; f(); a", 1),
+        (
+            "let counter = fn() { let n = 0; fn() { n += 1 } }; let c = counter(); c(); c(); c()",
+            3,
+        ),
+        (
+            "let i = 0; let s = 0; while (i < 5) { i += 1; s += i; } s",
+            15,
+        ),
+        ("let a = [1, 2, 3]; a[1] = 5; a[0] + a[1] + a[2]", 9),
+        ("let a = [1, 2]; let b = a; a[0] = 9; b[0]", 1),
+        ("let a = [[1, 2], [3, 4]]; a[1][0] += 10; a[1][0]", 13),
+        ("let a = [1]; a[0] = 7", 7),
+        (
+            r#"let h = {"a": 1}; h["a"] += 1; h["b"] = 5; h["a"] + h["b"]"#,
+            7,
+        ),
+        (r#"let h = {"xs": [1, 2]}; h["xs"][1] = 4; h["xs"][1]"#, 4),
+    ];
+
+    for tt in tests.iter() {
+        let evaluated = test_eval(tt.0);
+        test_integer_object(&evaluated, tt.1);
+    }
+}
+
 #[test]
 fn test_error_handling() {
     let tests = [
@@ -206,6 +352,34 @@ return 1;
             r#"{"name": "Monkey"}[fn(x){ x }];"#,
             "unusable as hash key: FUNCTION",
         ),
+        (r#"{1.5: "x"}"#, "unusable as hash key: FLOAT"),
+        ("1.5 + true", "type mismatch: FLOAT + BOOLEAN"),
+        ("1 / 0", "division by zero"),
+        ("let a = 5; a %= 0", "division by zero"),
+        ("9223372036854775807 + 1", "integer overflow"),
+        ("-9223372036854775807 - 2", "integer overflow"),
+        ("4611686018427387904 * 2", "integer overflow"),
+        ("(-9223372036854775807 - 1) / -1", "integer overflow"),
+        ("(-9223372036854775807 - 1) % -1", "integer overflow"),
+        ("-(-9223372036854775807 - 1)", "integer overflow"),
+        (
+            "fn(x) { x }(1, 2)",
+            "wrong number of arguments: want=1, got=2",
+        ),
+        ("b = 1", "identifier not found: b"),
+        ("let a = 1; a += true", "type mismatch: INTEGER + BOOLEAN"),
+        (
+            "let a = [1, 2]; a[2] = 3",
+            "index out of range: index=2, length=2",
+        ),
+        (
+            "let a = 1; a[0] = 3",
+            "index operator not supported: INTEGER",
+        ),
+        (
+            r#"let h = {}; h[fn(x) { x }] = 1"#,
+            "unusable as hash key: FUNCTION",
+        ),
     ];
 
     for tt in tests.iter() {
@@ -223,6 +397,74 @@ return 1;
     }
 }
 
+#[test]
+fn test_error_positions() {
+    let tests = [
+        ("5 + true;", "1:3: type mismatch: INTEGER + BOOLEAN"),
+        ("let a = 1;\n  -true", "2:3: unknown operator: -BOOLEAN"),
+        (
+            "let f = fn() { foobar };\nf()",
+            "1:16: identifier not found: foobar",
+        ),
+    ];
+
+    for tt in tests.iter() {
+        let env = Rc::new(RefCell::new(new_environment()));
+        let l = Lexer::new(String::from(tt.0));
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        match try_evaluate(program, env) {
+            Ok(obj) => panic!("no error returned. got={:?}", obj),
+            Err(err) => assert!(
+                err.to_string() == tt.1,
+                "wrong error. expected={}, got={}",
+                tt.1,
+                err
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_error_backtrace() {
+    let tests = [
+        ("1 / 0", "    at <main> (1:3)\n"),
+        (
+            "let inner = fn(x) {\n  x / 0\n};\nlet outer = fn() { inner(1) };\nouter()",
+            "    at inner (2:5)\n    at outer (4:25)\n    at <main> (5:6)\n",
+        ),
+        (
+            "let f = fn(g) { g() };\nf(fn() { [] + 1 })",
+            "    at <anonymous> (2:13)\n    at f (1:18)\n    at <main> (2:2)\n",
+        ),
+        (
+            "let f = fn() { len(1) };\nlet g = f;\ng()",
+            "    at f (1:19)\n    at <main> (3:2)\n",
+        ),
+        (
+            "let f = fn(n) { if (n == 0) { 1 / 0 } else { 1 + f(n - 1) } };\nf(5);",
+            "    at f (1:33)\n    at f (1:51)\n    ... repeated 4 more times\n    at <main> (2:2)\n",
+        ),
+    ];
+
+    for tt in tests.iter() {
+        let env = Rc::new(RefCell::new(new_environment()));
+        let l = Lexer::new(String::from(tt.0));
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        match try_evaluate(program, env) {
+            Ok(obj) => panic!("no error returned. got={:?}", obj),
+            Err(err) => assert!(
+                err.backtrace() == tt.1,
+                "wrong backtrace for {:?}. expected={:?}, got={:?}",
+                tt.0,
+                tt.1,
+                err.backtrace()
+            ),
+        }
+    }
+}
+
 #[test]
 fn test_let_statements() {
     let tests = [
@@ -245,6 +487,7 @@ fn test_function_object() {
         parameters,
         body,
         env: _,
+        name: _,
     }) = evaluated
     {
         assert!(
@@ -337,6 +580,11 @@ fn test_builtin_functions() {
             r#"len("hello world")"#,
             Object::Integer(Integer { value: 11 }),
         ),
+        (r#"len("你好")"#, Object::Integer(Integer { value: 2 })),
+        (
+            r#"len("👍\u{1F600}")"#,
+            Object::Integer(Integer { value: 2 }),
+        ),
         (
             r#"len(1)"#,
             Object::ErrorObj(ErrorObj {
@@ -346,7 +594,7 @@ fn test_builtin_functions() {
         (
             r#"len("one", "two")"#,
             Object::ErrorObj(ErrorObj {
-                message: String::from("wrong number of arguments. got=2, want=1"),
+                message: String::from("wrong number of arguments: want=1, got=2"),
             }),
         ),
     ];
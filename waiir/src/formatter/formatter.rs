@@ -0,0 +1,363 @@
+// src/formatter/formatter.rs
+
+use crate::ast::*;
+use crate::lexer::*;
+use crate::parser::*;
+use crate::token::*;
+use std::fmt;
+
+const INDENT: &str = "    ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    Parse(Vec<ParseError>),
+    LostComment(Trivia),
+}
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Parse(errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            FormatError::LostComment(comment) => write!(
+                f,
+                "{}: the formatter cannot keep a comment in this position",
+                comment.span
+            ),
+        }
+    }
+}
+impl std::error::Error for FormatError {}
+
+// Formats Monkey source. Comments are kept when they sit between statements;
+// the input is rejected rather than silently losing any other comment.
+pub fn format_source(input: &str) -> Result<String, FormatError> {
+    let l = Lexer::new(String::from(input));
+    let mut p = Parser::new(l);
+    let program = p.parse_program().map_err(FormatError::Parse)?;
+    let output = format_program(&program);
+
+    let before = comments(input);
+    let after = comments(&output);
+    for (i, comment) in before.into_iter().enumerate() {
+        if after.get(i).map(|c| &c.text) != Some(&comment.text) {
+            return Err(FormatError::LostComment(comment));
+        }
+    }
+    Ok(output)
+}
+
+// Prints program as idiomatic source that parses back to the same program:
+// one statement per line, four-space indentation and only the parentheses
+// that operator precedence requires.
+pub fn format_program(program: &Program) -> String {
+    let mut f = Formatter {
+        out: String::new(),
+        indent: 0,
+    };
+    f.write_statements(&program.statements, &program.trailing);
+    f.out
+}
+
+struct Formatter {
+    out: String,
+    indent: usize,
+}
+impl Formatter {
+    fn write_statements(&mut self, statements: &[Statement], trailing: &[Trivia]) {
+        let formatted: Vec<String> = statements
+            .iter()
+            .map(|stmt| self.nested(|f| f.write_statement(stmt)))
+            .collect();
+        for (i, stmt) in statements.iter().enumerate() {
+            if self.write_trivia(&statement_token(stmt).trivia, i == 0) {
+                self.out.push('\n');
+            }
+            self.write_indent();
+            self.out.push_str(&formatted[i]);
+            // An if expression needs no semicolon unless the next statement
+            // would otherwise be read as its continuation, e.g. `-1` or `(x)`.
+            if let Statement::ExpressionStatement(ExpressionStatement {
+                expression: Expression::IfExpression(_),
+                ..
+            }) = stmt
+            {
+                let next = formatted.get(i + 1).and_then(|s| s.chars().next());
+                if let Some('-') | Some('(') | Some('[') = next {
+                    self.out.push(';');
+                }
+            }
+            self.out.push('\n');
+        }
+        self.write_trivia(trailing, statements.is_empty());
+    }
+
+    // Writes the comments in trivia, keeping a comment on the line of the
+    // preceding code if it was there in the source. Blank lines are kept,
+    // except at the start of a block or file. Returns whether a blank line
+    // should separate the next statement from what comes before it.
+    fn write_trivia(&mut self, trivia: &[Trivia], mut at_start: bool) -> bool {
+        let mut newlines = 0;
+        for t in trivia.iter() {
+            if t.kind == TriviaKind::Newline {
+                newlines += 1;
+                continue;
+            }
+            if newlines == 0 && !self.out.is_empty() {
+                self.out.pop();
+                self.out.push(' ');
+            } else {
+                if newlines > 1 && !at_start {
+                    self.out.push('\n');
+                }
+                self.write_indent();
+                at_start = false;
+            }
+            self.out.push_str(&t.text);
+            self.out.push('\n');
+            newlines = 0;
+        }
+        newlines > 1 && !at_start
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    // Runs write against an empty buffer at the current indentation and
+    // returns what it wrote.
+    fn nested<F: FnOnce(&mut Formatter)>(&mut self, write: F) -> String {
+        let mut f = Formatter {
+            out: String::new(),
+            indent: self.indent,
+        };
+        write(&mut f);
+        f.out
+    }
+
+    fn write_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::LetStatement(let_stmt) => {
+                self.out.push_str("let ");
+                self.out.push_str(&let_stmt.name.value);
+                self.out.push_str(" = ");
+                self.write_expression(&let_stmt.value);
+                self.out.push(';');
+            }
+            Statement::ReturnStatement(return_stmt) => {
+                self.out.push_str("return ");
+                self.write_expression(&return_stmt.return_value);
+                self.out.push(';');
+            }
+            Statement::ExpressionStatement(expr_stmt) => {
+                self.write_expression(&expr_stmt.expression);
+                if let Expression::IfExpression(_) = expr_stmt.expression {
+                    return;
+                }
+                self.out.push(';');
+            }
+            Statement::BlockStatement(block_stmt) => self.write_block(block_stmt),
+            Statement::WhileStatement(while_stmt) => {
+                self.out.push_str("while (");
+                self.write_expression(&while_stmt.condition);
+                self.out.push_str(") ");
+                self.write_block(&while_stmt.body);
+            }
+            Statement::BreakStatement(_) => self.out.push_str("break;"),
+            Statement::ContinueStatement(_) => self.out.push_str("continue;"),
+        }
+    }
+
+    fn write_block(&mut self, block: &BlockStatement) {
+        let has_comments = block.trailing.iter().any(|t| t.kind != TriviaKind::Newline);
+        if block.statements.is_empty() && !has_comments {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push_str("{\n");
+        self.indent += 1;
+        self.write_statements(&block.statements, &block.trailing);
+        self.indent -= 1;
+        self.write_indent();
+        self.out.push('}');
+    }
+
+    fn write_expression(&mut self, exp: &Expression) {
+        match exp {
+            Expression::Identifier(ident) => self.out.push_str(&ident.value),
+            Expression::IntegerLiteral(integer_literal) => {
+                self.out.push_str(&integer_literal.token.literal)
+            }
+            Expression::FloatLiteral(float_literal) => {
+                self.out.push_str(&float_literal.token.literal)
+            }
+            Expression::BooleanLiteral(bo) => {
+                self.out.push_str(if bo.value { "true" } else { "false" })
+            }
+            Expression::StringLiteral(string_literal) => {
+                self.out.push_str(&quote(&string_literal.value))
+            }
+            Expression::PrefixExpression(prefix_expr) => {
+                self.out.push_str(&prefix_expr.operator);
+                let parens = precedence(&prefix_expr.right) < Precedence::PREFIX;
+                self.write_operand(&prefix_expr.right, parens);
+            }
+            Expression::InfixExpression(infix_expr) => {
+                // Infix operators are left-associative, so a right operand of
+                // the same precedence must keep its parentheses.
+                let p = get_precedence(&infix_expr.token.r#type);
+                let parens = precedence(&infix_expr.left) < p;
+                self.write_operand(&infix_expr.left, parens);
+                self.out.push(' ');
+                self.out.push_str(&infix_expr.operator);
+                self.out.push(' ');
+                let parens = precedence(&infix_expr.right) <= p;
+                self.write_operand(&infix_expr.right, parens);
+            }
+            Expression::AssignExpression(assign_expr) => {
+                self.write_expression(&assign_expr.target);
+                self.out.push(' ');
+                self.out.push_str(&assign_expr.operator);
+                self.out.push(' ');
+                self.write_expression(&assign_expr.value);
+            }
+            Expression::IfExpression(if_expr) => {
+                self.out.push_str("if (");
+                self.write_expression(&if_expr.condition);
+                self.out.push_str(") ");
+                self.write_block(&if_expr.consequence);
+                if let Some(alternative) = &if_expr.alternative {
+                    self.out.push_str(" else ");
+                    self.write_block(alternative);
+                }
+            }
+            Expression::FunctionLiteral(function_literal) => {
+                self.out.push_str("fn(");
+                let parameters: Vec<&str> = function_literal
+                    .parameters
+                    .iter()
+                    .map(|x| &x.value[..])
+                    .collect();
+                self.out.push_str(&parameters.join(", "));
+                self.out.push_str(") ");
+                self.write_block(&function_literal.body);
+            }
+            Expression::CallExpression(call_expr) => {
+                let parens = precedence(&call_expr.function) < Precedence::CALL;
+                self.write_operand(&call_expr.function, parens);
+                self.out.push('(');
+                self.write_expression_list(&call_expr.arguments);
+                self.out.push(')');
+            }
+            Expression::ArrayLiteral(array_literal) => {
+                self.out.push('[');
+                self.write_expression_list(&array_literal.elements);
+                self.out.push(']');
+            }
+            Expression::IndexExpression(index_expr) => {
+                let parens = precedence(&index_expr.left) < Precedence::INDEX;
+                self.write_operand(&index_expr.left, parens);
+                self.out.push('[');
+                self.write_expression(&index_expr.index);
+                self.out.push(']');
+            }
+            Expression::HashLiteral(hash_literal) => {
+                // The pairs are unordered in the AST; print them in source order.
+                let mut pairs: Vec<(&Expression, &Expression)> =
+                    hash_literal.pairs.iter().collect();
+                pairs.sort_by_key(|(k, _)| k.span().start);
+                self.out.push('{');
+                for (i, (k, v)) in pairs.into_iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.write_expression(k);
+                    self.out.push_str(": ");
+                    self.write_expression(v);
+                }
+                self.out.push('}');
+            }
+        }
+    }
+
+    fn write_operand(&mut self, exp: &Expression, parens: bool) {
+        if parens {
+            self.out.push('(');
+        }
+        self.write_expression(exp);
+        if parens {
+            self.out.push(')');
+        }
+    }
+
+    fn write_expression_list(&mut self, list: &[Expression]) {
+        for (i, exp) in list.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.write_expression(exp);
+        }
+    }
+}
+
+// The precedence exp was parsed at. Anything that is not built from an
+// operator binds tighter than every operator.
+fn precedence(exp: &Expression) -> Precedence {
+    match exp {
+        Expression::InfixExpression(infix_expr) => get_precedence(&infix_expr.token.r#type),
+        Expression::AssignExpression(_) => Precedence::ASSIGN,
+        Expression::PrefixExpression(_) => Precedence::PREFIX,
+        _ => Precedence::INDEX,
+    }
+}
+
+fn statement_token(stmt: &Statement) -> &Token {
+    match stmt {
+        Statement::LetStatement(x) => &x.token,
+        Statement::ReturnStatement(x) => &x.token,
+        Statement::ExpressionStatement(x) => &x.token,
+        Statement::BlockStatement(x) => &x.token,
+        Statement::WhileStatement(x) => &x.token,
+        Statement::BreakStatement(x) => &x.token,
+        Statement::ContinueStatement(x) => &x.token,
+    }
+}
+
+// Writes value as a string literal, escaping what the lexer would not read
+// back verbatim.
+fn quote(value: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\0' => out.push_str("\\0"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn comments(input: &str) -> Vec<Trivia> {
+    let mut l = Lexer::new(String::from(input));
+    let mut comments = Vec::new();
+    loop {
+        let tok = l.next_token();
+        comments.extend(
+            tok.trivia
+                .into_iter()
+                .filter(|t| t.kind != TriviaKind::Newline),
+        );
+        if tok.r#type == TokenType::EOF {
+            return comments;
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// src/formatter/formatter_test.rs
+
+use crate::ast::*;
+use crate::formatter::*;
+use crate::lexer::*;
+use crate::parser::*;
+
+#[test]
+fn test_format_source() {
+    let tests = [
+        ("let   x=5", "let x = 5;\n"),
+        ("return x;", "return x;\n"),
+        ("a+b*c", "a + b * c;\n"),
+        ("(a+b)*c", "(a + b) * c;\n"),
+        ("a-(b-c)", "a - (b - c);\n"),
+        ("(a-b)-c", "a - b - c;\n"),
+        ("-(a+b)", "-(a + b);\n"),
+        ("-a*b", "-a * b;\n"),
+        ("!(a==b)", "!(a == b);\n"),
+        ("--5", "--5;\n"),
+        ("a&&b||c", "a && b || c;\n"),
+        ("a&&(b||c)", "a && (b || c);\n"),
+        ("(a+b)(c)", "(a + b)(c);\n"),
+        ("(-a)[0]", "(-a)[0];\n"),
+        ("f(1)[0](2)", "f(1)[0](2);\n"),
+        ("a=b=c", "a = b = c;\n"),
+        ("x[0]+=1", "x[0] += 1;\n"),
+        ("1+(a=2)", "1 + (a = 2);\n"),
+        ("[1,2.5,\"a\"]", "[1, 2.5, \"a\"];\n"),
+        ("{\"b\":2,\"a\":1}", "{\"b\": 2, \"a\": 1};\n"),
+        ("{}", "{};\n"),
+        ("\"tab\\tquote\\\"\\u{7}\"", "\"tab\\tquote\\\"\\u{7}\";\n"),
+        ("fn(){}", "fn() {};\n"),
+        (
+            "let add=fn(x,y){x+y}",
+            "let add = fn(x, y) {\n    x + y;\n};\n",
+        ),
+        (
+            "if(x<y){x}else{y}",
+            "if (x < y) {\n    x;\n} else {\n    y;\n}\n",
+        ),
+        ("if (x) { 1 }; -1", "if (x) {\n    1;\n};\n-1;\n"),
+        ("if (x) { 1 } y", "if (x) {\n    1;\n}\ny;\n"),
+        (
+            "while(i<3){if(i==1){break;}continue;}",
+            "while (i < 3) {\n    if (i == 1) {\n        break;\n    }\n    continue;\n}\n",
+        ),
+    ];
+
+    for tt in tests.iter() {
+        let output = format_source(tt.0).unwrap();
+        assert!(
+            output == tt.1,
+            "wrong output for {:?}. expected={:?}, got={:?}",
+            tt.0,
+            tt.1,
+            output
+        );
+    }
+}
+
+#[test]
+fn test_format_comments() {
+    let input = "// header
+
+
+let x = 1; // one
+/* two */
+let y = 2;
+let f = fn() { // body
+
+    x // last
+
+    // end
+};
+// trailing
+";
+    let expected = "// header
+
+let x = 1; // one
+/* two */
+let y = 2;
+let f = fn() { // body
+    x; // last
+
+    // end
+};
+// trailing
+";
+
+    let output = format_source(input).unwrap();
+    assert!(
+        output == expected,
+        "wrong output. expected={:?}, got={:?}",
+        expected,
+        output
+    );
+
+    match format_source("let x = 1 + /* two */ 2;") {
+        Err(FormatError::LostComment(comment)) => assert!(
+            comment.text == "/* two */" && comment.span.column == 13,
+            "wrong comment. got={:?}",
+            comment
+        ),
+        other => panic!("expected a lost comment. got={:?}", other),
+    }
+}
+
+#[test]
+fn test_format_round_trip() {
+    let input = "
+let fibonacci = fn(x) {
+    if (x == 0) { 0 } else {
+        if (x == 1) { return 1; }
+        fibonacci(x - 1) + fibonacci(x - 2)
+    }
+};
+let map = fn(arr, f) {
+    let iter = fn(arr, accumulated) {
+        if (len(arr) == 0) { accumulated }
+        else { iter(rest(arr), push(accumulated, f(first(arr)))) }
+    };
+    iter(arr, [])
+};
+let people = [{\"name\": \"Alice\"}, {\"name\": \"Anna\"}];
+let total = 0;
+while (total < 10 && !(total % 3 == 1)) { total += 1; }
+puts(-fibonacci(10) * 2 / (1 - 3.5e2), people[0][\"name\"], \"a\\nb\");
+";
+
+    let program = parse(input);
+    let output = format_program(&program);
+    let reparsed = parse(&output);
+    assert!(
+        reparsed.string() == program.string(),
+        "round trip changed the program. expected={}, got={}",
+        program.string(),
+        reparsed.string()
+    );
+    assert!(
+        format_program(&reparsed) == output,
+        "formatting is not idempotent. got={:?}",
+        format_program(&reparsed)
+    );
+}
+
+fn parse(input: &str) -> Program {
+    let l = Lexer::new(String::from(input));
+    let mut p = Parser::new(l);
+    p.parse_program().unwrap()
+}
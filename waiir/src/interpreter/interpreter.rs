@@ -0,0 +1,154 @@
+// src/interpreter/interpreter.rs
+
+use crate::ast::*;
+use crate::evaluator::*;
+use crate::lexer::*;
+use crate::object::*;
+use crate::parser::*;
+use std::cell::*;
+use std::fmt;
+use std::rc::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterpreterError {
+    Parse(Vec<ParseError>),
+    Runtime(RuntimeError),
+}
+impl fmt::Display for InterpreterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpreterError::Parse(errors) => write!(
+                f,
+                "{}",
+                errors
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            ),
+            InterpreterError::Runtime(err) => write!(f, "{}", err),
+        }
+    }
+}
+impl std::error::Error for InterpreterError {}
+impl From<Vec<ParseError>> for InterpreterError {
+    fn from(errors: Vec<ParseError>) -> Self {
+        InterpreterError::Parse(errors)
+    }
+}
+impl From<RuntimeError> for InterpreterError {
+    fn from(err: RuntimeError) -> Self {
+        InterpreterError::Runtime(err)
+    }
+}
+
+pub fn parse(input: &str) -> Result<Program, Vec<ParseError>> {
+    let l = Lexer::new(String::from(input));
+    let mut p = Parser::new(l);
+    p.parse_program()
+}
+
+// Entry point for host applications: keeps one global environment alive
+// across evaluations so scripts can be loaded piece by piece.
+pub struct Interpreter {
+    env: Rc<RefCell<Environment>>,
+    builtins: Rc<Builtins>,
+}
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        let builtins = Rc::new(new_builtins());
+        let env = Rc::new(RefCell::new(new_environment_with_builtins(Rc::clone(
+            &builtins,
+        ))));
+        // Tracked so that it can be freed once the interpreter is dropped.
+        if let Some(heap) = env.borrow().heap() {
+            heap.track(&env);
+        }
+        Interpreter {
+            env: env,
+            builtins: builtins,
+        }
+    }
+
+    // Makes a native function callable from scripts under name. An arity of
+    // None accepts any number of arguments.
+    pub fn register_builtin<F>(&mut self, name: &str, arity: Option<usize>, func: F)
+    where
+        F: Fn(&[Object]) -> Result<Object, RuntimeError> + 'static,
+    {
+        Rc::make_mut(&mut self.builtins).register(name, arity, func);
+        self.env.borrow_mut().builtins = Some(Rc::clone(&self.builtins));
+    }
+
+    // Selects what integer arithmetic does on overflow. Checked by default.
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.env.borrow_mut().arithmetic = arithmetic;
+    }
+
+    pub fn eval(&mut self, input: &str) -> Result<Object, InterpreterError> {
+        let program = parse(input)?;
+        self.eval_program(program)
+    }
+
+    pub fn eval_program(&mut self, program: Program) -> Result<Object, InterpreterError> {
+        Ok(try_evaluate(program, Rc::clone(&self.env))?)
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        let env = self.env.borrow();
+        env.get(name).or_else(|| env.get_builtin(name))
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Object) {
+        self.env.borrow_mut().define(String::from(name), value);
+    }
+
+    // Calls the function bound to `name` with already evaluated arguments.
+    pub fn call(&mut self, name: &str, args: Vec<Object>) -> Result<Object, InterpreterError> {
+        match self.get_global(name) {
+            Some(func) => self.call_function(func, args),
+            None => Err(
+                RuntimeError::new(RuntimeErrorKind::IdentifierNotFound(String::from(name))).into(),
+            ),
+        }
+    }
+
+    pub fn call_function(
+        &mut self,
+        func: Object,
+        mut args: Vec<Object>,
+    ) -> Result<Object, InterpreterError> {
+        Ok(apply_function(func, &mut args)?)
+    }
+
+    // Frees environments that are only kept alive by reference cycles, such
+    // as a closure stored in the scope it captured. Returns how many were
+    // freed.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.heap().collect()
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        self.heap().stats()
+    }
+
+    fn heap(&self) -> Rc<Heap> {
+        self.env.borrow().heap().unwrap()
+    }
+}
+impl Drop for Interpreter {
+    // Every function defined at the top level refers back to the global
+    // environment, so it is only freed by a collection once the interpreter
+    // no longer holds it. Values the host still holds keep the environments
+    // they refer to alive, and those are not collected afterwards.
+    fn drop(&mut self) {
+        let heap = self.heap();
+        self.env = Rc::new(RefCell::new(new_enclosed_environment(None)));
+        heap.collect();
+    }
+}
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
@@ -0,0 +1,286 @@
+// src/interpreter/interpreter_test.rs
+
+use crate::interpreter::*;
+use crate::object::*;
+use crate::parser::*;
+use crate::token::*;
+use std::cell::*;
+use std::rc::*;
+
+#[test]
+fn test_eval_keeps_globals() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("let a = 5;").unwrap();
+    let result = interpreter.eval("a * 2").unwrap();
+    assert!(
+        result == Object::Integer(Integer { value: 10 }),
+        "wrong result. got={:?}",
+        result
+    );
+}
+
+#[test]
+fn test_get_and_set_globals() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_global("limit", Object::Integer(Integer { value: 3 }));
+    interpreter.eval("let doubled = limit + limit;").unwrap();
+
+    let got = interpreter.get_global("doubled");
+    assert!(
+        got == Some(Object::Integer(Integer { value: 6 })),
+        "wrong global. got={:?}",
+        got
+    );
+    assert!(
+        interpreter.get_global("missing").is_none(),
+        "unexpected global"
+    );
+}
+
+#[test]
+fn test_call_function() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("let add = fn(a, b) { a + b };").unwrap();
+
+    let result = interpreter
+        .call(
+            "add",
+            vec![
+                Object::Integer(Integer { value: 1 }),
+                Object::Integer(Integer { value: 2 }),
+            ],
+        )
+        .unwrap();
+    assert!(
+        result == Object::Integer(Integer { value: 3 }),
+        "wrong result. got={:?}",
+        result
+    );
+
+    let result = interpreter
+        .call(
+            "len",
+            vec![Object::StringObj(StringObj {
+                value: String::from("four"),
+            })],
+        )
+        .unwrap();
+    assert!(
+        result == Object::Integer(Integer { value: 4 }),
+        "wrong result. got={:?}",
+        result
+    );
+}
+
+#[test]
+fn test_errors() {
+    let mut interpreter = Interpreter::new();
+    match interpreter.eval("let = 1;") {
+        Err(InterpreterError::Parse(errors)) => assert!(
+            errors[0].kind
+                == ParseErrorKind::UnexpectedToken {
+                    expected: TokenType::IDENT,
+                    found: TokenType::ASSIGN,
+                },
+            "wrong parse error. got={:?}",
+            errors
+        ),
+        other => panic!("expected parse error. got={:?}", other),
+    }
+    match interpreter.eval("1 + true") {
+        Err(InterpreterError::Runtime(err)) => assert!(
+            err.kind
+                == RuntimeErrorKind::TypeMismatch {
+                    left: String::from("INTEGER"),
+                    operator: String::from("+"),
+                    right: String::from("BOOLEAN"),
+                },
+            "wrong runtime error. got={:?}",
+            err
+        ),
+        other => panic!("expected runtime error. got={:?}", other),
+    }
+    match interpreter.call("missing", Vec::new()) {
+        Err(InterpreterError::Runtime(err)) => assert!(
+            err.kind == RuntimeErrorKind::IdentifierNotFound(String::from("missing")),
+            "wrong runtime error. got={:?}",
+            err
+        ),
+        other => panic!("expected runtime error. got={:?}", other),
+    }
+}
+
+#[test]
+fn test_register_builtin() {
+    let calls = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&calls);
+
+    let mut interpreter = Interpreter::new();
+    interpreter.register_builtin("double", Some(1), move |args| {
+        counter.set(counter.get() + 1);
+        match &args[0] {
+            Object::Integer(Integer { value }) => Ok(Object::Integer(Integer { value: value * 2 })),
+            other => Err(RuntimeErrorKind::ArgumentTypeMismatch {
+                function: String::from("double"),
+                want: String::from("INTEGER"),
+                got: String::from(other.get_type()),
+            }
+            .into()),
+        }
+    });
+
+    let result = interpreter
+        .eval("let f = fn(x) { double(x) + 1 }; f(20) + double(1)")
+        .unwrap();
+    assert!(
+        result == Object::Integer(Integer { value: 43 }),
+        "wrong result. got={:?}",
+        result
+    );
+    assert!(
+        calls.get() == 2,
+        "wrong number of calls. got={}",
+        calls.get()
+    );
+
+    let tests = [
+        ("double(1, 2)", "wrong number of arguments: want=1, got=2"),
+        (
+            "double(true)",
+            "argument to `double` must be INTEGER, got BOOLEAN",
+        ),
+    ];
+    for tt in tests.iter() {
+        match interpreter.eval(tt.0) {
+            Err(InterpreterError::Runtime(err)) => assert!(
+                err.kind.to_string() == tt.1,
+                "wrong error. expected={}, got={}",
+                tt.1,
+                err.kind
+            ),
+            other => panic!("expected runtime error. got={:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_while_loops() {
+    let ticks = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&ticks);
+
+    let mut interpreter = Interpreter::new();
+    interpreter.register_builtin("tick", Some(0), move |_| {
+        counter.set(counter.get() + 1);
+        Ok(Object::Integer(Integer {
+            value: counter.get(),
+        }))
+    });
+
+    let result = interpreter
+        .eval(
+            "let n = 0;
+            while (true) {
+                let n = tick();
+                if (n < 3) { continue; }
+                if (n == 5) { break; }
+            }
+            n",
+        )
+        .unwrap();
+    assert!(
+        result == Object::Integer(Integer { value: 5 }),
+        "wrong result. got={:?}",
+        result
+    );
+    assert!(
+        ticks.get() == 5,
+        "wrong number of ticks. got={}",
+        ticks.get()
+    );
+}
+
+#[test]
+fn test_wrapping_arithmetic() {
+    let mut interpreter = Interpreter::new();
+    let err = interpreter.eval("9223372036854775807 + 1").unwrap_err();
+    assert!(
+        err.to_string() == "1:21: integer overflow",
+        "wrong error. got={}",
+        err
+    );
+
+    interpreter.set_arithmetic(Arithmetic::Wrapping);
+    let tests = [
+        ("let f = fn(x) { x + 1 }; f(9223372036854775807)", i64::MIN),
+        ("-(-9223372036854775807 - 1)", i64::MIN),
+        ("(-9223372036854775807 - 1) / -1", i64::MIN),
+        (
+            "let a = [3037000500]; a[0] *= a[0]; a[0]",
+            -9223372036709301616,
+        ),
+    ];
+    for tt in tests.iter() {
+        let result = interpreter.eval(tt.0).unwrap();
+        assert!(
+            result == Object::Integer(Integer { value: tt.1 }),
+            "wrong result for {}. got={:?}",
+            tt.0,
+            result
+        );
+    }
+
+    let err = interpreter.eval("1 % 0").unwrap_err();
+    assert!(
+        err.to_string() == "1:3: division by zero",
+        "wrong error. got={}",
+        err
+    );
+}
+
+#[test]
+fn test_collect_garbage() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval(
+            "let counter = fn() { let n = 0; let next = fn() { n = n + 1; n }; next };
+counter();
+let c = counter();",
+        )
+        .unwrap();
+
+    let stats = interpreter.heap_stats();
+    assert!(
+        stats.environments == 3,
+        "wrong number of live environments. got={:?}",
+        stats
+    );
+    let freed = interpreter.collect_garbage();
+    assert!(
+        freed == 1,
+        "wrong number of freed environments. got={}",
+        freed
+    );
+    let stats = interpreter.heap_stats();
+    assert!(
+        stats.environments == 2 && stats.collections == 1 && stats.freed == 1,
+        "wrong stats after collecting. got={:?}",
+        stats
+    );
+
+    let c = interpreter.get_global("c").unwrap();
+    let env = match &c {
+        Object::Function(f) => Rc::downgrade(&f.env),
+        other => panic!("c is not a function. got={:?}", other),
+    };
+    let result = interpreter.call_function(c.clone(), vec![]).unwrap();
+    assert!(
+        result == Object::Integer(Integer { value: 1 }),
+        "wrong result. got={:?}",
+        result
+    );
+
+    // Everything is freed with the interpreter once the host lets go.
+    drop(c);
+    drop(interpreter);
+    assert!(env.upgrade().is_none(), "environment leaked");
+}
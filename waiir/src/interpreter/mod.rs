@@ -0,0 +1,5 @@
+mod interpreter;
+pub use interpreter::*;
+
+#[cfg(test)]
+mod interpreter_test;
@@ -1,12 +1,15 @@
 // src/lexer/lexer.rs
 
 use crate::token::*;
+use unicode_xid::UnicodeXID;
 
 pub struct Lexer {
     input: String,
-    position: usize,      // 当前字符位置
+    position: usize,      // 当前字符位置（字节偏移）
     read_position: usize, // 当前读取位置（在当前字符位置之后）
-    ch: u8,               // 当前字符
+    ch: char,             // 当前字符
+    line: usize,          // 当前字符所在行
+    column: usize,        // 当前字符所在列
 }
 impl Lexer {
     pub fn new(input: String) -> Lexer {
@@ -14,89 +17,183 @@ impl Lexer {
             input: input,
             position: 0,
             read_position: 0,
-            ch: 0,
+            ch: '\0',
+            line: 1,
+            column: 0,
         };
         l.read_char();
         l
     }
 
     pub fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = 0;
+        if self.ch == '\n' {
+            self.line += 1;
+            self.column = 1;
         } else {
-            self.ch = self.input.bytes().nth(self.read_position).unwrap();
+            self.column += 1;
         }
         self.position = self.read_position;
-        self.read_position += 1;
+        match self.input[self.read_position..].chars().next() {
+            Some(ch) => {
+                self.ch = ch;
+                self.read_position += ch.len_utf8();
+            }
+            None => self.ch = '\0',
+        }
     }
 
     pub fn next_token(&mut self) -> Token {
-        let tok: Token;
+        let mut trivia = Vec::new();
+        loop {
+            self.skip_whitespace(&mut trivia);
+            if self.ch != '/' || (self.peek_char() != '/' && self.peek_char() != '*') {
+                break;
+            }
+            let start = self.position;
+            let line = self.line;
+            let column = self.column;
+            let result = if self.peek_char() == '/' {
+                self.read_line_comment();
+                Ok(TriviaKind::LineComment)
+            } else {
+                self.read_block_comment().map(|_| TriviaKind::BlockComment)
+            };
+            let text = String::from(&self.input[start..self.position]);
+            let span = Span {
+                start: start,
+                end: self.position,
+                line: line,
+                column: column,
+            };
+            match result {
+                Ok(kind) => trivia.push(Trivia {
+                    kind: kind,
+                    text: text,
+                    span: span,
+                }),
+                Err(err) => {
+                    return Token {
+                        r#type: TokenType::ILLEGAL,
+                        literal: text,
+                        span: span,
+                        error: Some(err),
+                        trivia: trivia,
+                    }
+                }
+            }
+        }
+
+        let start = self.position;
+        let line = self.line;
+        let column = self.column;
+        let mut tok = self.read_token();
+        tok.span = Span {
+            start: start,
+            end: self.position,
+            line: line,
+            column: column,
+        };
+        tok.trivia = trivia;
+        tok
+    }
 
-        self.skip_whitespace();
+    fn read_token(&mut self) -> Token {
+        let tok: Token;
 
         match self.ch {
-            b':' => tok = new_token(TokenType::COLON, self.ch),
-            b'"' => {
-                tok = Token {
-                    r#type: TokenType::STRING,
-                    literal: String::from(self.read_string()),
+            ':' => tok = new_token(TokenType::COLON, self.ch),
+            '"' => {
+                let position = self.position;
+                tok = match self.read_string() {
+                    Ok(value) => Token {
+                        r#type: TokenType::STRING,
+                        literal: value,
+                        span: Span::default(),
+                        error: None,
+                        trivia: Vec::new(),
+                    },
+                    Err(err) => Token {
+                        r#type: TokenType::ILLEGAL,
+                        literal: String::from(&self.input[position..self.read_position]),
+                        span: Span::default(),
+                        error: Some(err),
+                        trivia: Vec::new(),
+                    },
                 }
             }
-            b'=' => {
-                if self.peek_char() == b'=' {
+            '=' => {
+                if self.peek_char() == '=' {
                     self.read_char();
                     tok = Token {
                         r#type: TokenType::EQ,
                         literal: String::from("=="),
+                        span: Span::default(),
+                        error: None,
+                        trivia: Vec::new(),
                     }
                 } else {
                     tok = new_token(TokenType::ASSIGN, self.ch)
                 }
             }
-            b';' => tok = new_token(TokenType::SEMICOLON, self.ch),
-            b'(' => tok = new_token(TokenType::LPAREN, self.ch),
-            b')' => tok = new_token(TokenType::RPAREN, self.ch),
-            b',' => tok = new_token(TokenType::COMMA, self.ch),
-            b'+' => tok = new_token(TokenType::PLUS, self.ch),
-            b'{' => tok = new_token(TokenType::LBRACE, self.ch),
-            b'}' => tok = new_token(TokenType::RBRACE, self.ch),
-            b'-' => tok = new_token(TokenType::MINUS, self.ch),
-            b'!' => {
-                if self.peek_char() == b'=' {
+            ';' => tok = new_token(TokenType::SEMICOLON, self.ch),
+            '(' => tok = new_token(TokenType::LPAREN, self.ch),
+            ')' => tok = new_token(TokenType::RPAREN, self.ch),
+            ',' => tok = new_token(TokenType::COMMA, self.ch),
+            '+' => tok = self.read_operator(TokenType::PLUS, TokenType::PLUSASSIGN),
+            '{' => tok = new_token(TokenType::LBRACE, self.ch),
+            '}' => tok = new_token(TokenType::RBRACE, self.ch),
+            '-' => tok = self.read_operator(TokenType::MINUS, TokenType::MINUSASSIGN),
+            '!' => {
+                if self.peek_char() == '=' {
                     self.read_char();
                     tok = Token {
                         r#type: TokenType::NOTEQ,
                         literal: String::from("!="),
+                        span: Span::default(),
+                        error: None,
+                        trivia: Vec::new(),
                     }
                 } else {
                     tok = new_token(TokenType::BANG, self.ch)
                 }
             }
-            b'/' => tok = new_token(TokenType::SLASH, self.ch),
-            b'*' => tok = new_token(TokenType::ASTERISK, self.ch),
-            b'<' => tok = new_token(TokenType::LT, self.ch),
-            b'>' => tok = new_token(TokenType::GT, self.ch),
-            b'[' => tok = new_token(TokenType::LBRACKET, self.ch),
-            b']' => tok = new_token(TokenType::RBRACKET, self.ch),
-            0 => {
+            '/' => tok = self.read_operator(TokenType::SLASH, TokenType::SLASHASSIGN),
+            '*' => tok = self.read_operator(TokenType::ASTERISK, TokenType::ASTERISKASSIGN),
+            '%' => tok = self.read_operator(TokenType::PERCENT, TokenType::PERCENTASSIGN),
+            '&' => tok = self.read_double(TokenType::AND),
+            '|' => tok = self.read_double(TokenType::OR),
+            '<' => tok = self.read_operator(TokenType::LT, TokenType::LTEQ),
+            '>' => tok = self.read_operator(TokenType::GT, TokenType::GTEQ),
+            '[' => tok = new_token(TokenType::LBRACKET, self.ch),
+            ']' => tok = new_token(TokenType::RBRACKET, self.ch),
+            '\0' => {
                 tok = Token {
                     r#type: TokenType::EOF,
                     literal: String::new(),
+                    span: Span::default(),
+                    error: None,
+                    trivia: Vec::new(),
                 }
             }
             _ => {
-                if is_letter(self.ch) {
+                if is_identifier_start(self.ch) {
                     let literal = self.read_identifier();
                     tok = Token {
                         r#type: lookup_ident(&literal),
                         literal: String::from(literal),
+                        span: Span::default(),
+                        error: None,
+                        trivia: Vec::new(),
                     };
                     return tok;
                 } else if self.ch.is_ascii_digit() {
+                    let (token_type, literal) = self.read_number();
                     tok = Token {
-                        r#type: TokenType::INT,
-                        literal: String::from(self.read_number()),
+                        r#type: token_type,
+                        literal: String::from(literal),
+                        span: Span::default(),
+                        error: None,
+                        trivia: Vec::new(),
                     };
                     return tok;
                 }
@@ -107,60 +204,263 @@ impl Lexer {
         tok
     }
 
+    // Reads a one-character operator, or its two-character form when it is
+    // followed by `=` (`+=`, `<=`, ...).
+    fn read_operator(&mut self, single: TokenType, with_assign: TokenType) -> Token {
+        if self.peek_char() == '=' {
+            let mut literal = String::new();
+            literal.push(self.ch);
+            self.read_char();
+            literal.push(self.ch);
+            Token {
+                r#type: with_assign,
+                literal: literal,
+                span: Span::default(),
+                error: None,
+                trivia: Vec::new(),
+            }
+        } else {
+            new_token(single, self.ch)
+        }
+    }
+
+    // Reads a two-character operator made of the same character twice, such
+    // as `&&`. A lone character is illegal.
+    fn read_double(&mut self, token_type: TokenType) -> Token {
+        if self.peek_char() == self.ch {
+            let mut literal = String::new();
+            literal.push(self.ch);
+            self.read_char();
+            literal.push(self.ch);
+            Token {
+                r#type: token_type,
+                literal: literal,
+                span: Span::default(),
+                error: None,
+                trivia: Vec::new(),
+            }
+        } else {
+            new_token(TokenType::ILLEGAL, self.ch)
+        }
+    }
+
     fn read_identifier(&mut self) -> &str {
         let position = self.position;
-        while is_letter(self.ch) {
+        while is_identifier_continue(self.ch) {
             self.read_char();
         }
         &self.input[position..self.position]
     }
 
-    fn skip_whitespace(&mut self) {
+    // Line breaks are kept as trivia so that a formatter can tell trailing
+    // comments from leading ones and preserve blank lines.
+    fn skip_whitespace(&mut self, trivia: &mut Vec<Trivia>) {
         loop {
             match self.ch {
-                b' ' | b'\t' | b'\n' | b'\r' => self.read_char(),
+                ' ' | '\t' | '\r' => self.read_char(),
+                '\n' => {
+                    trivia.push(Trivia {
+                        kind: TriviaKind::Newline,
+                        text: String::from("\n"),
+                        span: Span {
+                            start: self.position,
+                            end: self.position + 1,
+                            line: self.line,
+                            column: self.column,
+                        },
+                    });
+                    self.read_char();
+                }
                 _ => return,
             }
         }
     }
 
-    fn read_number(&mut self) -> &str {
-        let position = self.position;
-        while self.ch.is_ascii_digit() {
+    // Skips a `//` comment up to, but not including, the end of the line.
+    fn read_line_comment(&mut self) {
+        while self.ch != '\n' && self.ch != '\0' {
             self.read_char();
         }
-        &self.input[position..self.position]
     }
 
-    fn peek_char(&mut self) -> u8 {
-        if self.read_position >= self.input.len() {
-            return 0;
+    // Skips a `/* */` comment including its closing delimiter. Block comments
+    // do not nest, so an opening delimiter inside one is an error.
+    fn read_block_comment(&mut self) -> Result<(), LexError> {
+        self.read_char();
+        self.read_char();
+        let mut nested = false;
+        loop {
+            match (self.ch, self.peek_char()) {
+                ('\0', _) => return Err(LexError::UnterminatedComment),
+                ('*', '/') => {
+                    self.read_char();
+                    self.read_char();
+                    break;
+                }
+                ('/', '*') => {
+                    nested = true;
+                    self.read_char();
+                    self.read_char();
+                }
+                _ => self.read_char(),
+            }
+        }
+        if nested {
+            Err(LexError::NestedComment)
         } else {
-            return self.input.bytes().nth(self.read_position).unwrap();
+            Ok(())
+        }
+    }
+
+    // Reads an integer, or a float when the digits are followed by a
+    // fraction (`3.14`) and/or an exponent (`1e-9`).
+    fn read_number(&mut self) -> (TokenType, &str) {
+        let position = self.position;
+        let mut token_type = TokenType::INT;
+        self.read_digits();
+        if self.ch == '.' && self.peek_char().is_ascii_digit() {
+            token_type = TokenType::FLOAT;
+            self.read_char();
+            self.read_digits();
+        }
+        if self.ch == 'e' || self.ch == 'E' {
+            let sign = self.peek_char() == '+' || self.peek_char() == '-';
+            let digit = if sign {
+                self.peek_char_at(1)
+            } else {
+                self.peek_char()
+            };
+            if digit.is_ascii_digit() {
+                token_type = TokenType::FLOAT;
+                self.read_char();
+                if sign {
+                    self.read_char();
+                }
+                self.read_digits();
+            }
         }
+        (token_type, &self.input[position..self.position])
     }
 
-    fn read_string(&mut self) -> &str {
-        let position = self.position + 1;
+    fn read_digits(&mut self) {
+        while self.ch.is_ascii_digit() {
+            self.read_char();
+        }
+    }
+
+    fn peek_char(&mut self) -> char {
+        self.peek_char_at(0)
+    }
+
+    // Looks offset characters past the next one without consuming anything.
+    fn peek_char_at(&mut self, offset: usize) -> char {
+        self.input[self.read_position..]
+            .chars()
+            .nth(offset)
+            .unwrap_or('\0')
+    }
+
+    // Reads a string literal and decodes its escape sequences. The lexer is
+    // left on the closing quote, or at the end of input if there is none.
+    fn read_string(&mut self) -> Result<String, LexError> {
+        let mut value = String::new();
+        let mut error = None;
         loop {
             self.read_char();
-            if self.ch == b'"' {
-                break;
+            match self.ch {
+                '"' => break,
+                '\0' => return Err(LexError::UnterminatedString),
+                '\\' => match self.read_escape() {
+                    Ok(ch) => value.push(ch),
+                    Err(err) => {
+                        if error.is_none() {
+                            error = Some(err);
+                        }
+                    }
+                },
+                ch => value.push(ch),
             }
         }
-        &self.input[position..self.position]
+        match error {
+            Some(err) => Err(err),
+            None => Ok(value),
+        }
+    }
+
+    // Decodes the escape sequence starting at the backslash under the cursor
+    // and leaves the lexer on its last character.
+    fn read_escape(&mut self) -> Result<char, LexError> {
+        let start = self.position;
+        let ch = match self.peek_char() {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '"' => '"',
+            '\\' => '\\',
+            'u' => {
+                self.read_char();
+                return self.read_unicode_escape(start);
+            }
+            '\0' => return Err(LexError::UnterminatedString),
+            _ => {
+                let escape = self.input[start..].chars().take(2).collect();
+                self.read_char();
+                return Err(LexError::InvalidEscape(escape));
+            }
+        };
+        self.read_char();
+        Ok(ch)
+    }
+
+    // Reads the `{XXXX}` part of a `\u{XXXX}` escape with 1 to 6 hex digits.
+    fn read_unicode_escape(&mut self, start: usize) -> Result<char, LexError> {
+        if self.peek_char() != '{' {
+            return Err(LexError::InvalidEscape(String::from(
+                &self.input[start..self.read_position],
+            )));
+        }
+        self.read_char();
+        let digits_start = self.read_position;
+        while self.peek_char().is_ascii_hexdigit() {
+            self.read_char();
+        }
+        let digits_end = self.read_position;
+        if self.peek_char() != '}' {
+            return Err(LexError::InvalidEscape(String::from(
+                &self.input[start..self.read_position],
+            )));
+        }
+        self.read_char();
+        let digits = &self.input[digits_start..digits_end];
+        let escape = &self.input[start..self.read_position];
+        if digits.is_empty() || digits.len() > 6 {
+            return Err(LexError::InvalidEscape(String::from(escape)));
+        }
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(std::char::from_u32)
+            .ok_or_else(|| LexError::InvalidEscape(String::from(escape)))
     }
 }
 
-pub fn new_token(token_type: TokenType, ch: u8) -> Token {
+pub fn new_token(token_type: TokenType, ch: char) -> Token {
     let mut literal = String::new();
-    literal.push(ch as char);
+    literal.push(ch);
     Token {
         r#type: token_type,
         literal: literal,
+        span: Span::default(),
+        error: None,
+        trivia: Vec::new(),
     }
 }
 
-fn is_letter(ch: u8) -> bool {
-    ch.is_ascii_alphabetic() || ch == b'_'
+// Identifiers follow Unicode XID rules, with `_` also allowed at the start.
+fn is_identifier_start(ch: char) -> bool {
+    ch == '_' || UnicodeXID::is_xid_start(ch)
+}
+
+fn is_identifier_continue(ch: char) -> bool {
+    UnicodeXID::is_xid_continue(ch)
 }
@@ -1,15 +1,106 @@
 // src/main.rs
 
-mod ast;
-mod evaluator;
-mod lexer;
-mod object;
-mod parser;
-mod repl;
-mod token;
+use std::cell::*;
+use std::rc::*;
+use waiir::evaluator::*;
+use waiir::formatter::*;
+use waiir::lexer::*;
+use waiir::object::*;
+use waiir::parser::*;
+use waiir::repl;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 && args[1] == "fmt" {
+        std::process::exit(fmt_files(&args[2..]));
+    }
+    if args.len() > 1 {
+        std::process::exit(run_file(&args[1]));
+    }
+
     println!("Hello, This is the Monkey programming language!");
     println!("Feel free to type in commands");
     repl::start(&mut std::io::stdin(), &mut std::io::stdout());
 }
+
+// Runs a whole script file and returns the process exit code.
+fn run_file(path: &str) -> i32 {
+    let input = match std::fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            return 1;
+        }
+    };
+
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l);
+    match p.parse_program() {
+        Ok(program) => {
+            let env = Rc::new(RefCell::new(new_environment()));
+            match try_evaluate(program, Rc::clone(&env)) {
+                Ok(_) => 0,
+                Err(err) => {
+                    eprintln!("{}:{}", path, err);
+                    eprint!("{}", err.backtrace());
+                    1
+                }
+            }
+        }
+        Err(errors) => {
+            for msg in errors.iter() {
+                eprintln!("{}:{}", path, msg);
+            }
+            1
+        }
+    }
+}
+
+// Rewrites each file in the canonical style. With --check, files are left
+// untouched and the ones that are not formatted are listed instead.
+fn fmt_files(args: &[String]) -> i32 {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if paths.is_empty() {
+        eprintln!("usage: waiir fmt [--check] <file>...");
+        return 1;
+    }
+
+    let mut code = 0;
+    for path in paths {
+        let input = match std::fs::read_to_string(path) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                code = 1;
+                continue;
+            }
+        };
+        let output = match format_source(&input) {
+            Ok(output) => output,
+            Err(FormatError::Parse(errors)) => {
+                for msg in errors.iter() {
+                    eprintln!("{}:{}", path, msg);
+                }
+                code = 1;
+                continue;
+            }
+            Err(err) => {
+                eprintln!("{}:{}", path, err);
+                code = 1;
+                continue;
+            }
+        };
+        if output == input {
+            continue;
+        }
+        if check {
+            println!("{}", path);
+            code = 1;
+        } else if let Err(err) = std::fs::write(path, output) {
+            eprintln!("{}: {}", path, err);
+            code = 1;
+        }
+    }
+    code
+}
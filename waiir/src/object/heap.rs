@@ -0,0 +1,155 @@
+// src/object/heap.rs
+
+use super::environment::*;
+use super::object::*;
+use std::cell::*;
+use std::collections::*;
+use std::rc::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HeapStats {
+    pub environments: usize, // tracked environments still alive
+    pub bindings: usize,     // names bound in those environments
+    pub collections: usize,
+    pub freed: usize, // environments reclaimed by all collections so far
+}
+
+// Tracks the environments created under one global environment. A function
+// keeps the environment it was defined in alive, so a function stored in that
+// environment, or in one it encloses, forms a reference cycle that reference
+// counting never frees. collect finds the environments that only such cycles
+// keep alive and empties them, which lets reference counting do the rest.
+#[derive(Debug, Default)]
+pub struct Heap {
+    envs: RefCell<Vec<Weak<RefCell<Environment>>>>,
+    pruned_len: Cell<usize>, // length of envs after dead entries were last dropped
+    collections: Cell<usize>,
+    freed: Cell<usize>,
+}
+impl Heap {
+    pub fn new() -> Heap {
+        Heap::default()
+    }
+
+    pub fn track(&self, env: &Rc<RefCell<Environment>>) {
+        let mut envs = self.envs.borrow_mut();
+        // Most environments are freed as soon as their call returns.
+        if envs.len() >= 2 * self.pruned_len.get() + 64 {
+            envs.retain(|env| env.strong_count() > 0);
+            self.pruned_len.set(envs.len());
+        }
+        envs.push(Rc::downgrade(env));
+    }
+
+    // Returns the number of environments freed.
+    //
+    // An environment is in use if it has more strong references than other
+    // tracked environments account for, or if it is reachable from one that
+    // is. References this cannot see, such as those held by the evaluator or
+    // by native closures, only ever keep environments alive, so it is safe to
+    // collect in the middle of an evaluation.
+    pub fn collect(&self) -> usize {
+        let envs: Vec<Rc<RefCell<Environment>>> = self
+            .envs
+            .borrow()
+            .iter()
+            .filter_map(|env| env.upgrade())
+            .collect();
+        let index: HashMap<*const RefCell<Environment>, usize> = envs
+            .iter()
+            .enumerate()
+            .map(|(i, env)| (Rc::as_ptr(env), i))
+            .collect();
+
+        // Subtract the references tracked environments hold to each other.
+        // Whatever is left comes from outside, apart from the one in envs.
+        let mut external: Vec<usize> = envs.iter().map(|env| Rc::strong_count(env) - 1).collect();
+        let mut edges: Vec<Vec<usize>> = vec![Vec::new(); envs.len()];
+        for (i, env) in envs.iter().enumerate() {
+            // An environment being modified is in use anyway.
+            if let Ok(env) = env.try_borrow() {
+                env_references(&env, &mut |target| {
+                    if let Some(&j) = index.get(&Rc::as_ptr(target)) {
+                        external[j] -= 1;
+                        edges[i].push(j);
+                    }
+                });
+            }
+        }
+
+        let mut reachable = vec![false; envs.len()];
+        let mut work: Vec<usize> = (0..envs.len()).filter(|&i| external[i] > 0).collect();
+        while let Some(i) = work.pop() {
+            if !reachable[i] {
+                reachable[i] = true;
+                work.extend(edges[i].iter().cloned());
+            }
+        }
+
+        let mut freed = 0;
+        for (i, env) in envs.iter().enumerate() {
+            if reachable[i] {
+                continue;
+            }
+            // Move the contents out before dropping them, since that drops
+            // references to other environments.
+            let contents = match env.try_borrow_mut() {
+                Ok(mut env) => (std::mem::take(&mut env.store), env.outer.take()),
+                Err(_) => continue,
+            };
+            drop(contents);
+            freed += 1;
+        }
+        drop(envs);
+
+        self.envs.borrow_mut().retain(|env| env.strong_count() > 0);
+        self.pruned_len.set(self.envs.borrow().len());
+        self.collections.set(self.collections.get() + 1);
+        self.freed.set(self.freed.get() + freed);
+        freed
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        let mut stats = HeapStats {
+            environments: 0,
+            bindings: 0,
+            collections: self.collections.get(),
+            freed: self.freed.get(),
+        };
+        for env in self.envs.borrow().iter().filter_map(|env| env.upgrade()) {
+            stats.environments += 1;
+            if let Ok(env) = env.try_borrow() {
+                stats.bindings += env.store.len();
+            }
+        }
+        stats
+    }
+}
+
+// Calls f with each environment env refers to directly.
+fn env_references(env: &Environment, f: &mut dyn FnMut(&Rc<RefCell<Environment>>)) {
+    if let Some(outer) = &env.outer {
+        f(outer);
+    }
+    for value in env.store.values() {
+        object_references(value, f);
+    }
+}
+
+fn object_references(obj: &Object, f: &mut dyn FnMut(&Rc<RefCell<Environment>>)) {
+    match obj {
+        Object::Function(function) => f(&function.env),
+        Object::Array(Array { elements }) => {
+            for element in elements.iter() {
+                object_references(element, f);
+            }
+        }
+        Object::Hash(Hash { pairs }) => {
+            for value in pairs.values() {
+                object_references(value, f);
+            }
+        }
+        Object::ReturnValue(ReturnValue { value }) => object_references(value, f),
+        _ => {}
+    }
+}
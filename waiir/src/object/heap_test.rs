@@ -0,0 +1,95 @@
+// src/object/heap_test.rs
+
+use crate::evaluator::*;
+use crate::lexer::*;
+use crate::object::*;
+use crate::parser::*;
+use std::cell::*;
+use std::rc::*;
+
+fn eval(input: &str, env: &Rc<RefCell<Environment>>) -> Object {
+    let l = Lexer::new(String::from(input));
+    let mut p = Parser::new(l);
+    try_evaluate(p.parse_program().unwrap(), Rc::clone(env)).unwrap()
+}
+
+#[test]
+fn test_collect_cycles() {
+    let env = Rc::new(RefCell::new(new_environment()));
+    let heap = env.borrow().heap().unwrap();
+    eval(
+        "
+let counter = fn() { let n = 0; let next = fn() { n = n + 1; n }; next };
+counter();
+counter();
+let c = counter();
+c();
+let boxed = fn() { let box = [{\"f\": fn() { box }}]; 0 };
+boxed();
+",
+        &env,
+    );
+
+    let stats = heap.stats();
+    assert!(
+        stats.environments == 4 && stats.bindings == 7,
+        "wrong stats before collecting. got={:?}",
+        stats
+    );
+
+    let freed = heap.collect();
+    let stats = heap.stats();
+    assert!(
+        freed == 3,
+        "wrong number of freed environments. got={}",
+        freed
+    );
+    assert!(
+        stats
+            == HeapStats {
+                environments: 1,
+                bindings: 2,
+                collections: 1,
+                freed: 3,
+            },
+        "wrong stats after collecting. got={:?}",
+        stats
+    );
+
+    // The environment c still refers to survives with its state.
+    let result = eval("c()", &env);
+    assert!(
+        result == Object::Integer(Integer { value: 2 }),
+        "wrong result. got={:?}",
+        result
+    );
+    assert!(heap.collect() == 0, "freed an environment in use");
+}
+
+#[test]
+fn test_collect_during_evaluation() {
+    let env = Rc::new(RefCell::new(new_environment()));
+    let heap = env.borrow().heap().unwrap();
+    eval(
+        "let make = fn() { let f = fn() { f }; f }; let g = fn(x) { make(); x + 1 };",
+        &env,
+    );
+
+    // Collect from inside a call, while the evaluator still holds the
+    // environments of the calls in progress.
+    let collector = Rc::clone(&heap);
+    let mut builtins = new_builtins();
+    builtins.register("gc", Some(0), move |_| {
+        Ok(Object::Integer(Integer {
+            value: collector.collect() as i64,
+        }))
+    });
+    env.borrow_mut().builtins = Some(Rc::new(builtins));
+
+    let result = eval("let h = fn(x) { let y = g(x); gc() + y * 10 }; h(1)", &env);
+    assert!(
+        result == Object::Integer(Integer { value: 21 }),
+        "wrong result. got={:?}",
+        result
+    );
+}
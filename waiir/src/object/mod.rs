@@ -1,8 +1,14 @@
 mod environment;
+mod error;
+mod heap;
 mod object;
 
 pub use environment::*;
+pub use error::*;
+pub use heap::*;
 pub use object::*;
 
+#[cfg(test)]
+mod heap_test;
 #[cfg(test)]
 mod object_test;
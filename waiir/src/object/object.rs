@@ -17,9 +17,12 @@ pub trait ObjectTrait {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Object {
     Integer(Integer),
+    Float(Float),
     Boolean(Boolean),
     Null(Null),
     ReturnValue(ReturnValue),
+    Break(Break),
+    Continue(Continue),
     ErrorObj(ErrorObj),
     Function(Function),
     StringObj(StringObj),
@@ -31,9 +34,12 @@ impl ObjectTrait for Object {
     fn get_type(&self) -> &str {
         match self {
             Object::Integer(i) => i.get_type(),
+            Object::Float(f) => f.get_type(),
             Object::Boolean(b) => b.get_type(),
             Object::Null(n) => n.get_type(),
             Object::ReturnValue(rv) => rv.get_type(),
+            Object::Break(b) => b.get_type(),
+            Object::Continue(c) => c.get_type(),
             Object::ErrorObj(e) => e.get_type(),
             Object::Function(f) => f.get_type(),
             Object::StringObj(s) => s.get_type(),
@@ -45,9 +51,12 @@ impl ObjectTrait for Object {
     fn inspect(&self) -> String {
         match self {
             Object::Integer(i) => i.inspect(),
+            Object::Float(f) => f.inspect(),
             Object::Boolean(b) => b.inspect(),
             Object::Null(n) => n.inspect(),
             Object::ReturnValue(rv) => rv.inspect(),
+            Object::Break(b) => b.inspect(),
+            Object::Continue(c) => c.inspect(),
             Object::ErrorObj(e) => e.inspect(),
             Object::Function(f) => f.inspect(),
             Object::StringObj(s) => s.inspect(),
@@ -71,6 +80,22 @@ impl ObjectTrait for Integer {
     }
 }
 
+// Floats are compared with IEEE semantics, so NaN is not equal to itself.
+// They are deliberately not hashable.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Float {
+    pub value: f64,
+}
+impl Eq for Float {}
+impl ObjectTrait for Float {
+    fn get_type(&self) -> &str {
+        "FLOAT"
+    }
+    fn inspect(&self) -> String {
+        format!("{:?}", self.value)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Boolean {
     pub value: bool,
@@ -108,6 +133,28 @@ impl ObjectTrait for ReturnValue {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Break {}
+impl ObjectTrait for Break {
+    fn get_type(&self) -> &str {
+        "BREAK"
+    }
+    fn inspect(&self) -> String {
+        String::from("break")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Continue {}
+impl ObjectTrait for Continue {
+    fn get_type(&self) -> &str {
+        "CONTINUE"
+    }
+    fn inspect(&self) -> String {
+        String::from("continue")
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ErrorObj {
     pub message: String,
@@ -126,6 +173,7 @@ pub struct Function {
     pub parameters: Vec<Identifier>,
     pub body: BlockStatement,
     pub env: Rc<RefCell<Environment>>,
+    pub name: Option<String>, // set when the function is first bound by a let statement
 }
 impl ObjectTrait for Function {
     fn get_type(&self) -> &str {
@@ -171,11 +219,27 @@ impl ObjectTrait for StringObj {
     }
 }
 
-pub type BuiltinFunction = fn(&Vec<Object>) -> std::result::Result<Object, String>;
+pub type BuiltinFunction = Rc<dyn Fn(&[Object]) -> std::result::Result<Object, RuntimeError>>;
 
 pub struct Builtin {
+    pub name: String,
+    pub arity: Option<usize>, // None accepts any number of arguments
     pub func: BuiltinFunction,
 }
+impl Builtin {
+    pub fn call(&self, args: &[Object]) -> std::result::Result<Object, RuntimeError> {
+        if let Some(arity) = self.arity {
+            if args.len() != arity {
+                return Err(RuntimeErrorKind::WrongArgumentCount {
+                    want: arity,
+                    got: args.len(),
+                }
+                .into());
+            }
+        }
+        (self.func)(args)
+    }
+}
 impl ObjectTrait for Builtin {
     fn get_type(&self) -> &str {
         "BUILTIN"
@@ -186,7 +250,7 @@ impl ObjectTrait for Builtin {
 }
 impl std::fmt::Debug for Builtin {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Builtin")
+        write!(f, "Builtin({})", self.name)
     }
 }
 impl PartialEq for Builtin {
@@ -197,7 +261,89 @@ impl PartialEq for Builtin {
 impl Eq for Builtin {}
 impl Clone for Builtin {
     fn clone(&self) -> Self {
-        Builtin { func: self.func }
+        Builtin {
+            name: self.name.clone(),
+            arity: self.arity,
+            func: Rc::clone(&self.func),
+        }
+    }
+}
+
+// How integer arithmetic behaves when the result does not fit in an i64.
+// Checked arithmetic reports an error, wrapping arithmetic wraps around in
+// two's complement. Division by zero is an error either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arithmetic {
+    Checked,
+    Wrapping,
+}
+impl Default for Arithmetic {
+    fn default() -> Self {
+        Arithmetic::Checked
+    }
+}
+
+// Named native functions that scripts can call. Host applications register
+// their own entries next to the standard ones.
+#[derive(Clone, Default)]
+pub struct Builtins {
+    entries: Vec<Builtin>,
+}
+impl Builtins {
+    pub fn new() -> Builtins {
+        Builtins {
+            entries: Vec::new(),
+        }
+    }
+
+    // Adds func under name, replacing an earlier entry with the same name.
+    // Returns the index of the entry.
+    pub fn register<F>(&mut self, name: &str, arity: Option<usize>, func: F) -> usize
+    where
+        F: Fn(&[Object]) -> std::result::Result<Object, RuntimeError> + 'static,
+    {
+        let builtin = Builtin {
+            name: String::from(name),
+            arity: arity,
+            func: Rc::new(func),
+        };
+        match self.entries.iter().position(|x| x.name == name) {
+            Some(index) => {
+                self.entries[index] = builtin;
+                index
+            }
+            None => {
+                self.entries.push(builtin);
+                self.entries.len() - 1
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Builtin> {
+        self.entries.iter().find(|x| x.name == name)
+    }
+
+    pub fn get_by_index(&self, index: usize) -> Option<&Builtin> {
+        self.entries.get(index)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Builtin> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+impl std::fmt::Debug for Builtins {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.entries.iter().map(|x| &x.name))
+            .finish()
     }
 }
 
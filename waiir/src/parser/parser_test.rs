@@ -3,12 +3,13 @@
 use crate::ast::*;
 use crate::lexer::*;
 use crate::parser::*;
+use crate::token::*;
 use std::collections::*;
 
-fn panic_with_errors(errors: Vec<String>) {
+fn panic_with_errors(errors: Vec<ParseError>) {
     let mut messages = Vec::new();
     for msg in errors.into_iter() {
-        messages.push(msg);
+        messages.push(msg.to_string());
     }
     panic!("parser error: {}", messages.join("\n"));
 }
@@ -23,7 +24,7 @@ let foobar = 838383;
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             assert!(
                 statements.len() == 3,
                 "program.statements does not contain 3 statements. got={}",
@@ -80,7 +81,7 @@ return 993322;
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             assert!(
                 statements.len() == 3,
                 "program.statements does not contain 3 statements. got={}",
@@ -109,7 +110,7 @@ fn test_identifier_expression() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             assert!(
                 statements.len() == 1,
                 "program has not enough statements. got={}",
@@ -154,7 +155,7 @@ fn test_integer_literal_expression() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             assert!(
                 statements.len() == 1,
                 "program has not enough statements. got={}",
@@ -188,6 +189,49 @@ fn test_integer_literal_expression() {
     }
 }
 
+#[test]
+fn test_float_literal_expression() {
+    let tests = [("2.5;", 2.5, "2.5"), ("1e-9", 1e-9, "1e-9")];
+
+    for tt in tests.iter() {
+        let l = Lexer::new(String::from(tt.0));
+        let mut p = Parser::new(l);
+        match p.parse_program() {
+            Ok(Program { statements, .. }) => {
+                assert!(
+                    statements.len() == 1,
+                    "program has not enough statements. got={}",
+                    statements.len()
+                );
+
+                if let Statement::ExpressionStatement(ExpressionStatement {
+                    token: _,
+                    expression,
+                }) = &statements[0]
+                {
+                    if let Expression::FloatLiteral(FloatLiteral { token, value }) = expression {
+                        assert!(*value == tt.1, "literal.value not {}. got={}", tt.1, value);
+                        assert!(
+                            token.literal == tt.2,
+                            "literal.token_literal not {}. got={}",
+                            tt.2,
+                            token.literal
+                        );
+                    } else {
+                        panic!("exp not FloatLiteral. got={:?}", expression);
+                    }
+                } else {
+                    panic!(
+                        "program.statements[0] is not ExpressionStatement. got={:?}",
+                        &statements[0]
+                    );
+                }
+            }
+            Err(errors) => panic_with_errors(errors),
+        }
+    }
+}
+
 enum ExpectedType {
     Ival(i64),
     Sval(String),
@@ -212,7 +256,7 @@ fn test_parsing_prefix_expression() {
         let l = Lexer::new(String::from(tt.0));
         let mut p = Parser::new(l);
         match p.parse_program() {
-            Ok(Program { statements }) => {
+            Ok(Program { statements, .. }) => {
                 assert!(
                     statements.len() == 1,
                     "program.statements does not contain {} statements. got={}",
@@ -319,7 +363,7 @@ fn test_parsing_infix_expressions() {
         let l = Lexer::new(String::from(tt.0));
         let mut p = Parser::new(l);
         match p.parse_program() {
-            Ok(Program { statements }) => {
+            Ok(Program { statements, .. }) => {
                 assert!(
                     statements.len() == 1,
                     "program.statements does not contain {} statements. got={}",
@@ -407,6 +451,16 @@ fn test_operator_precedence_parsing() {
             "add(a * b[2], b[1], 2 * [1, 2][1])",
             "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))",
         ),
+        ("a = b + c", "(a = (b + c))"),
+        ("a = b = c", "(a = (b = c))"),
+        ("a += b * c", "(a += (b * c))"),
+        ("a[i][j] -= f(x)", "(((a[i])[j]) -= f(x))"),
+        ("h[k] *= 2; h /= 2", "((h[k]) *= 2)(h /= 2)"),
+        ("a || b && c", "(a || (b && c))"),
+        ("a && b || c && d", "((a && b) || (c && d))"),
+        ("a == b && c < d", "((a == b) && (c < d))"),
+        ("x = a || b", "(x = (a || b))"),
+        ("a + 1 <= b == c >= d", "(((a + 1) <= b) == (c >= d))"),
     ];
 
     for tt in tests.iter() {
@@ -501,7 +555,7 @@ fn test_if_expression() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             assert!(
                 statements.len() == 1,
                 "program.body does not contain {} statements. got={}",
@@ -573,7 +627,7 @@ fn test_if_else_expression() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             assert!(
                 statements.len() == 1,
                 "program.body does not contain {} statements. got={}",
@@ -654,13 +708,66 @@ fn test_if_else_expression() {
     }
 }
 
+#[test]
+fn test_while_statement() {
+    let input = "while (x < y) { if (x) { break; } continue; x }";
+    let l = Lexer::new(String::from(input));
+    let mut p = Parser::new(l);
+    match p.parse_program() {
+        Ok(Program { statements, .. }) => {
+            assert!(
+                statements.len() == 1,
+                "program.body does not contain {} statements. got={}",
+                1,
+                statements.len()
+            );
+
+            if let Statement::WhileStatement(WhileStatement {
+                token: _,
+                condition,
+                body,
+            }) = &statements[0]
+            {
+                test_infix_expression(
+                    condition,
+                    &ExpectedType::from("x"),
+                    "<",
+                    &ExpectedType::from("y"),
+                );
+
+                assert!(
+                    body.statements.len() == 3,
+                    "body is not 3 statements. got={}",
+                    body.statements.len()
+                );
+                assert!(
+                    matches!(body.statements[1], Statement::ContinueStatement(_)),
+                    "body.statements[1] is not ContinueStatement. got={:?}",
+                    body.statements[1]
+                );
+                assert!(
+                    statements[0].string() == "while(x < y) ifx break;continue;x",
+                    "wrong string. got={}",
+                    statements[0].string()
+                );
+            } else {
+                panic!(
+                    "program.statements[0] is not WhileStatement. got={:?}",
+                    &statements[0]
+                );
+            }
+        }
+        Err(errors) => panic_with_errors(errors),
+    }
+}
+
 #[test]
 fn test_function_literal_parsing() {
     let input = "fn(x, y) { x + y; }";
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             assert!(
                 statements.len() == 1,
                 "program.body does not contain {} statements. got={}",
@@ -746,7 +853,7 @@ fn test_function_parameter_parsing() {
         let l = Lexer::new(String::from(tt.0));
         let mut p = Parser::new(l);
         match p.parse_program() {
-            Ok(Program { statements }) => {
+            Ok(Program { statements, .. }) => {
                 if let Statement::ExpressionStatement(ExpressionStatement {
                     token: _,
                     expression,
@@ -789,7 +896,7 @@ fn test_call_expression_parsing() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             assert!(
                 statements.len() == 1,
                 "program.statements does not contain {} statements. got={}",
@@ -849,7 +956,7 @@ fn test_string_literal_expression() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             if let Statement::ExpressionStatement(ExpressionStatement {
                 token: _,
                 expression,
@@ -879,7 +986,7 @@ fn test_parsing_array_literals() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             if let Statement::ExpressionStatement(ExpressionStatement {
                 token: _,
                 expression,
@@ -921,7 +1028,7 @@ fn test_parsing_index_expressions() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             if let Statement::ExpressionStatement(ExpressionStatement {
                 token: _,
                 expression,
@@ -957,7 +1064,7 @@ fn test_parsing_hash_literals_string_keys() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             if let Statement::ExpressionStatement(ExpressionStatement {
                 token: _,
                 expression,
@@ -998,7 +1105,7 @@ fn test_parsing_empty_hash_literal() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             if let Statement::ExpressionStatement(ExpressionStatement {
                 token: _,
                 expression,
@@ -1027,7 +1134,7 @@ fn test_parsing_hash_literal_with_expressions() {
     let l = Lexer::new(String::from(input));
     let mut p = Parser::new(l);
     match p.parse_program() {
-        Ok(Program { statements }) => {
+        Ok(Program { statements, .. }) => {
             if let Statement::ExpressionStatement(ExpressionStatement {
                 token: _,
                 expression,
@@ -1087,3 +1194,119 @@ fn test_parsing_hash_literal_with_expressions() {
         Err(errors) => panic_with_errors(errors),
     }
 }
+
+#[test]
+fn test_parser_error_positions() {
+    let tests = [
+        (
+            "let = 5;",
+            "1:5: expected next token to be IDENT, got ASSIGN instead",
+        ),
+        (
+            "let x = 1;\nlet y 2;",
+            "2:7: expected next token to be ASSIGN, got INT instead",
+        ),
+        ("1 + ;", "1:5: no prefix parse function for SEMICOLON found"),
+        ("break;", "1:1: break outside loop"),
+        ("1 + a = 2", "1:7: invalid assignment target: (1 + a)"),
+        ("f()[0] += 1", "1:8: invalid assignment target: (f()[0])"),
+        (
+            "while (true) { fn() { continue; } }",
+            "1:23: continue outside loop",
+        ),
+        (
+            "while (true) { s = s + (if (c) { continue; } else { 1 }); }",
+            "1:34: continue inside expression",
+        ),
+        (
+            "while (true) { let x = if (c) { break; } else { 1 }; }",
+            "1:33: break inside expression",
+        ),
+        (
+            "while (true) { if (c) { if (d) { break; } } + 1; }",
+            "1:34: break inside expression",
+        ),
+        (
+            "let s = \"abc;\nlet t = 1;",
+            "1:9: unterminated string literal",
+        ),
+        ("puts(\"a\\qb\")", "1:6: invalid escape sequence: \\q"),
+        (
+            "let \"x\\u{110000}\" = 1",
+            "1:5: invalid escape sequence: \\u{110000}",
+        ),
+        ("let x = 1;\n/* no end", "2:1: unterminated block comment"),
+        ("let x = /* a /* b */ 1;", "1:9: nested block comment"),
+    ];
+
+    for tt in tests.iter() {
+        let l = Lexer::new(String::from(tt.0));
+        let mut p = Parser::new(l);
+        match p.parse_program() {
+            Ok(program) => panic!("expected parser errors. got={}", program.string()),
+            Err(errors) => assert!(
+                errors[0].to_string() == tt.1,
+                "wrong error. expected={:?}, got={}",
+                tt.1,
+                errors[0]
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_parser_error_kinds() {
+    let l = Lexer::new(String::from("add(1, 2"));
+    let mut p = Parser::new(l);
+    match p.parse_program() {
+        Ok(program) => panic!("expected parser errors. got={}", program.string()),
+        Err(errors) => {
+            let expected = ParseErrorKind::UnexpectedToken {
+                expected: TokenType::RPAREN,
+                found: TokenType::EOF,
+            };
+            assert!(
+                errors[0].kind == expected,
+                "wrong error kind. expected={:?}, got={:?}",
+                expected,
+                errors[0].kind
+            );
+        }
+    }
+}
+
+#[test]
+fn test_parser_error_recovery() {
+    let input = "let = 5;
+let x = 10;
+let y 2;
+fn(a b) { a; b };
+let f = fn() { 1 + ; let z = 3; z };
+x + }
+x;";
+    let expected_errors = [
+        "1:5: expected next token to be IDENT, got ASSIGN instead",
+        "3:7: expected next token to be ASSIGN, got INT instead",
+        "4:6: expected next token to be RPAREN, got IDENT instead",
+        "5:20: no prefix parse function for SEMICOLON found",
+        "6:5: no prefix parse function for RBRACE found",
+    ];
+
+    let l = Lexer::new(String::from(input));
+    let mut p = Parser::new(l);
+    let (program, errors) = p.parse_program_with_errors();
+    let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+    assert!(
+        errors == expected_errors,
+        "wrong errors. expected={:?}, got={:?}",
+        expected_errors,
+        errors
+    );
+    let expected_program = "let x = 10;let f = fn () let z = 3;z;x";
+    assert!(
+        program.string() == expected_program,
+        "wrong partial program. expected={:?}, got={:?}",
+        expected_program,
+        program.string()
+    );
+}
@@ -18,8 +18,9 @@ pub fn start(input: &mut dyn Read, output: &mut dyn Write) {
         write!(output, "{}", PROMPT).unwrap();
         output.flush().unwrap();
         let mut line = String::new();
-        if scanner.read_line(&mut line).is_err() {
-            return;
+        match scanner.read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
         }
         let l = Lexer::new(line);
         let mut p = Parser::new(l);
@@ -49,7 +50,7 @@ const MONKEY_FACE: &str = r#"
            '-----'
 "#;
 
-fn print_parser_errors(output: &mut dyn Write, errors: &[String]) {
+fn print_parser_errors(output: &mut dyn Write, errors: &[ParseError]) {
     writeln!(output, "{}", MONKEY_FACE).unwrap();
     writeln!(output, "Woops! We ran into some monkey business here!").unwrap();
     writeln!(output, " parser errros:").unwrap();
@@ -4,41 +4,109 @@
 pub struct Token {
     pub r#type: TokenType,
     pub literal: String,
+    pub span: Span,
+    pub error: Option<LexError>, // why the token is ILLEGAL, if known
+    pub trivia: Vec<Trivia>,     // comments and line breaks before this token
+}
+
+// Source text that does not affect parsing but is kept so that tools such as
+// a formatter can reproduce it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String, // including the comment delimiters
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    LineComment,  // `// ...` up to the end of the line
+    BlockComment, // `/* ... */`
+    Newline,      // a line break outside of comments and strings
+}
+
+// Problems the lexer detects inside a token. The token is turned into an
+// ILLEGAL one and the parser reports the error at its position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    UnterminatedString,
+    InvalidEscape(String),
+    UnterminatedComment,
+    NestedComment,
+}
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexError::UnterminatedString => write!(f, "unterminated string literal"),
+            LexError::InvalidEscape(escape) => write!(f, "invalid escape sequence: {}", escape),
+            LexError::UnterminatedComment => write!(f, "unterminated block comment"),
+            LexError::NestedComment => write!(f, "nested block comment"),
+        }
+    }
+}
+
+// Location of a token in the source text. Offsets are in bytes, line and
+// column are 1-based and the column counts characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
 }
 
 #[derive(PartialEq, Debug, Clone, Hash, Eq)]
 pub enum TokenType {
-    ILLEGAL,   // unknown character
-    EOF,       // end of file
-    IDENT,     // identifier
-    INT,       // integer
-    ASSIGN,    // =
-    PLUS,      // +
-    COMMA,     // ,
-    SEMICOLON, // ;
-    LPAREN,    // (
-    RPAREN,    // )
-    LBRACE,    // {
-    RBRACE,    // }
-    FUNCTION,  // function
-    LET,       // let
-    MINUS,     // -
-    BANG,      // !
-    ASTERISK,  // *
-    SLASH,     // /
-    LT,        // <
-    GT,        // >
-    TRUE,      // true
-    FALSE,     // false
-    IF,        // if
-    ELSE,      // else
-    RETURN,    // return
-    EQ,        // ==
-    NOTEQ,     // !=
-    STRING,    // string
-    LBRACKET,  // [
-    RBRACKET,  // ]
-    COLON,     // :
+    ILLEGAL,        // unknown character
+    EOF,            // end of file
+    IDENT,          // identifier
+    INT,            // integer
+    FLOAT,          // float
+    ASSIGN,         // =
+    PLUS,           // +
+    COMMA,          // ,
+    SEMICOLON,      // ;
+    LPAREN,         // (
+    RPAREN,         // )
+    LBRACE,         // {
+    RBRACE,         // }
+    FUNCTION,       // function
+    LET,            // let
+    MINUS,          // -
+    BANG,           // !
+    ASTERISK,       // *
+    SLASH,          // /
+    PERCENT,        // %
+    LT,             // <
+    GT,             // >
+    LTEQ,           // <=
+    GTEQ,           // >=
+    TRUE,           // true
+    FALSE,          // false
+    IF,             // if
+    ELSE,           // else
+    RETURN,         // return
+    EQ,             // ==
+    NOTEQ,          // !=
+    STRING,         // string
+    LBRACKET,       // [
+    RBRACKET,       // ]
+    COLON,          // :
+    WHILE,          // while
+    BREAK,          // break
+    CONTINUE,       // continue
+    PLUSASSIGN,     // +=
+    MINUSASSIGN,    // -=
+    ASTERISKASSIGN, // *=
+    SLASHASSIGN,    // /=
+    PERCENTASSIGN,  // %=
+    AND,            // &&
+    OR,             // ||
 }
 
 pub fn lookup_ident(ident: &str) -> TokenType {
@@ -50,6 +118,9 @@ pub fn lookup_ident(ident: &str) -> TokenType {
         "if" => TokenType::IF,
         "else" => TokenType::ELSE,
         "return" => TokenType::RETURN,
+        "while" => TokenType::WHILE,
+        "break" => TokenType::BREAK,
+        "continue" => TokenType::CONTINUE,
         _ => TokenType::IDENT,
     }
 }